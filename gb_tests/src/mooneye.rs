@@ -0,0 +1,203 @@
+use gbemulator::system::boot::GameBoyModel;
+use gbemulator::system::ram::mapping_chip::DynamicMappingChip;
+use gbemulator::system::sm83::registers::RegisterName;
+use gbemulator::system::{System, M_CYCLES_PER_FRAME};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+// the opcode mooneye ROMs execute once they are done
+const LD_B_B: u16 = 0x40;
+// B, C, D, E, H and L when the test passed
+const FIBONACCI: [u16; 6] = [3, 5, 8, 13, 21, 34];
+// 20 seconds of emulation, the slowest acceptance tests finish in a few
+const DEFAULT_MAX_CYCLES: u128 = M_CYCLES_PER_FRAME * 60 * 20;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum MooneyeResult {
+    Passed,
+    Failed,
+    Timeout,
+    // the ROM targets another model than the emulated DMG
+    Skipped,
+    Error(String),
+}
+
+pub struct MooneyeOutcome {
+    pub name: String,
+    pub result: MooneyeResult,
+    pub cycles: u128,
+}
+
+// Mooneye ROMs name the models they run on after the last dash: "dmgABC", "mgb", "cgb"... or
+// letters, G for the DMG, S for the SGB, C for the CGB and A for the AGB.
+fn targets_dmg(name: &str) -> bool {
+    let stem = name.trim_end_matches(".gb");
+    let models = match stem.rsplit_once('-') {
+        Some((_, models)) => models,
+        None => return true,
+    };
+    if models.contains("dmgABC") {
+        return true;
+    }
+    if !models.is_empty() && models.chars().all(|c| "GSCA".contains(c)) {
+        return models.contains('G');
+    }
+    !matches!(
+        models,
+        "dmg0" | "mgb" | "sgb" | "sgb2" | "cgb" | "cgb0" | "agb" | "ags"
+    )
+}
+
+// boots the ROM headless from the post boot state and runs it until it executes LD B,B
+fn run_until_ld_b_b(path: &Path, max_cycles: u128) -> (MooneyeResult, u128) {
+    let chip = match DynamicMappingChip::from_rom_path(path.to_str().unwrap()) {
+        Ok(chip) => chip,
+        Err(e) => return (MooneyeResult::Error(e), 0),
+    };
+    let mut system = System::new(Some(chip), true).with_skip_boot(GameBoyModel::DMG);
+    system.boot();
+    let start_cycle = system.cycle_count();
+    loop {
+        let cycles = system.cycle_count() - start_cycle;
+        // IR holds the opcode about to be executed
+        if system.get_register(RegisterName::IR) == LD_B_B {
+            let registers = [
+                RegisterName::B,
+                RegisterName::C,
+                RegisterName::D,
+                RegisterName::E,
+                RegisterName::H,
+                RegisterName::L,
+            ]
+            .map(|register| system.get_register(register));
+            let result = if registers == FIBONACCI {
+                MooneyeResult::Passed
+            } else {
+                MooneyeResult::Failed
+            };
+            return (result, cycles);
+        }
+        if cycles >= max_cycles {
+            return (MooneyeResult::Timeout, cycles);
+        }
+        system.step_instruction();
+    }
+}
+
+// a panic in the emulator is an error for that ROM instead of the end of the whole run
+pub fn run_rom(path: &Path, max_cycles: u128) -> (MooneyeResult, u128) {
+    match panic::catch_unwind(AssertUnwindSafe(|| run_until_ld_b_b(path, max_cycles))) {
+        Ok(outcome) => outcome,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            (MooneyeResult::Error(format!("panicked: {}", message)), 0)
+        }
+    }
+}
+
+fn find_roms(directory: &Path, roms: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(..) => panic!("unable to find files in path: {}", directory.display()),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_roms(&path, roms);
+        } else if path.extension().is_some_and(|extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+}
+
+pub fn run_suite(directory: &Path, max_cycles: u128) -> Vec<MooneyeOutcome> {
+    let mut roms = Vec::new();
+    find_roms(directory, &mut roms);
+    roms.sort();
+    let mut outcomes = Vec::new();
+    for path in roms {
+        let name = path
+            .strip_prefix(directory)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let (result, cycles) = if targets_dmg(file_name) {
+            run_rom(&path, max_cycles)
+        } else {
+            (MooneyeResult::Skipped, 0)
+        };
+        println!("{}: {:?}", name, result);
+        outcomes.push(MooneyeOutcome {
+            name,
+            result,
+            cycles,
+        });
+    }
+    outcomes
+}
+
+pub fn print_table(outcomes: &[MooneyeOutcome]) {
+    let width = outcomes
+        .iter()
+        .map(|outcome| outcome.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("test".len());
+    println!("{:<width$}  {:<8}  {:>12}", "test", "result", "M-cycles");
+    println!("{}", "-".repeat(width + 24));
+    for outcome in outcomes {
+        let result = match &outcome.result {
+            MooneyeResult::Passed => "pass".to_string(),
+            MooneyeResult::Failed => "FAIL".to_string(),
+            MooneyeResult::Timeout => "timeout".to_string(),
+            MooneyeResult::Skipped => "skipped".to_string(),
+            MooneyeResult::Error(e) => format!("error: {}", e),
+        };
+        println!(
+            "{:<width$}  {:<8}  {:>12}",
+            outcome.name, result, outcome.cycles
+        );
+    }
+    let count = |result: MooneyeResult| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.result == result)
+            .count()
+    };
+    let skipped = count(MooneyeResult::Skipped);
+    println!(
+        "{}/{} passed, {} failed, {} timed out, {} skipped",
+        count(MooneyeResult::Passed),
+        outcomes.len() - skipped,
+        count(MooneyeResult::Failed),
+        count(MooneyeResult::Timeout),
+        skipped
+    );
+}
+
+// usage: gb_tests mooneye <directory> [--max-cycles n]
+pub fn main(args: &[String]) {
+    let mut directory = None;
+    let mut max_cycles = DEFAULT_MAX_CYCLES;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-cycles" => {
+                max_cycles = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--max-cycles needs a number of M-cycles")
+            }
+            _ => directory = Some(arg.clone()),
+        }
+    }
+    let directory = directory.unwrap_or("./mooneye/acceptance".to_string());
+    let outcomes = run_suite(Path::new(&directory), max_cycles);
+    print_table(&outcomes);
+}
@@ -0,0 +1,150 @@
+use json::{array, object, JsonValue};
+
+// the outcome of the test cases of one JSON file, which all test the same opcode
+pub struct FileResult {
+    pub opcode: String,
+    pub path: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    // name and diff of the first failing cases
+    pub failures: Vec<(String, String)>,
+    // the file could not be read
+    pub error: Option<String>,
+}
+
+impl FileResult {
+    pub fn new(opcode: String, path: String) -> Self {
+        FileResult {
+            opcode,
+            path,
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            failures: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn is_passed(&self) -> bool {
+        self.failed == 0 && self.error.is_none()
+    }
+}
+
+pub fn print_failures(results: &[FileResult]) {
+    for result in results {
+        if let Some(error) = &result.error {
+            println!("\n{}: {}", result.opcode, error);
+        }
+        for (name, diff) in &result.failures {
+            println!("\n{} failed:\n{}", name, diff);
+        }
+        if result.failed > result.failures.len() {
+            println!(
+                "... and {} more failing {} cases",
+                result.failed - result.failures.len(),
+                result.opcode
+            );
+        }
+    }
+}
+
+pub fn print_summary(results: &[FileResult]) {
+    println!("\nopcode   passed  failed  skipped");
+    for result in results {
+        let marker = if result.is_passed() { "" } else { "  <-" };
+        println!(
+            "{:<7}  {:>6}  {:>6}  {:>7}{}",
+            result.opcode, result.passed, result.failed, result.skipped, marker
+        );
+    }
+    let failing = results.iter().filter(|result| !result.is_passed()).count();
+    println!(
+        "{}/{} opcodes passed, {} cases passed, {} failed",
+        results.len() - failing,
+        results.len(),
+        results.iter().map(|result| result.passed).sum::<usize>(),
+        results.iter().map(|result| result.failed).sum::<usize>()
+    );
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A test case per opcode, so that CI lists the opcodes that regressed. The failure holds the
+// diffs of the first failing cases.
+pub fn to_junit(results: &[FileResult]) -> String {
+    let failing = results.iter().filter(|result| !result.is_passed()).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml += format!(
+        "<testsuites>\n  <testsuite name=\"sm83\" tests=\"{}\" failures=\"{}\">\n",
+        results.len(),
+        failing
+    )
+    .as_str();
+    for result in results {
+        xml += format!(
+            "    <testcase classname=\"sm83\" name=\"{}\"",
+            escape_xml(&result.opcode)
+        )
+        .as_str();
+        if result.is_passed() {
+            xml += "/>\n";
+            continue;
+        }
+        let message = match &result.error {
+            Some(error) => error.clone(),
+            None => format!(
+                "{} of {} cases failed",
+                result.failed,
+                result.passed + result.failed
+            ),
+        };
+        let mut details = String::new();
+        for (name, diff) in &result.failures {
+            details += format!("{}:\n{}\n", name, diff).as_str();
+        }
+        xml += format!(
+            ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+            escape_xml(&message),
+            escape_xml(&details)
+        )
+        .as_str();
+    }
+    xml += "  </testsuite>\n</testsuites>\n";
+    xml
+}
+
+pub fn to_json(results: &[FileResult]) -> String {
+    let mut opcodes = array![];
+    for result in results {
+        let mut failures = array![];
+        for (name, diff) in &result.failures {
+            failures
+                .push(object! { "name": name.as_str(), "diff": diff.as_str() })
+                .unwrap();
+        }
+        let mut entry = object! {
+            "opcode": result.opcode.as_str(),
+            "path": result.path.as_str(),
+            "passed": result.passed,
+            "failed": result.failed,
+            "skipped": result.skipped,
+            "failures": failures,
+        };
+        if let Some(error) = &result.error {
+            entry["error"] = JsonValue::from(error.as_str());
+        }
+        opcodes.push(entry).unwrap();
+    }
+    let report = object! {
+        "passed": results.iter().map(|result| result.passed).sum::<usize>(),
+        "failed": results.iter().map(|result| result.failed).sum::<usize>(),
+        "opcodes": opcodes,
+    };
+    report.pretty(2)
+}
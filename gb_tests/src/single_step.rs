@@ -0,0 +1,402 @@
+use crate::report::{self, FileResult};
+use gbemulator::system::ram::RAM;
+use gbemulator::system::sm83::bus_trace::{compare_bus_traces, BusCycle};
+use gbemulator::system::sm83::snapshot::SM83Snapshot;
+use gbemulator::system::System;
+use json::{self, JsonValue};
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_TESTS_PATH: &str = "./sm83/v1/";
+const DEFAULT_PROGRESS_PATH: &str = "./sm83/succesful_tests.txt";
+// failing cases kept with their diff for each file, the others are only counted
+const MAX_REPORTED_FAILURES: usize = 5;
+
+pub enum CaseResult {
+    Passed,
+    Failed(String),
+    Skipped,
+}
+
+fn read_u8_value(json_state: &JsonValue, key: &str) -> u8 {
+    if json_state.has_key(key) {
+        json_state[key].as_u8().unwrap()
+    } else {
+        0
+    }
+}
+
+fn read_u16_value(json_state: &JsonValue, key: &str) -> u16 {
+    if json_state.has_key(key) {
+        json_state[key].as_u16().unwrap()
+    } else {
+        0
+    }
+}
+
+fn read_bool_value(json_state: &JsonValue, key: &str) -> bool {
+    if json_state.has_key(key) {
+        json_state[key].as_u8().unwrap() > 0
+    } else {
+        false
+    }
+}
+
+fn fill_ram(json_ram: &JsonValue, ram: &mut RAM) {
+    for json_ram_item in json_ram.members() {
+        let address = json_ram_item[0].as_u16().unwrap();
+        let value = json_ram_item[1].as_u8().unwrap();
+        ram.set_at(address, value);
+    }
+}
+
+// one line per differing address
+fn check_ram(json_ram: &JsonValue, ram: &RAM) -> Result<(), String> {
+    let mut result = String::new();
+    for json_ram_item in json_ram.members() {
+        let address = json_ram_item[0].as_u16().unwrap();
+        let value = json_ram_item[1].as_u8().unwrap();
+        let actual_value = ram.get_at(address).unwrap().to_owned();
+        if value != actual_value {
+            result += format!(
+                "  {:04x}      {:02x}        {:02x}\n",
+                address, value, actual_value
+            )
+            .as_str();
+        }
+    }
+    if result.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("  address   expected  actual\n{}", result))
+    }
+}
+
+fn format_flags(f: u8) -> String {
+    ['Z', 'N', 'H', 'C']
+        .iter()
+        .enumerate()
+        .map(|(i, flag)| if f & (0x80 >> i) != 0 { *flag } else { '-' })
+        .collect()
+}
+
+// the fields SM83Snapshot::compare checks, side by side, marking the ones that differ
+fn diff_snapshots(actual: &SM83Snapshot, expected: &SM83Snapshot) -> Result<(), String> {
+    if actual.compare(expected).is_ok() {
+        return Ok(());
+    }
+    let byte = |name, expected: u8, actual: u8| {
+        (name, format!("{:02x}", expected), format!("{:02x}", actual))
+    };
+    let word = |name, expected: u16, actual: u16| {
+        (name, format!("{:04x}", expected), format!("{:04x}", actual))
+    };
+    let fields = [
+        byte("a", expected.a, actual.a),
+        byte("b", expected.b, actual.b),
+        byte("c", expected.c, actual.c),
+        byte("d", expected.d, actual.d),
+        byte("e", expected.e, actual.e),
+        (
+            "f",
+            format!("{:02x} {}", expected.f, format_flags(expected.f)),
+            format!("{:02x} {}", actual.f, format_flags(actual.f)),
+        ),
+        byte("h", expected.h, actual.h),
+        byte("l", expected.l, actual.l),
+        word("sp", expected.sp, actual.sp),
+        word("pc", expected.pc, actual.pc),
+        byte("ie", expected.ie, actual.ie),
+        ("ime", expected.ime.to_string(), actual.ime.to_string()),
+        word("address bus", expected.address_bus, actual.address_bus),
+        byte("data bus", expected.data_bus, actual.data_bus),
+    ];
+    let mut result = String::from("  register     expected   actual\n");
+    for (name, expected, actual) in fields {
+        let marker = if expected != actual { "<-" } else { "" };
+        let row = format!("  {:<11}  {:<9}  {:<9}{}", name, expected, actual, marker);
+        result += row.trim_end();
+        result += "\n";
+    }
+    Err(result)
+}
+
+// each M-cycle is [address, data, "rwm"], with null data and "---" for internal cycles
+fn read_bus_trace(json_cycles: &JsonValue) -> Vec<BusCycle> {
+    json_cycles
+        .members()
+        .map(|json_cycle| {
+            let address = json_cycle[0].as_u16().unwrap_or(0);
+            let data = json_cycle[1].as_u8().unwrap_or(0);
+            let activity = json_cycle[2].as_str().unwrap_or("---");
+            if activity.starts_with('r') {
+                BusCycle::read(address, data)
+            } else if activity.contains('w') {
+                BusCycle::write(address, data)
+            } else {
+                BusCycle::internal(address)
+            }
+        })
+        .collect()
+}
+
+fn read_sm83_state(json_state: &JsonValue) -> (SM83Snapshot, RAM) {
+    assert!(json_state.has_key("ram"));
+    let snapshot = SM83Snapshot::new();
+    let snapshot = snapshot
+        .with_a(read_u8_value(json_state, "a"))
+        .with_b(read_u8_value(json_state, "b"))
+        .with_c(read_u8_value(json_state, "c"))
+        .with_d(read_u8_value(json_state, "d"))
+        .with_e(read_u8_value(json_state, "e"))
+        .with_f(read_u8_value(json_state, "f"))
+        .with_h(read_u8_value(json_state, "h"))
+        .with_l(read_u8_value(json_state, "l"))
+        //.with_ie(read_u8_value(json_state, "ie"))
+        .with_ime(read_bool_value(json_state, "ime"))
+        .with_pc(read_u16_value(json_state, "pc"))
+        .with_sp(read_u16_value(json_state, "sp"));
+    let mut ram = RAM::new(None);
+    fill_ram(&json_state["ram"], &mut ram);
+    (snapshot, ram)
+}
+
+fn read_test_case(json_content: &json::JsonValue) -> CaseResult {
+    assert!(json_content.has_key("name"));
+    assert!(json_content.has_key("initial"));
+    assert!(json_content.has_key("final"));
+    assert!(json_content.has_key("cycles"));
+    if
+    /*json_content["name"].as_str().unwrap().contains("27 ") ||*/
+    json_content["name"].as_str().unwrap().contains("FB ") {
+        return CaseResult::Skipped;
+    }
+    let initial_state = &json_content["initial"];
+    let final_state = &json_content["final"];
+    let (initial_snapshot, intial_ram) = read_sm83_state(initial_state);
+    let mut simulator =
+        System::from_ram_snapshot(intial_ram, initial_snapshot, true).with_bus_tracing();
+    simulator.next();
+    let (mut final_snapshot, _) = read_sm83_state(final_state);
+    if !json_content["name"].as_str().unwrap().contains("76 ")
+        || json_content["name"].as_str().unwrap().contains("CB ")
+    {
+        let new_pc = if final_snapshot.pc < u16::MAX {
+            final_snapshot.pc + 1
+        } else {
+            0
+        };
+        final_snapshot = final_snapshot.with_pc(new_pc);
+    }
+    // everything that differs is reported, not only the first difference
+    let mut diff = String::new();
+    if let Err(e) = diff_snapshots(&simulator.to_snapshot(), &final_snapshot) {
+        diff += e.as_str();
+    }
+    if let Err(e) = check_ram(&final_state["ram"], &simulator.get_ram()) {
+        diff += e.as_str();
+    }
+    let expected_trace = read_bus_trace(&json_content["cycles"]);
+    if let Err(e) = compare_bus_traces(&expected_trace, &simulator.get_bus_trace()) {
+        diff += format!("  bus trace: {}\n", e).as_str();
+    }
+    if diff.is_empty() {
+        CaseResult::Passed
+    } else {
+        CaseResult::Failed(diff)
+    }
+}
+
+// a panic in the emulator fails the case instead of the whole run
+fn run_test_case(json_content: &json::JsonValue) -> CaseResult {
+    match panic::catch_unwind(AssertUnwindSafe(|| read_test_case(json_content))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            CaseResult::Failed(format!("  panicked: {}\n", message))
+        }
+    }
+}
+
+// the files are named after the opcode they test, "3e" or "cb 12"
+fn opcode_of(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn run_file(path: &Path) -> FileResult {
+    let mut result = FileResult::new(opcode_of(path), path.display().to_string());
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            result.error = Some(format!("unable to read {}: {}", path.display(), e));
+            return result;
+        }
+    };
+    let tests_list = match json::parse(&content) {
+        Ok(tests_list) if tests_list.is_array() => tests_list,
+        _ => {
+            result.error = Some(format!("{} is not a list of test cases", path.display()));
+            return result;
+        }
+    };
+    for test_case in tests_list.members() {
+        match run_test_case(test_case) {
+            CaseResult::Passed => result.passed += 1,
+            CaseResult::Skipped => result.skipped += 1,
+            CaseResult::Failed(diff) => {
+                result.failed += 1;
+                if result.failures.len() < MAX_REPORTED_FAILURES {
+                    let name = test_case["name"].as_str().unwrap_or_default().to_string();
+                    result.failures.push((name, diff));
+                }
+            }
+        }
+    }
+    result
+}
+
+struct Options {
+    tests_path: String,
+    progress_path: String,
+    opcodes: Vec<String>,
+    jobs: usize,
+    resume: bool,
+    junit_path: Option<String>,
+    json_path: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Options {
+    let mut options = Options {
+        tests_path: DEFAULT_TESTS_PATH.to_string(),
+        progress_path: DEFAULT_PROGRESS_PATH.to_string(),
+        opcodes: Vec::new(),
+        jobs: std::thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+        resume: false,
+        junit_path: None,
+        json_path: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| panic!("{} needs a value", name))
+                .clone()
+        };
+        match arg.as_str() {
+            "--tests" => options.tests_path = value("--tests"),
+            "--progress" => options.progress_path = value("--progress"),
+            // "cb_12" is accepted for "cb 12" to spare the quotes
+            "--opcode" => options
+                .opcodes
+                .push(value("--opcode").to_lowercase().replace('_', " ")),
+            "--jobs" => {
+                options.jobs = value("--jobs")
+                    .parse()
+                    .expect("--jobs needs a number of threads")
+            }
+            "--resume" => options.resume = true,
+            "--junit" => options.junit_path = Some(value("--junit")),
+            "--json" => options.json_path = Some(value("--json")),
+            _ => panic!("unknown option {}", arg),
+        }
+    }
+    options
+}
+
+// Runs every JSON file of the SingleStepTests suite on a pool of threads. Fully passing files
+// are appended to the progress file, and skipped on the next run with --resume.
+//
+// usage: gb_tests [--tests dir] [--opcode 3e]... [--jobs n] [--resume] [--progress path]
+//                 [--junit report.xml] [--json report.json]
+pub fn main(args: &[String]) {
+    let options = parse_options(args);
+    let mut succesful_tests_file_contents =
+        fs::read_to_string(&options.progress_path).unwrap_or_default();
+    let json_files_paths = match fs::read_dir(&options.tests_path) {
+        Ok(paths) => paths,
+        Err(..) => panic!("unable to find files in path: {}", options.tests_path),
+    };
+    let mut paths: Vec<PathBuf> = json_files_paths
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter(|path| options.opcodes.is_empty() || options.opcodes.contains(&opcode_of(path)))
+        .filter(|path| {
+            !options.resume || !succesful_tests_file_contents.contains(path.to_str().unwrap())
+        })
+        .collect();
+    paths.sort();
+    let total_paths = paths.len();
+
+    // failures are reported with their diff, the default hook would print every panic
+    panic::set_hook(Box::new(|_| {}));
+    let queue = Arc::new(Mutex::new(VecDeque::from(paths)));
+    let (sender, receiver) = channel();
+    let workers: Vec<_> = (0..options.jobs.max(1))
+        .map(|_| {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || loop {
+                let path = match queue.lock().unwrap().pop_front() {
+                    Some(path) => path,
+                    None => break,
+                };
+                if sender.send(run_file(&path)).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut results = Vec::new();
+    for (index, result) in receiver.iter().enumerate() {
+        println!(
+            "{} {}/{} {}/{}",
+            result.path,
+            index + 1,
+            total_paths,
+            result.passed,
+            result.passed + result.failed
+        );
+        if result.is_passed() && !succesful_tests_file_contents.contains(&result.path) {
+            succesful_tests_file_contents += result.path.as_str();
+            succesful_tests_file_contents += "\n";
+            if let Err(e) = fs::write(&options.progress_path, &succesful_tests_file_contents) {
+                println!("unable to write {}: {}", options.progress_path, e);
+            }
+        }
+        results.push(result);
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let _ = panic::take_hook();
+
+    results.sort_by(|a, b| a.opcode.cmp(&b.opcode));
+    report::print_failures(&results);
+    report::print_summary(&results);
+    if let Some(path) = &options.junit_path {
+        fs::write(path, report::to_junit(&results)).unwrap();
+    }
+    if let Some(path) = &options.json_path {
+        fs::write(path, report::to_json(&results)).unwrap();
+    }
+    if results.iter().any(|result| !result.is_passed()) {
+        std::process::exit(1);
+    }
+}
@@ -1,16 +1,139 @@
 mod system;
-use system::ram::mapping_chip::{DynamicMappingChip, FakeChip};
-
-use crate::system::ram::mapping_chip::{NoChip, MBC1};
+use std::path::Path;
+use std::str::FromStr;
+use system::audio_sink::{AudioSink, NullAudioSink, WavAudioSink, DEFAULT_SAMPLE_RATE};
+use system::boot::GameBoyModel;
+use system::controllers::key_bindings::KeyBindings;
+use system::link_cable::SocketLinkCable;
+use system::movie::Movie;
+use system::printer::Printer;
+use system::ram::mapping_chip::{battery_save_path, DynamicMappingChip};
+use system::ram::BootRom;
+use system::video_sink::{PngSequenceSink, VideoSink};
 
+// usage: gbemulator [rom] [--model dmg0|dmg|mgb|sgb|sgb2|cgb] [--boot-rom path | --skip-boot]
+//                   [--keys bindings.json] [--record movie.txt | --play movie.txt]
+//                   [--link-listen address | --link-connect address | --printer paper.pgm]
+//                   [--frames directory] [--wav sound.wav]
+// link cable addresses are host:port, or unix:path for a Unix domain socket
+// --frames writes every frame as a PNG file in the directory instead of opening the window, the
+// sound is then not played either
+// --wav records the sound to a WAV file instead of playing it
 #[show_image::main]
 fn main() {
-    let gameboy = system::System::new(
-        Some(DynamicMappingChip::NoChip(NoChip::from_rom_path(
-            "./ttr.gb",
-        ))),
-        false,
-    );
+    let mut rom_path = String::from("./ttr.gb");
+    let mut boot_rom_path = None;
+    let mut skip_boot = false;
+    let mut key_bindings = KeyBindings::new();
+    let mut record_path = None;
+    let mut play_path = None;
+    let mut link = None;
+    let mut printer_path = None;
+    let mut frames_path = None;
+    let mut wav_path = None;
+    let mut model = GameBoyModel::DMG;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--boot-rom" => boot_rom_path = Some(args.next().expect("--boot-rom needs a path")),
+            "--skip-boot" => skip_boot = true,
+            "--record" => record_path = Some(args.next().expect("--record needs a path")),
+            "--play" => play_path = Some(args.next().expect("--play needs a path")),
+            "--link-listen" => {
+                link = Some((true, args.next().expect("--link-listen needs an address")))
+            }
+            "--link-connect" => {
+                link = Some((false, args.next().expect("--link-connect needs an address")))
+            }
+            "--printer" => printer_path = Some(args.next().expect("--printer needs a path")),
+            "--frames" => frames_path = Some(args.next().expect("--frames needs a directory")),
+            "--wav" => wav_path = Some(args.next().expect("--wav needs a path")),
+            "--keys" => {
+                let path = args.next().expect("--keys needs a path");
+                key_bindings = KeyBindings::from_file(&path).unwrap_or_else(|e| {
+                    println!("{}", e);
+                    std::process::exit(1);
+                })
+            }
+            "--model" => {
+                model =
+                    GameBoyModel::from_str(&args.next().expect("--model needs a model")).unwrap()
+            }
+            _ => rom_path = arg,
+        }
+    }
+
+    if let Some(path) = play_path {
+        let result = Movie::from_file(&path).and_then(|movie| movie.play(&rom_path));
+        match result {
+            Ok(frame_hashes) => println!(
+                "played {} frames, last frame hash {:016x}",
+                frame_hashes.len(),
+                frame_hashes.last().copied().unwrap_or(0)
+            ),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // the window and the audio device are only opened when nothing replaces them
+    let video_sink: Option<Box<dyn VideoSink>> = match frames_path {
+        Some(path) => match PngSequenceSink::new(&path) {
+            Ok(sink) => Some(Box::new(sink)),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let audio_sink: Option<Box<dyn AudioSink>> = match wav_path {
+        Some(path) => match WavAudioSink::new(&path, DEFAULT_SAMPLE_RATE) {
+            Ok(sink) => Some(Box::new(sink)),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        // a run without a window is not played either
+        None if video_sink.is_some() => Some(Box::new(NullAudioSink::default())),
+        None => None,
+    };
+    let chip = DynamicMappingChip::from_rom_path(&rom_path).unwrap();
+    let save_path = battery_save_path(&rom_path);
+    if chip.has_battery() && Path::new(&save_path).exists() {
+        println!("loaded battery save {}", save_path);
+    }
+    let mut gameboy = system::System::with_sinks(Some(chip), video_sink, audio_sink)
+        .with_key_bindings(key_bindings);
+    if skip_boot {
+        gameboy = gameboy.with_skip_boot(model);
+    } else if let Some(path) = boot_rom_path {
+        gameboy = gameboy.with_boot_rom(BootRom::from_file(&path, model).unwrap());
+    }
+    if let Some(path) = record_path {
+        gameboy = gameboy.with_movie_recording(&path).unwrap();
+    }
+    if let Some(path) = printer_path {
+        gameboy = gameboy.with_link_cable(Printer::new().with_image_path(&path));
+    } else if let Some((listen, address)) = link {
+        let link_cable = match (listen, address.strip_prefix("unix:")) {
+            (true, Some(path)) => SocketLinkCable::listen_unix(path),
+            (false, Some(path)) => SocketLinkCable::connect_unix(path),
+            (true, None) => SocketLinkCable::listen_tcp(&address),
+            (false, None) => SocketLinkCable::connect_tcp(&address),
+        };
+        match link_cable {
+            Ok(link_cable) => gameboy = gameboy.with_link_cable(link_cable),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
     let n_cycles = 60 * 1_000_000;
     let _ = gameboy.run(n_cycles);
 }
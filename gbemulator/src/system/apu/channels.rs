@@ -0,0 +1,672 @@
+// the waveforms of the four NRx1 duty settings, 12.5%, 25%, 50% and 75%
+const DUTY_WAVEFORMS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+// NR43 divisor codes, in M-cycles
+const NOISE_DIVISORS: [u32; 8] = [2, 4, 8, 12, 16, 20, 24, 28];
+const MAX_FREQUENCY: u16 = 2047;
+// how far the NR32 output levels shift the wave samples right, mute, 100%, 50% and 25%
+const WAVE_VOLUME_SHIFTS: [u8; 4] = [4, 0, 1, 2];
+const WAVE_RAM_SIZE: usize = 16;
+// the wave channel reads its first sample a little after the trigger
+const WAVE_TRIGGER_DELAY: u32 = 6;
+const T_CYCLES_PER_M_CYCLE: u32 = 4;
+
+// The length part of NRx1 and the length enable bit of NRx4. Once enabled the counter goes
+// down on every length clock of the frame sequencer and the channel stops when it reaches 0.
+#[derive(Clone)]
+pub struct LengthCounter {
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub fn new(max: u16) -> Self {
+        LengthCounter {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    pub fn load(&mut self, length: u8) {
+        self.counter = self.max - length as u16;
+    }
+
+    // NRx4, false when the channel has to stop. Enabling the length when the next step of the
+    // frame sequencer does not clock it clocks it once more, and a trigger that reloads an
+    // empty counter then also takes one off.
+    pub fn write_control(&mut self, enabled: bool, trigger: bool, length_clock_next: bool) -> bool {
+        let extra_clock = !length_clock_next && !self.enabled && enabled;
+        self.enabled = enabled;
+        let mut playing = true;
+        if extra_clock && self.counter > 0 {
+            self.counter -= 1;
+            playing = self.counter > 0;
+        }
+        if trigger && self.counter == 0 {
+            self.counter = self.max;
+            if enabled && !length_clock_next {
+                self.counter -= 1;
+            }
+        }
+        playing || trigger
+    }
+
+    // false once the channel has to stop
+    pub fn clock(&mut self) -> bool {
+        if self.enabled && self.counter > 0 {
+            self.counter -= 1;
+            return self.counter > 0;
+        }
+        true
+    }
+}
+
+// NRx2. The settings are only picked up by the next trigger, then the volume moves by one every
+// period envelope clocks, until it would leave 0 to 15.
+#[derive(Clone)]
+pub struct Envelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+    volume: u8,
+    timer: u8,
+    updating: bool,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Envelope {
+            initial_volume: 0,
+            increase: false,
+            period: 0,
+            volume: 0,
+            timer: 0,
+            updating: false,
+        }
+    }
+
+    // Writing while the channel plays changes the volume straight away, the "zombie" mode of
+    // the DMG: it goes up by 1 if the envelope was running with a period of 0, by 2 if it was
+    // decreasing, and is mirrored to 16 - volume when the direction changes. Games use it to
+    // change the volume without a trigger.
+    pub fn write(&mut self, value: u8, playing: bool) {
+        let increase = value & 0x08 != 0;
+        if playing {
+            if self.period == 0 && self.updating {
+                self.volume = self.volume.wrapping_add(1);
+            } else if !self.increase {
+                self.volume = self.volume.wrapping_add(2);
+            }
+            if increase != self.increase {
+                self.volume = 16u8.wrapping_sub(self.volume);
+            }
+            self.volume &= 0x0F;
+        }
+        self.initial_volume = value >> 4;
+        self.increase = increase;
+        self.period = value & 0x07;
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.period;
+        self.updating = true;
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 || !self.updating {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.increase && self.volume < 15 {
+                self.volume += 1;
+            } else if !self.increase && self.volume > 0 {
+                self.volume -= 1;
+            } else {
+                self.updating = false;
+            }
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// NR10, channel 1 only. Every period sweep clocks the frequency moves by itself shifted right
+// by shift, the channel stops when it would go over 2047.
+#[derive(Clone)]
+pub struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    timer: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Sweep {
+            period: 0,
+            negate: false,
+            shift: 0,
+            timer: 0,
+            enabled: false,
+            shadow_frequency: 0,
+        }
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+    }
+
+    // a period of 0 counts as 8
+    fn reload_timer(&mut self) {
+        self.timer = if self.period == 0 { 8 } else { self.period };
+    }
+
+    fn next_frequency(&self) -> u16 {
+        let delta = self.shadow_frequency >> self.shift;
+        if self.negate {
+            self.shadow_frequency - delta
+        } else {
+            self.shadow_frequency + delta
+        }
+    }
+
+    // false when the first calculation already overflows
+    pub fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow_frequency = frequency;
+        self.reload_timer();
+        self.enabled = self.period != 0 || self.shift != 0;
+        self.shift == 0 || self.next_frequency() <= MAX_FREQUENCY
+    }
+
+    // updates the frequency, false when it overflowed
+    pub fn clock(&mut self, frequency: &mut u16) -> bool {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return true;
+        }
+        self.reload_timer();
+        if !self.enabled || self.period == 0 {
+            return true;
+        }
+        let next_frequency = self.next_frequency();
+        if next_frequency > MAX_FREQUENCY {
+            return false;
+        }
+        if self.shift > 0 {
+            self.shadow_frequency = next_frequency;
+            *frequency = next_frequency;
+        }
+        self.next_frequency() <= MAX_FREQUENCY
+    }
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Channels 1 and 2, a square wave with a duty cycle. The duty position moves every
+// 2048 - frequency M-cycles, so the tone is at 131072 / (2048 - frequency) Hz.
+#[derive(Clone)]
+pub struct SquareChannel {
+    enabled: bool,
+    dac_enabled: bool,
+    duty: u8,
+    duty_step: u8,
+    frequency: u16,
+    timer: u16,
+    length: LengthCounter,
+    envelope: Envelope,
+    sweep: Option<Sweep>,
+}
+
+impl SquareChannel {
+    pub fn new(with_sweep: bool) -> Self {
+        SquareChannel {
+            enabled: false,
+            dac_enabled: false,
+            duty: 0,
+            duty_step: 0,
+            frequency: 0,
+            timer: 2048,
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            sweep: if with_sweep { Some(Sweep::new()) } else { None },
+        }
+    }
+
+    // the APU was turned off, everything but the length goes back to 0
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        *self = SquareChannel::new(self.sweep.is_some());
+        self.length = length;
+    }
+
+    pub fn write_sweep(&mut self, value: u8) {
+        if let Some(sweep) = self.sweep.as_mut() {
+            sweep.write(value);
+        }
+    }
+
+    pub fn write_duty(&mut self, value: u8) {
+        self.duty = value >> 6;
+    }
+
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value & 0x3F);
+    }
+
+    pub fn write_envelope(&mut self, value: u8) {
+        self.envelope.write(value, self.enabled);
+        self.dac_enabled = value & 0xF8 != 0;
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
+    }
+
+    pub fn write_frequency_lo(&mut self, value: u8) {
+        self.frequency = (self.frequency & 0x0700) | value as u16;
+    }
+
+    pub fn write_frequency_hi(&mut self, value: u8, length_clock_next: bool) {
+        self.frequency = (self.frequency & 0x00FF) | (((value & 0x07) as u16) << 8);
+        let trigger = value & 0x80 != 0;
+        if !self
+            .length
+            .write_control(value & 0x40 != 0, trigger, length_clock_next)
+        {
+            self.enabled = false;
+        }
+        if trigger {
+            self.trigger();
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.timer = 2048 - self.frequency;
+        self.envelope.trigger();
+        if let Some(sweep) = self.sweep.as_mut() {
+            if !sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    // one M-cycle
+    pub fn step(&mut self) {
+        self.timer -= 1;
+        if self.timer == 0 {
+            self.timer = 2048 - self.frequency;
+            self.duty_step = (self.duty_step + 1) & 0x07;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if !self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if let Some(sweep) = self.sweep.as_mut() {
+            if !sweep.clock(&mut self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    // what goes into the DAC, 0 to 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        DUTY_WAVEFORMS[self.duty as usize][self.duty_step as usize] * self.envelope.volume()
+    }
+}
+
+// Channel 3, plays the 32 4 bit samples of the wave RAM, high nibble first, moving to the next
+// one every 2 * (2048 - frequency) T-cycles, so the wave repeats at 65536 / (2048 - frequency)
+// Hz. NR30 turns its DAC on and NR32 shifts the samples down to set the volume.
+//
+// The channel reads the wave RAM as it plays, and on the DMG the CPU then only reaches the
+// byte the channel is reading, on the cycle it reads it. Otherwise reads give 0xFF and writes
+// are lost. The channel is stepped once the instruction that accessed it has run, so an access
+// looks ahead to the M-cycle of the instruction it happens on.
+#[derive(Clone)]
+pub struct WaveChannel {
+    enabled: bool,
+    dac_enabled: bool,
+    volume_shift: u8,
+    frequency: u16,
+    // T-cycles until the next sample
+    timer: u32,
+    position: u8,
+    // the byte of the wave RAM last read by the channel
+    sample_buffer: u8,
+    // whether the channel read the wave RAM in the last M-cycle it was stepped through
+    read_this_cycle: bool,
+    wave_ram: [u8; WAVE_RAM_SIZE],
+    length: LengthCounter,
+}
+
+impl WaveChannel {
+    pub fn new() -> Self {
+        WaveChannel {
+            enabled: false,
+            dac_enabled: false,
+            volume_shift: WAVE_VOLUME_SHIFTS[0],
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample_buffer: 0,
+            read_this_cycle: false,
+            wave_ram: [0; WAVE_RAM_SIZE],
+            length: LengthCounter::new(256),
+        }
+    }
+
+    // the wave RAM and the length survive the APU being turned off
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        let wave_ram = self.wave_ram;
+        *self = WaveChannel::new();
+        self.length = length;
+        self.wave_ram = wave_ram;
+    }
+
+    pub fn write_dac(&mut self, value: u8) {
+        self.dac_enabled = value & 0x80 != 0;
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
+    }
+
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value);
+    }
+
+    pub fn write_output_level(&mut self, value: u8) {
+        self.volume_shift = WAVE_VOLUME_SHIFTS[((value >> 5) & 0x03) as usize];
+    }
+
+    pub fn write_frequency_lo(&mut self, value: u8) {
+        self.frequency = (self.frequency & 0x0700) | value as u16;
+    }
+
+    pub fn write_frequency_hi(&mut self, value: u8, length_clock_next: bool) {
+        self.frequency = (self.frequency & 0x00FF) | (((value & 0x07) as u16) << 8);
+        let trigger = value & 0x80 != 0;
+        if !self
+            .length
+            .write_control(value & 0x40 != 0, trigger, length_clock_next)
+        {
+            self.enabled = false;
+        }
+        if trigger {
+            self.trigger();
+        }
+    }
+
+    fn period(&self) -> u32 {
+        2 * (2048 - self.frequency as u32)
+    }
+
+    // the position goes back to 0, which is played last as the channel moves on before reading
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.position = 0;
+        self.timer = self.period() + WAVE_TRIGGER_DELAY;
+    }
+
+    // one M-cycle
+    pub fn step(&mut self) {
+        self.read_this_cycle = false;
+        if !self.enabled {
+            return;
+        }
+        let mut t_cycles = T_CYCLES_PER_M_CYCLE;
+        while t_cycles >= self.timer {
+            t_cycles -= self.timer;
+            self.timer = self.period();
+            self.position = (self.position + 1) & 0x1F;
+            self.sample_buffer = self.wave_ram[self.position as usize / 2];
+            self.read_this_cycle = true;
+        }
+        self.timer -= t_cycles;
+    }
+
+    // the channel on the M-cycle after the pending_cycles it has not been stepped for yet
+    fn on_access_cycle(&self, pending_cycles: u64) -> WaveChannel {
+        let mut channel = self.clone();
+        for _ in 0..=pending_cycles {
+            channel.step();
+        }
+        channel
+    }
+
+    pub fn read_wave_ram(&self, offset: usize, pending_cycles: u64) -> u8 {
+        if !self.enabled {
+            return self.wave_ram[offset];
+        }
+        let channel = self.on_access_cycle(pending_cycles);
+        if channel.read_this_cycle {
+            self.wave_ram[channel.position as usize / 2]
+        } else {
+            0xFF
+        }
+    }
+
+    pub fn write_wave_ram(&mut self, offset: usize, value: u8, pending_cycles: u64) {
+        if !self.enabled {
+            self.wave_ram[offset] = value;
+            return;
+        }
+        let channel = self.on_access_cycle(pending_cycles);
+        if channel.read_this_cycle {
+            self.wave_ram[channel.position as usize / 2] = value;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if !self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        let sample = if self.position.is_multiple_of(2) {
+            self.sample_buffer >> 4
+        } else {
+            self.sample_buffer & 0x0F
+        };
+        sample >> self.volume_shift
+    }
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Channel 4, the output of a linear feedback shift register clocked every divisor << shift
+// M-cycles. In 7 bit mode the feedback also goes to bit 6, which makes a shorter, more tonal
+// sequence.
+#[derive(Clone)]
+pub struct NoiseChannel {
+    enabled: bool,
+    dac_enabled: bool,
+    shift: u8,
+    short_mode: bool,
+    divisor_code: u8,
+    lfsr: u16,
+    timer: u32,
+    length: LengthCounter,
+    envelope: Envelope,
+}
+
+impl NoiseChannel {
+    pub fn new() -> Self {
+        NoiseChannel {
+            enabled: false,
+            dac_enabled: false,
+            shift: 0,
+            short_mode: false,
+            divisor_code: 0,
+            lfsr: 0x7FFF,
+            timer: NOISE_DIVISORS[0],
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+        }
+    }
+
+    pub fn power_off(&mut self) {
+        let length = self.length.clone();
+        *self = NoiseChannel::new();
+        self.length = length;
+    }
+
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value & 0x3F);
+    }
+
+    pub fn write_envelope(&mut self, value: u8) {
+        self.envelope.write(value, self.enabled);
+        self.dac_enabled = value & 0xF8 != 0;
+        if !self.dac_enabled {
+            self.enabled = false;
+        }
+    }
+
+    pub fn write_polynomial(&mut self, value: u8) {
+        self.shift = value >> 4;
+        self.short_mode = value & 0x08 != 0;
+        self.divisor_code = value & 0x07;
+    }
+
+    pub fn write_control(&mut self, value: u8, length_clock_next: bool) {
+        let trigger = value & 0x80 != 0;
+        if !self
+            .length
+            .write_control(value & 0x40 != 0, trigger, length_clock_next)
+        {
+            self.enabled = false;
+        }
+        if trigger {
+            self.trigger();
+        }
+    }
+
+    fn period(&self) -> u32 {
+        NOISE_DIVISORS[self.divisor_code as usize] << self.shift
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.timer = self.period();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+    }
+
+    // one M-cycle
+    pub fn step(&mut self) {
+        self.timer -= 1;
+        if self.timer == 0 {
+            self.timer = self.period();
+            let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 0x01;
+            self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+            if self.short_mode {
+                self.lfsr = (self.lfsr & !0x40) | (feedback << 6);
+            }
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if !self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 0x01 != 0 {
+            return 0;
+        }
+        self.envelope.volume()
+    }
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
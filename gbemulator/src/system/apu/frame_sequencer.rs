@@ -0,0 +1,48 @@
+// The 512 Hz clock of the length counters, the sweep and the envelopes. It steps on every
+// falling edge of bit 4 of DIV and goes through 8 steps: the even ones clock the lengths
+// (256 Hz), steps 2 and 6 the sweep (128 Hz) and step 7 the envelopes (64 Hz).
+#[derive(Clone)]
+pub struct FrameSequencer {
+    // the step that comes next
+    step: u8,
+}
+
+// what a step of the frame sequencer clocks
+pub struct FrameSequencerStep {
+    pub length: bool,
+    pub sweep: bool,
+    pub envelope: bool,
+}
+
+impl FrameSequencer {
+    pub fn new() -> Self {
+        FrameSequencer { step: 0 }
+    }
+
+    // powering the APU on starts over from step 0
+    pub fn reset(&mut self) {
+        self.step = 0;
+    }
+
+    pub fn step(&mut self) -> FrameSequencerStep {
+        let step = self.step;
+        self.step = (self.step + 1) & 0x07;
+        FrameSequencerStep {
+            length: step.is_multiple_of(2),
+            sweep: step == 2 || step == 6,
+            envelope: step == 7,
+        }
+    }
+
+    // Lengths enabled in the first half of a length period, when the next step does not clock
+    // them, are clocked once more straight away.
+    pub fn is_length_clock_next(&self) -> bool {
+        self.step.is_multiple_of(2)
+    }
+}
+
+impl Default for FrameSequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,390 @@
+pub mod channels;
+pub mod frame_sequencer;
+pub mod resampler;
+
+use super::divider::FRAME_SEQUENCER_BIT;
+use channels::{NoiseChannel, SquareChannel, WaveChannel};
+use frame_sequencer::FrameSequencer;
+use resampler::BandLimitedBuffer;
+
+pub const SOUND_REGISTERS_START: u16 = 0xFF10;
+pub const NR50_ADDRESS: u16 = 0xFF24;
+pub const NR51_ADDRESS: u16 = 0xFF25;
+pub const NR52_ADDRESS: u16 = 0xFF26;
+pub const WAVE_RAM_START: u16 = 0xFF30;
+pub const WAVE_RAM_END: u16 = 0xFF3F;
+// the APU is clocked once per M-cycle
+pub const APU_CLOCK_RATE: u32 = 1_048_576;
+
+const NR10_ADDRESS: u16 = 0xFF10;
+const NR11_ADDRESS: u16 = 0xFF11;
+const NR12_ADDRESS: u16 = 0xFF12;
+const NR13_ADDRESS: u16 = 0xFF13;
+const NR14_ADDRESS: u16 = 0xFF14;
+const NR21_ADDRESS: u16 = 0xFF16;
+const NR22_ADDRESS: u16 = 0xFF17;
+const NR23_ADDRESS: u16 = 0xFF18;
+const NR24_ADDRESS: u16 = 0xFF19;
+const NR30_ADDRESS: u16 = 0xFF1A;
+const NR31_ADDRESS: u16 = 0xFF1B;
+const NR32_ADDRESS: u16 = 0xFF1C;
+const NR33_ADDRESS: u16 = 0xFF1D;
+const NR34_ADDRESS: u16 = 0xFF1E;
+const NR41_ADDRESS: u16 = 0xFF20;
+const NR42_ADDRESS: u16 = 0xFF21;
+const NR43_ADDRESS: u16 = 0xFF22;
+const NR44_ADDRESS: u16 = 0xFF23;
+const POWER_ON: u8 = 0x80;
+// the bits that read back as 1 in NR10 to NR51, write only bits included
+const READ_MASKS: [u8; 22] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+    0x00, 0x00, // NR50-NR51
+];
+const NR52_READ_MASK: u8 = 0x70;
+const T_CYCLES_PER_M_CYCLE: u16 = 4;
+// when nothing drains the samples only the last second is kept
+const MAX_BUFFERED_SECONDS: usize = 1;
+
+// the host side of the APU, only there once a sample rate was given
+#[derive(Clone)]
+struct Output {
+    left: BandLimitedBuffer,
+    right: BandLimitedBuffer,
+    // the mix as last added to the buffers
+    level: [f32; 2],
+    // interleaved, left then right
+    samples: Vec<f32>,
+    left_samples: Vec<f32>,
+    right_samples: Vec<f32>,
+}
+
+impl Output {
+    fn new(sample_rate: u32, clock: u64) -> Self {
+        Output {
+            left: BandLimitedBuffer::new(APU_CLOCK_RATE, sample_rate, clock),
+            right: BandLimitedBuffer::new(APU_CLOCK_RATE, sample_rate, clock),
+            level: [0.0; 2],
+            samples: Vec::new(),
+            left_samples: Vec::new(),
+            right_samples: Vec::new(),
+        }
+    }
+
+    fn set_level(&mut self, clock: u64, level: [f32; 2]) {
+        if level[0] != self.level[0] {
+            self.left.add_delta(clock, level[0] - self.level[0]);
+        }
+        if level[1] != self.level[1] {
+            self.right.add_delta(clock, level[1] - self.level[1]);
+        }
+        self.level = level;
+    }
+
+    fn read_until(&mut self, clock: u64) {
+        self.left.read_until(clock, &mut self.left_samples);
+        self.right.read_until(clock, &mut self.right_samples);
+        for (left, right) in self.left_samples.iter().zip(&self.right_samples) {
+            self.samples.push(*left);
+            self.samples.push(*right);
+        }
+        self.left_samples.clear();
+        self.right_samples.clear();
+        let max_samples = self.left.sample_rate() as usize * 2 * MAX_BUFFERED_SECONDS;
+        if self.samples.len() > max_samples {
+            self.samples.drain(..self.samples.len() - max_samples);
+        }
+    }
+}
+
+// The sound hardware, 0xFF10 to 0xFF3F. The channels are stepped every M-cycle from the CPU
+// clock, and the frame sequencer from DIV. RAM steps them once the instruction that took those
+// cycles has run, so register writes take effect at the end of that instruction. Wave RAM
+// accesses are given the cycles of the instruction before them, see WaveChannel. Each channel
+// goes through its DAC, NR51 routes the DACs to the left and right outputs and NR50 sets their
+// volume.
+//
+// With a sample rate set the mix is turned into samples for the host, see take_samples. Without
+// one, as when headless, the channels still run so that NR52 reports them, but nothing is
+// rendered.
+#[derive(Clone)]
+pub struct Apu {
+    powered: bool,
+    // NR10 to NR51 as written
+    registers: [u8; READ_MASKS.len()],
+    channel_1: SquareChannel,
+    channel_2: SquareChannel,
+    channel_3: WaveChannel,
+    channel_4: NoiseChannel,
+    clock: u64,
+    frame_sequencer: FrameSequencer,
+    output: Option<Output>,
+}
+
+// -1 to 1, a DAC that is off outputs nothing
+fn dac_output(dac_enabled: bool, value: u8) -> f32 {
+    if dac_enabled {
+        value as f32 / 7.5 - 1.0
+    } else {
+        0.0
+    }
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Apu {
+            powered: false,
+            registers: [0; READ_MASKS.len()],
+            channel_1: SquareChannel::new(true),
+            channel_2: SquareChannel::new(false),
+            channel_3: WaveChannel::new(),
+            channel_4: NoiseChannel::new(),
+            clock: 0,
+            frame_sequencer: FrameSequencer::new(),
+            output: None,
+        }
+    }
+
+    pub fn handles(address: u16) -> bool {
+        (SOUND_REGISTERS_START..=WAVE_RAM_END).contains(&address)
+    }
+
+    // pending_cycles are the M-cycles run before the access that the APU has not been ticked for
+    pub fn read(&self, address: u16, pending_cycles: u64) -> u8 {
+        match address {
+            NR52_ADDRESS => {
+                let mut value = NR52_READ_MASK;
+                if self.powered {
+                    value |= POWER_ON;
+                }
+                if self.channel_1.is_enabled() {
+                    value |= 0x01;
+                }
+                if self.channel_2.is_enabled() {
+                    value |= 0x02;
+                }
+                if self.channel_3.is_enabled() {
+                    value |= 0x04;
+                }
+                if self.channel_4.is_enabled() {
+                    value |= 0x08;
+                }
+                value
+            }
+            WAVE_RAM_START..=WAVE_RAM_END => self
+                .channel_3
+                .read_wave_ram((address - WAVE_RAM_START) as usize, pending_cycles),
+            _ => match self.register_index(address) {
+                Some(index) => self.registers[index] | READ_MASKS[index],
+                None => 0xFF,
+            },
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8, pending_cycles: u64) {
+        if address == NR52_ADDRESS {
+            self.write_power(value & POWER_ON != 0);
+            return;
+        }
+        if let WAVE_RAM_START..=WAVE_RAM_END = address {
+            self.channel_3.write_wave_ram(
+                (address - WAVE_RAM_START) as usize,
+                value,
+                pending_cycles,
+            );
+            return;
+        }
+        let index = match self.register_index(address) {
+            Some(index) => index,
+            None => return,
+        };
+        // while off only the lengths can be written, on the DMG
+        if !self.powered {
+            match address {
+                NR11_ADDRESS => self.channel_1.write_length(value),
+                NR21_ADDRESS => self.channel_2.write_length(value),
+                NR31_ADDRESS => self.channel_3.write_length(value),
+                NR41_ADDRESS => self.channel_4.write_length(value),
+                _ => {}
+            }
+            return;
+        }
+        self.registers[index] = value;
+        let length_clock_next = self.frame_sequencer.is_length_clock_next();
+        match address {
+            NR10_ADDRESS => self.channel_1.write_sweep(value),
+            NR11_ADDRESS => {
+                self.channel_1.write_duty(value);
+                self.channel_1.write_length(value);
+            }
+            NR12_ADDRESS => self.channel_1.write_envelope(value),
+            NR13_ADDRESS => self.channel_1.write_frequency_lo(value),
+            NR14_ADDRESS => self.channel_1.write_frequency_hi(value, length_clock_next),
+            NR21_ADDRESS => {
+                self.channel_2.write_duty(value);
+                self.channel_2.write_length(value);
+            }
+            NR22_ADDRESS => self.channel_2.write_envelope(value),
+            NR23_ADDRESS => self.channel_2.write_frequency_lo(value),
+            NR24_ADDRESS => self.channel_2.write_frequency_hi(value, length_clock_next),
+            NR30_ADDRESS => self.channel_3.write_dac(value),
+            NR31_ADDRESS => self.channel_3.write_length(value),
+            NR32_ADDRESS => self.channel_3.write_output_level(value),
+            NR33_ADDRESS => self.channel_3.write_frequency_lo(value),
+            NR34_ADDRESS => self.channel_3.write_frequency_hi(value, length_clock_next),
+            NR41_ADDRESS => self.channel_4.write_length(value),
+            NR42_ADDRESS => self.channel_4.write_envelope(value),
+            NR43_ADDRESS => self.channel_4.write_polynomial(value),
+            NR44_ADDRESS => self.channel_4.write_control(value, length_clock_next),
+            _ => {}
+        }
+    }
+
+    fn register_index(&self, address: u16) -> Option<usize> {
+        let index = address.checked_sub(SOUND_REGISTERS_START)? as usize;
+        if index < READ_MASKS.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    fn write_power(&mut self, powered: bool) {
+        if powered && !self.powered {
+            self.frame_sequencer.reset();
+        }
+        if !powered && self.powered {
+            self.registers = [0; READ_MASKS.len()];
+            self.channel_1.power_off();
+            self.channel_2.power_off();
+            self.channel_3.power_off();
+            self.channel_4.power_off();
+        }
+        self.powered = powered;
+    }
+
+    // Turns off the channels whose bit is clear in the given NR52 value, for states that were
+    // not reached by running the sound hardware, like the state after the boot ROM.
+    pub fn set_playing_channels(&mut self, nr52: u8) {
+        if nr52 & 0x01 == 0 {
+            self.channel_1.disable();
+        }
+        if nr52 & 0x02 == 0 {
+            self.channel_2.disable();
+        }
+        if nr52 & 0x04 == 0 {
+            self.channel_3.disable();
+        }
+        if nr52 & 0x08 == 0 {
+            self.channel_4.disable();
+        }
+    }
+
+    // the given M-cycles, starting with the internal counter of DIV at divider_counter
+    pub fn tick(&mut self, cycles: u64, divider_counter: u16) {
+        let mut divider_counter = divider_counter;
+        for _ in 0..cycles {
+            self.clock += 1;
+            let previous_divider_counter = divider_counter;
+            divider_counter = divider_counter.wrapping_add(T_CYCLES_PER_M_CYCLE);
+            if self.powered {
+                self.channel_1.step();
+                self.channel_2.step();
+                self.channel_3.step();
+                self.channel_4.step();
+                if previous_divider_counter & FRAME_SEQUENCER_BIT != 0
+                    && divider_counter & FRAME_SEQUENCER_BIT == 0
+                {
+                    self.clock_frame_sequencer();
+                }
+            }
+            if self.output.is_some() {
+                let level = self.mix();
+                let clock = self.clock;
+                if let Some(output) = self.output.as_mut() {
+                    output.set_level(clock, level);
+                }
+            }
+        }
+        let clock = self.clock;
+        if let Some(output) = self.output.as_mut() {
+            output.read_until(clock);
+        }
+    }
+
+    // bit 4 of DIV fell, either counting or because DIV was written
+    pub fn clock_frame_sequencer(&mut self) {
+        if !self.powered {
+            return;
+        }
+        let step = self.frame_sequencer.step();
+        if step.length {
+            self.channel_1.clock_length();
+            self.channel_2.clock_length();
+            self.channel_3.clock_length();
+            self.channel_4.clock_length();
+        }
+        if step.sweep {
+            self.channel_1.clock_sweep();
+        }
+        if step.envelope {
+            self.channel_1.clock_envelope();
+            self.channel_2.clock_envelope();
+            self.channel_4.clock_envelope();
+        }
+    }
+
+    // the left and right outputs, -1 to 1
+    fn mix(&self) -> [f32; 2] {
+        let channels = [
+            dac_output(self.channel_1.is_dac_enabled(), self.channel_1.output()),
+            dac_output(self.channel_2.is_dac_enabled(), self.channel_2.output()),
+            dac_output(self.channel_3.is_dac_enabled(), self.channel_3.output()),
+            dac_output(self.channel_4.is_dac_enabled(), self.channel_4.output()),
+        ];
+        let nr50 = self.registers[(NR50_ADDRESS - SOUND_REGISTERS_START) as usize];
+        let nr51 = self.registers[(NR51_ADDRESS - SOUND_REGISTERS_START) as usize];
+        let mut left = 0.0;
+        let mut right = 0.0;
+        for (channel, value) in channels.iter().enumerate() {
+            if nr51 & (0x10 << channel) != 0 {
+                left += value;
+            }
+            if nr51 & (0x01 << channel) != 0 {
+                right += value;
+            }
+        }
+        let left_volume = ((nr50 >> 4) & 0x07) as f32 + 1.0;
+        let right_volume = (nr50 & 0x07) as f32 + 1.0;
+        [
+            left / 4.0 * left_volume / 8.0,
+            right / 4.0 * right_volume / 8.0,
+        ]
+    }
+
+    // Samples are rendered at the given rate from now on, none to stop. Changing the rate
+    // drops the samples not taken yet.
+    pub fn set_sample_rate(&mut self, sample_rate: Option<u32>) {
+        self.output = sample_rate.map(|sample_rate| Output::new(sample_rate, self.clock));
+    }
+
+    pub fn get_sample_rate(&self) -> Option<u32> {
+        self.output.as_ref().map(|output| output.left.sample_rate())
+    }
+
+    // the samples rendered since the last call, interleaved left then right
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        match self.output.as_mut() {
+            Some(output) => std::mem::take(&mut output.samples),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,122 @@
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+// length of the band-limited step, in output samples
+const TAPS: usize = 16;
+// positions a step can take between two output samples
+const PHASES: usize = 64;
+// a little under the Nyquist frequency of the output, so that the window has room to fall off
+const CUTOFF: f64 = 0.9;
+// the capacitor on the Game Boy output loses this much of its charge every T-cycle
+const CAPACITOR_CHARGE_PER_T_CYCLE: f64 = 0.999958;
+const T_CYCLES_PER_SECOND: f64 = 4_194_304.0;
+
+// One row per phase, each a Blackman windowed sinc impulse shifted by phase / PHASES of a
+// sample and normalised so that its taps add up to 1.
+fn kernel() -> &'static [f32] {
+    static KERNEL: OnceLock<Vec<f32>> = OnceLock::new();
+    KERNEL.get_or_init(|| {
+        let mut kernel = Vec::with_capacity(PHASES * TAPS);
+        for phase in 0..PHASES {
+            let offset = phase as f64 / PHASES as f64;
+            let row: Vec<f64> = (0..TAPS)
+                .map(|tap| {
+                    let t = tap as f64 - (TAPS / 2 - 1) as f64 - offset;
+                    let x = CUTOFF * t;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (PI * x).sin() / (PI * x)
+                    };
+                    let window = 0.42
+                        + 0.5 * (2.0 * PI * t / TAPS as f64).cos()
+                        + 0.08 * (4.0 * PI * t / TAPS as f64).cos();
+                    sinc * window
+                })
+                .collect();
+            let sum: f64 = row.iter().sum();
+            kernel.extend(row.iter().map(|tap| (tap / sum) as f32));
+        }
+        kernel
+    })
+}
+
+// Turns a signal that only moves by steps, like the output of the APU, into samples at the
+// host rate. Every step is added at its exact position as a band-limited step, the integral of
+// a windowed sinc, so the square waves come out without the aliasing that picking one value
+// per output sample would fold back into the audible range. The samples are then high-pass
+// filtered like the capacitor on the Game Boy output, which removes the offset of the DACs.
+//
+// Times are in cycles of the emulated clock. A sample is final once no step added from then on
+// can reach it, which delays the output by half the length of the step.
+#[derive(Clone)]
+pub struct BandLimitedBuffer {
+    clock_rate: u64,
+    sample_rate: u64,
+    // index of the first sample of pending since the start of the clock
+    first_sample: u64,
+    // the steps landing on the samples that are not final yet
+    pending: Vec<f32>,
+    level: f32,
+    capacitor: f32,
+    charge_factor: f32,
+}
+
+impl BandLimitedBuffer {
+    pub fn new(clock_rate: u32, sample_rate: u32, clock: u64) -> Self {
+        let mut buffer = BandLimitedBuffer {
+            clock_rate: clock_rate as u64,
+            sample_rate: sample_rate as u64,
+            first_sample: 0,
+            pending: Vec::with_capacity(TAPS * 2),
+            level: 0.0,
+            capacitor: 0.0,
+            charge_factor: CAPACITOR_CHARGE_PER_T_CYCLE
+                .powf(T_CYCLES_PER_SECOND / sample_rate as f64) as f32,
+        };
+        buffer.first_sample = buffer.sample_at(clock).0;
+        buffer
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate as u32
+    }
+
+    // the output sample the clock falls in, and the phase within it
+    fn sample_at(&self, clock: u64) -> (u64, usize) {
+        let position = clock as u128 * self.sample_rate as u128;
+        let clock_rate = self.clock_rate as u128;
+        let phase = (position % clock_rate) * PHASES as u128 / clock_rate;
+        ((position / clock_rate) as u64, phase as usize)
+    }
+
+    // the signal moves by delta at the given clock, which cannot be before the last read
+    pub fn add_delta(&mut self, clock: u64, delta: f32) {
+        let (sample, phase) = self.sample_at(clock);
+        let start = sample.saturating_sub(self.first_sample) as usize;
+        if self.pending.len() < start + TAPS {
+            self.pending.resize(start + TAPS, 0.0);
+        }
+        let row = &kernel()[phase * TAPS..(phase + 1) * TAPS];
+        for (pending, tap) in self.pending[start..].iter_mut().zip(row) {
+            *pending += delta * tap;
+        }
+    }
+
+    // appends the samples that are final at the given clock
+    pub fn read_until(&mut self, clock: u64, output: &mut Vec<f32>) {
+        let last_sample = self.sample_at(clock).0;
+        if last_sample <= self.first_sample {
+            return;
+        }
+        let count = (last_sample - self.first_sample) as usize;
+        for index in 0..count {
+            self.level += self.pending.get(index).copied().unwrap_or(0.0);
+            let sample = self.level - self.capacitor;
+            self.capacitor = self.level - sample * self.charge_factor;
+            output.push(sample);
+        }
+        self.pending.drain(..count.min(self.pending.len()));
+        self.first_sample = last_sample;
+    }
+}
@@ -0,0 +1,241 @@
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, Sink};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::sync::{mpsc, Arc, Mutex};
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const WAV_HEADER_SIZE: u32 = 44;
+
+// Where the sound goes. Samples come interleaved, left then right, between -1 and 1, at the
+// rate the sink asks for.
+pub trait AudioSink: Send {
+    fn sample_rate(&self) -> u32;
+
+    fn queue(&mut self, samples: &[f32]);
+
+    // the emulation is over, flush what is left and release the device
+    fn close(&mut self) {}
+}
+
+// drops the samples, for headless runs
+pub struct NullAudioSink {
+    sample_rate: u32,
+}
+
+impl NullAudioSink {
+    pub fn new(sample_rate: u32) -> Self {
+        NullAudioSink { sample_rate }
+    }
+}
+
+impl Default for NullAudioSink {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl AudioSink for NullAudioSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, _samples: &[f32]) {}
+}
+
+// Keeps every sample in memory. Clones share the samples, so a clone kept aside can inspect
+// what the system played.
+#[derive(Clone)]
+pub struct SampleRecorder {
+    sample_rate: u32,
+    samples: Arc<Mutex<Vec<f32>>>,
+}
+
+impl SampleRecorder {
+    pub fn new(sample_rate: u32) -> Self {
+        SampleRecorder {
+            sample_rate,
+            samples: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn get_samples(&self) -> Vec<f32> {
+        self.samples.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.samples.lock().unwrap().clear();
+    }
+}
+
+impl AudioSink for SampleRecorder {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, samples: &[f32]) {
+        self.samples.lock().unwrap().extend_from_slice(samples);
+    }
+}
+
+// Plays the samples on the default output device. The output stream cannot leave the thread
+// that opened it, so a thread holds it while the sink is alive.
+pub struct RodioAudioSink {
+    sample_rate: u32,
+    sink: Sink,
+    // dropping it lets the stream thread end
+    stop: Option<mpsc::Sender<()>>,
+    thread_handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl RodioAudioSink {
+    pub fn new(sample_rate: u32) -> Result<Self, String> {
+        let (sink_sender, sink_receiver) = mpsc::channel();
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        let thread_handle = std::thread::spawn(move || {
+            let stream = OutputStream::try_default()
+                .map_err(|e| format!("no audio output: {}", e))
+                .and_then(|(stream, stream_handle)| {
+                    Sink::try_new(&stream_handle)
+                        .map(|sink| (stream, sink))
+                        .map_err(|e| format!("unable to play audio: {}", e))
+                });
+            match stream {
+                Ok((_stream, sink)) => {
+                    let _ = sink_sender.send(Ok(sink));
+                    // _stream must live as long as the sink
+                    let _ = stop_receiver.recv();
+                }
+                Err(e) => {
+                    let _ = sink_sender.send(Err(e));
+                }
+            }
+        });
+        let sink = sink_receiver
+            .recv()
+            .map_err(|e| format!("the audio thread ended: {}", e))??;
+        Ok(RodioAudioSink {
+            sample_rate,
+            sink,
+            stop: Some(stop_sender),
+            thread_handle: Some(thread_handle),
+        })
+    }
+}
+
+impl AudioSink for RodioAudioSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, samples: &[f32]) {
+        self.sink
+            .append(SamplesBuffer::new(2, self.sample_rate, samples.to_vec()));
+    }
+
+    fn close(&mut self) {
+        self.sink.stop();
+        self.stop = None;
+        if let Some(thread_handle) = self.thread_handle.take() {
+            let _ = thread_handle.join();
+        }
+    }
+}
+
+impl Drop for RodioAudioSink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// Writes the samples to a 16 bit stereo WAV file. The sizes in the header are only known at
+// the end, they are filled in by close.
+pub struct WavAudioSink {
+    sample_rate: u32,
+    path: String,
+    writer: Option<BufWriter<File>>,
+    data_size: u32,
+}
+
+fn wav_header(sample_rate: u32, data_size: u32) -> Vec<u8> {
+    let channels = 2u16;
+    let bits_per_sample = 16u16;
+    let block_align = channels * bits_per_sample / 8;
+    let mut header = Vec::with_capacity(WAV_HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(WAV_HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&bits_per_sample.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    header
+}
+
+impl WavAudioSink {
+    pub fn new(path: &str, sample_rate: u32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("unable to create {}: {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&wav_header(sample_rate, 0))
+            .map_err(|e| format!("unable to write {}: {}", path, e))?;
+        Ok(WavAudioSink {
+            sample_rate,
+            path: path.to_string(),
+            writer: Some(writer),
+            data_size: 0,
+        })
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        let mut writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(&wav_header(self.sample_rate, self.data_size))?;
+        writer.flush()
+    }
+}
+
+impl AudioSink for WavAudioSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, samples: &[f32]) {
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return,
+        };
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+            .collect();
+        match writer.write_all(&bytes) {
+            Ok(_) => self.data_size += bytes.len() as u32,
+            Err(e) => {
+                println!("unable to write {}: {}", self.path, e);
+                self.writer = None;
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if let Err(e) = self.finish() {
+            println!("unable to write {}: {}", self.path, e);
+        }
+    }
+}
+
+impl Drop for WavAudioSink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use super::apu::NR52_ADDRESS;
+use super::divider::DIV_ADDRESS;
+use super::ram::{BootLockMemoryRegister, BootRom, MemoryRegister, RAM};
+use super::sm83::{snapshot::SM83Snapshot, SM83};
+
+// where the boot ROM hands over to the cartridge
+pub const CARTRIDGE_ENTRY_POINT: u16 = 0x0100;
+const HEADER_CHECKSUM_ADDRESS: u16 = 0x014D;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameBoyModel {
+    DMG0,
+    DMG,
+    MGB,
+    SGB,
+    SGB2,
+    CGB,
+}
+
+impl GameBoyModel {
+    // the CGB boot ROM is split around the cartridge header: 0x0000-0x00FF and 0x0200-0x08FF
+    pub fn boot_rom_size(&self) -> usize {
+        match self {
+            GameBoyModel::CGB => 0x0900,
+            _ => 0x0100,
+        }
+    }
+}
+
+impl FromStr for GameBoyModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dmg0" => Ok(GameBoyModel::DMG0),
+            "dmg" => Ok(GameBoyModel::DMG),
+            "mgb" => Ok(GameBoyModel::MGB),
+            "sgb" => Ok(GameBoyModel::SGB),
+            "sgb2" => Ok(GameBoyModel::SGB2),
+            "cgb" => Ok(GameBoyModel::CGB),
+            _ => Err(format!(
+                "unknown model {}, expected one of dmg0, dmg, mgb, sgb, sgb2, cgb",
+                s
+            )),
+        }
+    }
+}
+
+// How the system gets from power on to the cartridge entry point
+#[derive(Clone)]
+pub enum BootMode {
+    BootRom(BootRom),
+    // start at 0x0100 with the state the given model's boot ROM leaves behind
+    Skip(GameBoyModel),
+}
+
+// CPU registers at 0x0100, as listed in the Pan Docs power up sequence. On DMG and MGB the
+// boot ROM leaves H and C set unless the header checksum is 0x00.
+pub fn post_boot_snapshot(model: GameBoyModel, ram: &RAM) -> SM83Snapshot {
+    let checksum_flags = match ram.get_at(HEADER_CHECKSUM_ADDRESS) {
+        Some(0x00) => 0x80,
+        _ => 0xB0,
+    };
+    let (af, bc, de, hl) = match model {
+        GameBoyModel::DMG0 => (0x0100, 0xFF13, 0x00C1, 0x8403),
+        GameBoyModel::DMG => (0x0100 | checksum_flags, 0x0013, 0x00D8, 0x014D),
+        GameBoyModel::MGB => (0xFF00 | checksum_flags, 0x0013, 0x00D8, 0x014D),
+        GameBoyModel::SGB => (0x0100, 0x0014, 0x0000, 0xC060),
+        GameBoyModel::SGB2 => (0xFF00, 0x0014, 0x0000, 0xC060),
+        GameBoyModel::CGB => (0x1180, 0x0000, 0xFF56, 0x000D),
+    };
+    SM83Snapshot::new()
+        .with_af(af)
+        .with_bc(bc)
+        .with_de(de)
+        .with_hl(hl)
+        .with_sp(0xFFFE)
+        .with_pc(CARTRIDGE_ENTRY_POINT)
+}
+
+// IO registers after the boot ROM. Registers the docs list as unknown are left at 0, DMA is
+// left out as writing it would start a transfer.
+pub fn post_boot_io_registers(model: GameBoyModel) -> Vec<(u16, u8)> {
+    let div = match model {
+        GameBoyModel::DMG0 => 0x18,
+        GameBoyModel::DMG | GameBoyModel::MGB => 0xAB,
+        _ => 0x00,
+    };
+    let serial_control = match model {
+        GameBoyModel::CGB => 0x7F,
+        _ => 0x7E,
+    };
+    let nr52 = match model {
+        GameBoyModel::SGB | GameBoyModel::SGB2 => 0xF0,
+        _ => 0xF1,
+    };
+    let stat = match model {
+        GameBoyModel::DMG0 => 0x81,
+        _ => 0x85,
+    };
+    vec![
+        (0xFF00, 0xCF),
+        (0xFF01, 0x00),
+        (0xFF02, serial_control),
+        (0xFF04, div),
+        (0xFF05, 0x00),
+        (0xFF06, 0x00),
+        (0xFF07, 0xF8),
+        (0xFF0F, 0xE1),
+        (0xFF10, 0x80),
+        (0xFF11, 0xBF),
+        (0xFF12, 0xF3),
+        (0xFF13, 0xFF),
+        (0xFF14, 0xBF),
+        (0xFF16, 0x3F),
+        (0xFF17, 0x00),
+        (0xFF18, 0xFF),
+        (0xFF19, 0xBF),
+        (0xFF1A, 0x7F),
+        (0xFF1B, 0xFF),
+        (0xFF1C, 0x9F),
+        (0xFF1D, 0xFF),
+        (0xFF1E, 0xBF),
+        (0xFF20, 0xFF),
+        (0xFF21, 0x00),
+        (0xFF22, 0x00),
+        (0xFF23, 0xBF),
+        (0xFF24, 0x77),
+        (0xFF25, 0xF3),
+        (0xFF26, nr52),
+        (0xFF40, 0x91),
+        (0xFF41, stat),
+        (0xFF42, 0x00),
+        (0xFF43, 0x00),
+        (0xFF45, 0x00),
+        (0xFF47, 0xFC),
+        (0xFF48, 0xFF),
+        (0xFF49, 0xFF),
+        (0xFF4A, 0x00),
+        (0xFF4B, 0x00),
+        (0xFFFF, 0x00),
+    ]
+}
+
+// Puts the CPU and memory in the state the boot ROM of the model would leave them in, with
+// the cartridge mapped and the CPU about to execute 0x0100
+pub fn skip_boot(model: GameBoyModel, cpu: &mut SM83, ram: &mut RAM) {
+    ram.load_base_rom_bank();
+    BootLockMemoryRegister::new().load_in_ram(ram);
+    // the APU ignores its registers while it is off
+    ram.set_at(NR52_ADDRESS, 0x80);
+    for (address, value) in post_boot_io_registers(model) {
+        if address == DIV_ADDRESS {
+            ram.load_div(value);
+            continue;
+        }
+        ram.set_at(address, value);
+        // writing NR14 started channel 1, only the boot ROMs that beep leave it playing
+        if address == NR52_ADDRESS {
+            ram.get_apu_mut().set_playing_channels(value);
+        }
+    }
+    cpu.load_snapshot(post_boot_snapshot(model, ram));
+    cpu.fetch_cycle(ram);
+}
@@ -0,0 +1,347 @@
+use crate::system::joypad::Button;
+use show_image::event::VirtualKeyCode;
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// Keys that can be named in a bindings file, spelled as their VirtualKeyCode variant
+const HOST_KEYS: [VirtualKeyCode; 96] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::Insert,
+    VirtualKeyCode::Home,
+    VirtualKeyCode::Delete,
+    VirtualKeyCode::End,
+    VirtualKeyCode::PageDown,
+    VirtualKeyCode::PageUp,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::NumpadEnter,
+    VirtualKeyCode::NumpadAdd,
+    VirtualKeyCode::NumpadSubtract,
+    VirtualKeyCode::NumpadMultiply,
+    VirtualKeyCode::NumpadDivide,
+    VirtualKeyCode::LAlt,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::RAlt,
+    VirtualKeyCode::RControl,
+    VirtualKeyCode::RShift,
+    VirtualKeyCode::Apostrophe,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::Grave,
+    VirtualKeyCode::LBracket,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::RBracket,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Capital,
+];
+
+pub fn parse_host_key(name: &str) -> Result<VirtualKeyCode, String> {
+    HOST_KEYS
+        .iter()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or(format!(
+            "unknown key {}, keys are named like A, Key1, F5, Up, Return, Space or LShift",
+            name
+        ))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hotkey {
+    Pause,
+    FastForward,
+    Screenshot,
+    SaveState,
+    LoadState,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pause" => Ok(Hotkey::Pause),
+            "fast_forward" => Ok(Hotkey::FastForward),
+            "screenshot" => Ok(Hotkey::Screenshot),
+            "save_state" => Ok(Hotkey::SaveState),
+            "load_state" => Ok(Hotkey::LoadState),
+            _ => Err(format!(
+                "unknown hotkey {}, expected one of pause, fast_forward, screenshot, save_state, \
+                 load_state",
+                s
+            )),
+        }
+    }
+}
+
+// Hotkey state shared between the window thread and the system threads. Pause toggles on
+// each press, fast forward lasts while the key is held, screenshots, save states and loading
+// them are requests that are cleared by whoever services them.
+#[derive(Clone, Default)]
+pub struct HotkeyState {
+    paused: Arc<AtomicBool>,
+    fast_forward: Arc<AtomicBool>,
+    screenshot_requested: Arc<AtomicBool>,
+    save_state_requested: Arc<AtomicBool>,
+    load_state_requested: Arc<AtomicBool>,
+}
+
+impl HotkeyState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&self, hotkey: Hotkey, pressed: bool) {
+        match hotkey {
+            Hotkey::Pause => {
+                if pressed {
+                    self.paused.fetch_xor(true, Ordering::Relaxed);
+                }
+            }
+            Hotkey::FastForward => self.fast_forward.store(pressed, Ordering::Relaxed),
+            Hotkey::Screenshot => {
+                if pressed {
+                    self.screenshot_requested.store(true, Ordering::Relaxed);
+                }
+            }
+            Hotkey::SaveState => {
+                if pressed {
+                    self.save_state_requested.store(true, Ordering::Relaxed);
+                }
+            }
+            Hotkey::LoadState => {
+                if pressed {
+                    self.load_state_requested.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.fast_forward.load(Ordering::Relaxed)
+    }
+
+    pub fn take_screenshot_request(&self) -> bool {
+        self.screenshot_requested.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn take_save_state_request(&self) -> bool {
+        self.save_state_requested.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn take_load_state_request(&self) -> bool {
+        self.load_state_requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+// Host keys bound to Game Boy buttons and emulator hotkeys. A bindings file is a JSON object
+// with an optional "buttons" and an optional "hotkeys" section, each mapping a name to a key or
+// a list of keys:
+//
+// {
+//     "buttons": { "up": ["W", "Up"], "left": "A", "down": "S", "right": "D",
+//                  "a": "K", "b": "J", "select": "RShift", "start": "Return" },
+//     "hotkeys": { "pause": "P", "fast_forward": "Tab", "screenshot": "F12", "save_state": "F5",
+//                  "load_state": "F8" }
+// }
+//
+// A section that is present replaces the default bindings of that section entirely.
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeyBindings {
+    buttons: Vec<(VirtualKeyCode, Button)>,
+    hotkeys: Vec<(VirtualKeyCode, Hotkey)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            buttons: vec![
+                (VirtualKeyCode::Right, Button::Right),
+                (VirtualKeyCode::Left, Button::Left),
+                (VirtualKeyCode::Up, Button::Up),
+                (VirtualKeyCode::Down, Button::Down),
+                (VirtualKeyCode::Z, Button::A),
+                (VirtualKeyCode::X, Button::B),
+                (VirtualKeyCode::Back, Button::Select),
+                (VirtualKeyCode::RShift, Button::Select),
+                (VirtualKeyCode::Return, Button::Start),
+            ],
+            hotkeys: vec![
+                (VirtualKeyCode::P, Hotkey::Pause),
+                (VirtualKeyCode::Tab, Hotkey::FastForward),
+                (VirtualKeyCode::F12, Hotkey::Screenshot),
+                (VirtualKeyCode::F5, Hotkey::SaveState),
+                (VirtualKeyCode::F8, Hotkey::LoadState),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read key bindings {}: {}", path, e))?;
+        KeyBindings::from_json(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        let config = json::parse(contents).map_err(|e| format!("invalid JSON: {}", e))?;
+        if !config.is_object() {
+            return Err("expected an object with \"buttons\" and \"hotkeys\" sections".to_string());
+        }
+        let mut bindings = KeyBindings::default();
+        for (section, entries) in config.entries() {
+            match section {
+                "buttons" => bindings.buttons = parse_section(section, entries)?,
+                "hotkeys" => bindings.hotkeys = parse_section(section, entries)?,
+                _ => {
+                    return Err(format!(
+                        "unknown section {}, expected buttons or hotkeys",
+                        section
+                    ))
+                }
+            }
+        }
+        bindings.check_duplicates()?;
+        Ok(bindings)
+    }
+
+    pub fn get_button(&self, key: VirtualKeyCode) -> Option<Button> {
+        find_binding(&self.buttons, key)
+    }
+
+    pub fn get_hotkey(&self, key: VirtualKeyCode) -> Option<Hotkey> {
+        find_binding(&self.hotkeys, key)
+    }
+
+    // a key driving two things at once is almost certainly a mistake in the file
+    fn check_duplicates(&self) -> Result<(), String> {
+        let mut seen: Vec<(VirtualKeyCode, String)> = Vec::new();
+        let buttons = self.buttons.iter().map(|(k, b)| (*k, format!("{:?}", b)));
+        let hotkeys = self.hotkeys.iter().map(|(k, h)| (*k, format!("{:?}", h)));
+        for (key, target) in buttons.chain(hotkeys) {
+            if let Some((_, other)) = seen.iter().find(|(k, _)| *k == key) {
+                return Err(format!(
+                    "key {:?} is bound to both {} and {}",
+                    key, other, target
+                ));
+            }
+            seen.push((key, target));
+        }
+        Ok(())
+    }
+}
+
+fn find_binding<T: Copy>(bindings: &[(VirtualKeyCode, T)], key: VirtualKeyCode) -> Option<T> {
+    bindings
+        .iter()
+        .find(|(binding, _)| *binding == key)
+        .map(|(_, target)| *target)
+}
+
+fn parse_section<T: FromStr<Err = String> + Copy>(
+    section: &str,
+    entries: &json::JsonValue,
+) -> Result<Vec<(VirtualKeyCode, T)>, String> {
+    if !entries.is_object() {
+        return Err(format!("{} should map names to keys", section));
+    }
+    let mut bindings = Vec::new();
+    for (name, keys) in entries.entries() {
+        let target = T::from_str(name).map_err(|e| format!("{}: {}", section, e))?;
+        let key_names: Vec<&json::JsonValue> = if keys.is_array() {
+            keys.members().collect()
+        } else {
+            vec![keys]
+        };
+        for key_name in key_names {
+            let key_name = key_name.as_str().ok_or(format!(
+                "{}.{}: expected a key name or a list of key names, got {}",
+                section, name, key_name
+            ))?;
+            let key =
+                parse_host_key(key_name).map_err(|e| format!("{}.{}: {}", section, name, e))?;
+            bindings.push((key, target));
+        }
+    }
+    Ok(bindings)
+}
@@ -1,17 +1,18 @@
+use crate::system::framebuffer::{Framebuffer, FramebufferFormat};
 use crate::system::ram::default_nonimplemented_memory_register_trait_impl;
 use crate::system::ram::lcd_registers::{
     BGPaletteRegister, LCDControlRegister, LCDStatusRegister, LYRegister, ScrollXRegister,
     ScrollYRegister,
 };
 use crate::system::ram::{MemoryRegister, RAM};
-use show_image::{create_window, ImageInfo, ImageView};
+use crate::system::video_sink::VideoSink;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 const VBLANK_PERIOD: std::time::Duration = std::time::Duration::from_millis(16);
 const BG_TILEMAP_SELECT_ADDRESSES: [u16; 2] = [0x9800, 0x9C00];
 const BG_WINDOW_TILEDATA_SELECT_ADDRESSES: [u16; 2] = [0x8800, 0x8000];
-const BG_SHADES: [u8; 4] = [255, 192, 64, 0];
+pub const BG_SHADES: [u8; 4] = [255, 192, 64, 0];
 const GB_SCREEN_WIDTH: usize = 160;
 const GB_SCREEN_HEIGHT: usize = 144;
 
@@ -73,61 +74,67 @@ impl LCDMode {
 
 struct LCDStateMachine {
     active_mode: LCDMode,
-    curr_mode_start: std::time::Instant,
-    last_vblank: std::time::Instant,
+    // timestamps since the controller started, in wall clock or emulated time
+    curr_mode_start: Duration,
+    last_vblank: Duration,
     current_line: u8,
-    last_line_time: std::time::Instant,
+    last_line_time: Duration,
 }
 
 impl LCDStateMachine {
     pub fn new() -> Self {
         LCDStateMachine {
             active_mode: LCDMode::OAM,
-            curr_mode_start: std::time::Instant::now(),
-            last_vblank: std::time::Instant::now(),
+            curr_mode_start: Duration::ZERO,
+            last_vblank: Duration::ZERO,
             current_line: 0,
-            last_line_time: std::time::Instant::now(),
+            last_line_time: Duration::ZERO,
         }
     }
 
-    pub fn next(&mut self) {
+    pub fn next(&mut self, now: Duration) {
         match self.active_mode {
             LCDMode::VBLANK => {
-                if self.curr_mode_start.elapsed() >= self.active_mode.get_mode_duration() {
-                    self.last_vblank = std::time::Instant::now();
+                if now.saturating_sub(self.curr_mode_start) >= self.active_mode.get_mode_duration()
+                {
+                    self.last_vblank = now;
                     self.current_line = 0;
-                    self.last_line_time = Instant::now();
+                    self.last_line_time = now;
                     //println!("End VBLANK");
-                } else if self.last_line_time.elapsed() > self.active_mode.get_line_duration() {
+                } else if now.saturating_sub(self.last_line_time)
+                    > self.active_mode.get_line_duration()
+                {
                     self.current_line = if self.current_line < 153 {
                         self.current_line + 1
                     } else {
                         self.current_line
                     };
-                    self.last_line_time = Instant::now();
+                    self.last_line_time = now;
                 }
             }
             _ => {
-                if self.last_vblank.elapsed() > VBLANK_PERIOD {
+                if now.saturating_sub(self.last_vblank) > VBLANK_PERIOD {
                     self.active_mode = LCDMode::VBLANK;
                     //println!("Start VBLANK");
-                    self.curr_mode_start = std::time::Instant::now();
+                    self.curr_mode_start = now;
                     self.current_line = GB_SCREEN_HEIGHT as u8;
-                    self.last_line_time = Instant::now();
-                } else if self.last_line_time.elapsed() > self.active_mode.get_line_duration() {
+                    self.last_line_time = now;
+                } else if now.saturating_sub(self.last_line_time)
+                    > self.active_mode.get_line_duration()
+                {
                     self.current_line = if self.current_line < GB_SCREEN_HEIGHT as u8 - 1 {
                         self.current_line + 1
                     } else {
                         self.current_line
                     };
-                    self.last_line_time = Instant::now()
+                    self.last_line_time = now
                 }
             }
         }
 
-        if self.curr_mode_start.elapsed() >= self.active_mode.get_mode_duration() {
+        if now.saturating_sub(self.curr_mode_start) >= self.active_mode.get_mode_duration() {
             self.active_mode = self.active_mode.get_next_state();
-            self.curr_mode_start = std::time::Instant::now();
+            self.curr_mode_start = now;
         }
     }
 
@@ -347,74 +354,76 @@ pub struct LCDController {
     lcd_status_register: LCDStatusRegister,
     ly_register: LYRegister,
     state_machine: LCDStateMachine,
-    pixel_data: Arc<Mutex<LCDImage>>,
-    thread_finished: Arc<Mutex<bool>>,
-    image_ready: Arc<Mutex<bool>>,
-    thread_handle: std::thread::JoinHandle<()>,
+    pixel_data: LCDImage,
+    video_sink: Box<dyn VideoSink>,
+    // a blank frame is presented once when the display is switched off
+    display_enabled: bool,
     should_draw: bool,
+    start: Instant,
+    frame_count: u64,
 }
 
 impl LCDController {
-    pub fn new(headless: bool) -> Self {
-        let thread_finished = std::sync::Arc::new(std::sync::Mutex::new(false));
-        let image_ready = std::sync::Arc::new(std::sync::Mutex::new(false));
-        let pixel_data = std::sync::Arc::new(std::sync::Mutex::new(LCDImage::new()));
+    pub fn new(video_sink: Box<dyn VideoSink>) -> Self {
         LCDController {
             lcd_control_register: LCDControlRegister::new(),
             lcd_status_register: LCDStatusRegister::new(),
             ly_register: LYRegister::new(),
             state_machine: LCDStateMachine::new(),
-            pixel_data: pixel_data.clone(),
-            thread_finished: thread_finished.clone(),
-            image_ready: image_ready.clone(),
-            thread_handle: std::thread::spawn(move || {
-                if headless {
-                    return;
-                }
-                let thread_finished_ref = thread_finished.clone();
-                let image_ready_ref = image_ready.clone();
-                let pixel_data_ref = pixel_data.clone();
-                let display_window = create_window("GameBoy Screen", Default::default()).unwrap();
-                let mut prev_cycle_time = std::time::Instant::now();
-                loop {
-                    if *thread_finished_ref.lock().unwrap() {
-                        break;
-                    }
-                    {
-                        let mut ready = image_ready_ref.lock().unwrap();
-                        if *ready {
-                            let current_data = pixel_data_ref.lock().unwrap().get_data();
-                            let image = ImageView::new(
-                                ImageInfo::mono8(GB_SCREEN_WIDTH as u32, GB_SCREEN_HEIGHT as u32),
-                                &current_data,
-                            );
-                            let fps = 1e9 / prev_cycle_time.elapsed().as_nanos() as f64;
-                            display_window
-                                .set_image(format!("GameBoy Screen {:.2} fps", fps), image)
-                                .unwrap();
-                            //println!("Drawing {:.2}fps", fps);
-                            prev_cycle_time = std::time::Instant::now();
-                            *ready = false;
-                        }
-                    }
-                    std::thread::sleep(std::time::Duration::from_millis(2));
-                }
-                println!("outside display loop")
-            }),
+            pixel_data: LCDImage::new(),
+            video_sink,
+            display_enabled: false,
             should_draw: false,
+            start: Instant::now(),
+            frame_count: 0,
         }
     }
 
+    // grey levels of the last drawn frame, one byte per pixel
+    pub fn get_image_data(&self) -> Vec<u8> {
+        self.pixel_data.get_data().to_vec()
+    }
+
+    // frames drawn so far, counted when the LCD enters VBLANK
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn set_video_sink(&mut self, video_sink: Box<dyn VideoSink>) {
+        self.video_sink.close();
+        self.video_sink = video_sink;
+    }
+
+    fn present_frame(&mut self) {
+        let frame = Framebuffer::from_grey(
+            &self.pixel_data.get_data(),
+            FramebufferFormat::Grey8,
+            self.frame_count,
+        );
+        self.video_sink.present(&frame);
+    }
+
     pub fn next(&mut self, ram: &Arc<Mutex<RAM>>) {
+        let now = self.start.elapsed();
+        self.next_at(ram, now);
+    }
+
+    // same as next, with the mode timings measured against the given time instead of the wall
+    // clock. Driven from the CPU cycle count this makes the output reproducible.
+    pub fn next_at(&mut self, ram: &Arc<Mutex<RAM>>, now: Duration) {
         let mut ram = ram.lock().unwrap();
         self.read_from_ram(&ram);
 
         if !self.lcd_control_register.get_lcd_display_enable() {
-            self.pixel_data.lock().unwrap().reset();
-            *self.image_ready.lock().unwrap() = true;
+            self.pixel_data.reset();
+            if self.display_enabled {
+                self.display_enabled = false;
+                self.present_frame();
+            }
         } else {
+            self.display_enabled = true;
             {
-                let mut pixel_data_ref = self.pixel_data.lock().unwrap();
+                let pixel_data_ref = &mut self.pixel_data;
                 pixel_data_ref.read_from_ram(&ram);
                 pixel_data_ref.set_bg_vram_address(
                     BG_TILEMAP_SELECT_ADDRESSES
@@ -428,17 +437,18 @@ impl LCDController {
 
             match self.state_machine.get_active_mode() {
                 LCDMode::TX => {
-                    self.pixel_data.lock().unwrap().read_tilemap(&ram);
+                    self.pixel_data.read_tilemap(&ram);
                 }
                 LCDMode::VBLANK => {
                     if self.should_draw {
-                        self.pixel_data.lock().unwrap().draw(
+                        self.pixel_data.draw(
                             self.lcd_control_register.get_bg_display_enable(),
                             self.lcd_control_register.get_window_display_enable(),
                             self.lcd_control_register.get_sprite_display_enable(),
                         );
-                        *self.image_ready.lock().unwrap() = true;
                         self.should_draw = false;
+                        self.frame_count += 1;
+                        self.present_frame();
                     }
                 }
                 LCDMode::OAM => {
@@ -446,20 +456,18 @@ impl LCDController {
                 }
                 _ => {}
             }
-            self.state_machine.next();
+            self.state_machine.next(now);
             self.lcd_status_register
                 .set_status(self.state_machine.get_active_mode().get_status_byte());
             self.ly_register
                 .set_line(self.state_machine.get_current_line());
             self.load_in_ram(&mut ram);
-            self.pixel_data.lock().unwrap().load_in_ram(&mut ram);
+            self.pixel_data.load_in_ram(&mut ram);
         }
     }
 
-    pub fn stop_window_thread(&self) {
-        let mut reference = self.thread_finished.lock().unwrap();
-        *reference = true;
-        std::thread::sleep(std::time::Duration::from_millis(32));
+    pub fn close_video_sink(&mut self) {
+        self.video_sink.close();
     }
 }
 
@@ -1,2 +1,3 @@
+pub mod key_bindings;
 pub mod lcd_controller;
 pub mod sound_controller;
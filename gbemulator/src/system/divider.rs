@@ -0,0 +1,50 @@
+pub const DIV_ADDRESS: u16 = 0xFF04;
+
+// bit 4 of DIV, the frame sequencer of the APU steps when it goes from 1 to 0
+pub const FRAME_SEQUENCER_BIT: u16 = 0x1000;
+const T_CYCLES_PER_M_CYCLE: u16 = 4;
+
+// DIV is the upper byte of a 16 bit counter going up every T-cycle, so it counts at 16384 Hz.
+// Writing any value to it clears the whole counter.
+#[derive(Clone)]
+pub struct Divider {
+    counter: u16,
+}
+
+impl Divider {
+    pub fn new() -> Self {
+        Divider { counter: 0 }
+    }
+
+    pub fn read(&self) -> u8 {
+        (self.counter >> 8) as u8
+    }
+
+    // true when clearing the counter made bit 4 of DIV fall, which steps the frame sequencer
+    pub fn write(&mut self) -> bool {
+        let falling_edge = self.counter & FRAME_SEQUENCER_BIT != 0;
+        self.counter = 0;
+        falling_edge
+    }
+
+    // for states that were not reached by running, like the state after the boot ROM
+    pub fn load(&mut self, div: u8) {
+        self.counter = (div as u16) << 8;
+    }
+
+    pub fn get_counter(&self) -> u16 {
+        self.counter
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        self.counter = self
+            .counter
+            .wrapping_add((cycles as u16).wrapping_mul(T_CYCLES_PER_M_CYCLE));
+    }
+}
+
+impl Default for Divider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,74 @@
+use super::controllers::lcd_controller::BG_SHADES;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FramebufferFormat {
+    // 0 to 3, 0 the lightest, as the palettes of the Game Boy see them
+    ShadeIndex,
+    // one grey level per pixel, 255 is white
+    Grey8,
+    // 4 bytes per pixel in red, green, blue, alpha order, alpha is always 255
+    Rgba8888,
+}
+
+impl FramebufferFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            FramebufferFormat::ShadeIndex | FramebufferFormat::Grey8 => 1,
+            FramebufferFormat::Rgba8888 => 4,
+        }
+    }
+}
+
+// The shade of a grey level, 0 the lightest. Images from other emulators come with their own
+// palettes, greys that are not one of ours are quantised to the nearest shade.
+pub fn shade_of_grey(grey: u8) -> u8 {
+    match BG_SHADES.iter().position(|shade| *shade == grey) {
+        Some(shade) => shade as u8,
+        None => 3 - ((grey as u16 * 3 + 127) / 255) as u8,
+    }
+}
+
+// a 160x144 frame, row by row from the top left pixel
+#[derive(Clone, PartialEq, Debug)]
+pub struct Framebuffer {
+    pub format: FramebufferFormat,
+    // the number of frames the LCD had drawn when this one was taken
+    pub frame: u64,
+    pub data: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn from_grey(grey: &[u8], format: FramebufferFormat, frame: u64) -> Self {
+        let data = match format {
+            FramebufferFormat::ShadeIndex => grey.iter().map(|grey| shade_of_grey(*grey)).collect(),
+            FramebufferFormat::Grey8 => grey.to_vec(),
+            FramebufferFormat::Rgba8888 => grey
+                .iter()
+                .flat_map(|grey| [*grey, *grey, *grey, 255])
+                .collect(),
+        };
+        Framebuffer {
+            format,
+            frame,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        SCREEN_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        SCREEN_HEIGHT
+    }
+
+    // the bytes of one pixel, in the format of the buffer
+    pub fn pixel(&self, x: usize, y: usize) -> &[u8] {
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let start = (y * SCREEN_WIDTH + x) * bytes_per_pixel;
+        &self.data[start..start + bytes_per_pixel]
+    }
+}
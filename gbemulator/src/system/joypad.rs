@@ -0,0 +1,163 @@
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+pub const JOYPAD_ADDRESS: u16 = 0xFF00;
+// bit 4 of IF
+pub const JOYPAD_INTERRUPT: u8 = 0x10;
+
+// P14 low selects the directions, P15 low selects the action buttons
+const SELECT_DIRECTIONS: u8 = 0x10;
+const SELECT_ACTIONS: u8 = 0x20;
+const SELECT_MASK: u8 = SELECT_DIRECTIONS | SELECT_ACTIONS;
+const UNUSED_BITS: u8 = 0xC0;
+const LINES_MASK: u8 = 0x0F;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    // the directions sit in the low nibble and the action buttons in the high one, each one on
+    // the same P10-P13 line as its counterpart in the other group
+    fn mask(&self) -> u8 {
+        match self {
+            Button::Right => 0x01,
+            Button::Left => 0x02,
+            Button::Up => 0x04,
+            Button::Down => 0x08,
+            Button::A => 0x10,
+            Button::B => 0x20,
+            Button::Select => 0x40,
+            Button::Start => 0x80,
+        }
+    }
+}
+
+impl FromStr for Button {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "right" => Ok(Button::Right),
+            "left" => Ok(Button::Left),
+            "up" => Ok(Button::Up),
+            "down" => Ok(Button::Down),
+            "a" => Ok(Button::A),
+            "b" => Ok(Button::B),
+            "select" => Ok(Button::Select),
+            "start" => Ok(Button::Start),
+            _ => Err(format!(
+                "unknown button {}, expected one of right, left, up, down, a, b, select, start",
+                s
+            )),
+        }
+    }
+}
+
+// Buttons held on the host, shared between the frontend and the emulated joypad
+#[derive(Clone, Default)]
+pub struct JoypadInput {
+    pressed: Arc<AtomicU8>,
+}
+
+impl JoypadInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_button(&self, button: Button, pressed: bool) {
+        if pressed {
+            self.pressed.fetch_or(button.mask(), Ordering::Relaxed);
+        } else {
+            self.pressed.fetch_and(!button.mask(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.load(Ordering::Relaxed) & button.mask() != 0
+    }
+
+    // one bit per button, Right, Left, Up, Down, A, B, Select and Start from bit 0 up
+    pub fn get_state(&self) -> u8 {
+        self.pressed.load(Ordering::Relaxed)
+    }
+
+    pub fn set_state(&self, state: u8) {
+        self.pressed.store(state, Ordering::Relaxed);
+    }
+}
+
+// The P1 register. The CPU selects a button group by pulling P14 or P15 low and reads the
+// state of that group on P10-P13, where a pressed button pulls its line low. Any selected line
+// going from high to low requests the joypad interrupt.
+#[derive(Clone)]
+pub struct Joypad {
+    select: u8,
+    // buttons as last seen by the emulated hardware
+    pressed: u8,
+    input: JoypadInput,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Joypad {
+            select: 0x00,
+            pressed: 0x00,
+            input: JoypadInput::new(),
+        }
+    }
+
+    pub fn get_input(&self) -> JoypadInput {
+        self.input.clone()
+    }
+
+    pub fn read(&self) -> u8 {
+        UNUSED_BITS | self.select | self.lines()
+    }
+
+    // only the select bits are writable, returns true if a line went low
+    pub fn write(&mut self, value: u8) -> bool {
+        let previous_lines = self.lines();
+        self.select = value & SELECT_MASK;
+        Joypad::has_falling_edge(previous_lines, self.lines())
+    }
+
+    // picks up the buttons changed by the frontend, returns true if a line went low
+    pub fn update(&mut self) -> bool {
+        let previous_lines = self.lines();
+        self.pressed = self.input.get_state();
+        Joypad::has_falling_edge(previous_lines, self.lines())
+    }
+
+    fn lines(&self) -> u8 {
+        let mut low = 0x00;
+        if self.select & SELECT_DIRECTIONS == 0 {
+            low |= self.pressed & LINES_MASK;
+        }
+        if self.select & SELECT_ACTIONS == 0 {
+            low |= self.pressed >> 4;
+        }
+        !low & LINES_MASK
+    }
+
+    fn has_falling_edge(previous_lines: u8, lines: u8) -> bool {
+        previous_lines & !lines != 0
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+const TRANSFER_TAG: u8 = 0x01;
+const REPLY_TAG: u8 = 0x02;
+const CLOCK_TAG: u8 = 0x03;
+// a tag, a byte and a cycle
+const MESSAGE_SIZE: usize = 10;
+
+// Cycles are M-cycles of the emulated time of the sender, counted by its serial port.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LinkMessage {
+    // the side providing the clock starts shifting this byte out, the 8 bits are done on cycle
+    Transfer { byte: u8, cycle: u64 },
+    // the other side answers with the byte it shifted out at the same time
+    Reply(u8),
+    // the emulated time of the sender reached this cycle
+    Clock(u64),
+}
+
+impl LinkMessage {
+    fn encode(&self) -> [u8; MESSAGE_SIZE] {
+        let (tag, byte, cycle) = match self {
+            LinkMessage::Transfer { byte, cycle } => (TRANSFER_TAG, *byte, *cycle),
+            LinkMessage::Reply(byte) => (REPLY_TAG, *byte, 0),
+            LinkMessage::Clock(cycle) => (CLOCK_TAG, 0, *cycle),
+        };
+        let mut bytes = [0; MESSAGE_SIZE];
+        bytes[0] = tag;
+        bytes[1] = byte;
+        bytes[2..].copy_from_slice(&cycle.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: [u8; MESSAGE_SIZE]) -> Option<Self> {
+        let cycle = u64::from_le_bytes(bytes[2..].try_into().unwrap());
+        match bytes[0] {
+            TRANSFER_TAG => Some(LinkMessage::Transfer {
+                byte: bytes[1],
+                cycle,
+            }),
+            REPLY_TAG => Some(LinkMessage::Reply(bytes[1])),
+            CLOCK_TAG => Some(LinkMessage::Clock(cycle)),
+            _ => None,
+        }
+    }
+}
+
+// One end of the cable between two Game Boys. The side providing the clock sends a Transfer
+// as it starts shifting a byte out, and both sides exchange their bytes on the cycle it is
+// stamped with, see Serial::tick. Neither end ever blocks, a side that has to wait for the
+// other one says so with Serial::is_stalled and the CPU thread waits outside of the RAM lock.
+pub trait LinkCable: Send {
+    fn send(&mut self, message: LinkMessage);
+
+    // returns right away
+    fn receive(&mut self) -> Option<LinkMessage>;
+
+    // false once the other end went away, the serial port then behaves as if unplugged
+    fn is_connected(&self) -> bool;
+
+    // Whether the other end is an emulated Game Boy sending Clock messages, the two are then
+    // kept within a byte of each other. Devices answering straight away, like the printer,
+    // have no emulated time to keep up with.
+    fn sends_clock(&self) -> bool {
+        true
+    }
+}
+
+// Both ends of a cable inside the same process, for tests and for running two systems side by
+// side. Neither end blocks, so the two systems can be stepped in turn on one thread.
+pub struct LocalLinkCable {
+    sender: Sender<LinkMessage>,
+    receiver: Receiver<LinkMessage>,
+    connected: bool,
+}
+
+impl LocalLinkCable {
+    pub fn pair() -> (Self, Self) {
+        let (first_sender, second_receiver) = channel();
+        let (second_sender, first_receiver) = channel();
+        (
+            LocalLinkCable {
+                sender: first_sender,
+                receiver: first_receiver,
+                connected: true,
+            },
+            LocalLinkCable {
+                sender: second_sender,
+                receiver: second_receiver,
+                connected: true,
+            },
+        )
+    }
+}
+
+impl LinkCable for LocalLinkCable {
+    fn send(&mut self, message: LinkMessage) {
+        if self.sender.send(message).is_err() {
+            self.connected = false;
+        }
+    }
+
+    fn receive(&mut self) -> Option<LinkMessage> {
+        match self.receiver.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.connected = false;
+                None
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+// A cable to another emulator over a stream socket, messages are a tag, the byte shifted out
+// and a little endian cycle. A thread reads the socket so that receiving never blocks.
+pub struct SocketLinkCable {
+    writer: Box<dyn Write + Send>,
+    receiver: Receiver<LinkMessage>,
+    connected: bool,
+    // the reading thread holds its own handle to the socket, dropping ours does not close it
+    shutdown: Option<Box<dyn Fn() + Send>>,
+}
+
+impl SocketLinkCable {
+    pub fn from_streams<R, W>(mut reader: R, writer: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            let mut bytes = [0; MESSAGE_SIZE];
+            while reader.read_exact(&mut bytes).is_ok() {
+                match LinkMessage::decode(bytes) {
+                    Some(message) if sender.send(message).is_ok() => {}
+                    _ => break,
+                }
+            }
+        });
+        SocketLinkCable {
+            writer: Box::new(writer),
+            receiver,
+            connected: true,
+            shutdown: None,
+        }
+    }
+
+    pub fn connect_tcp(address: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("unable to connect to {}: {}", address, e))?;
+        Self::from_tcp_stream(stream)
+    }
+
+    // waits for the other emulator to connect
+    pub fn listen_tcp(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("unable to listen on {}: {}", address, e))?;
+        Self::accept_tcp(&listener)
+    }
+
+    pub fn accept_tcp(listener: &TcpListener) -> Result<Self, String> {
+        let (stream, _) = listener
+            .accept()
+            .map_err(|e| format!("unable to accept a link cable: {}", e))?;
+        Self::from_tcp_stream(stream)
+    }
+
+    fn from_tcp_stream(stream: TcpStream) -> Result<Self, String> {
+        // a message per byte, they should not wait for more to fill a packet
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+        let socket = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut link_cable = Self::from_streams(reader, stream);
+        link_cable.shutdown = Some(Box::new(move || {
+            let _ = socket.shutdown(std::net::Shutdown::Both);
+        }));
+        Ok(link_cable)
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix(path: &str) -> Result<Self, String> {
+        let stream = std::os::unix::net::UnixStream::connect(path)
+            .map_err(|e| format!("unable to connect to {}: {}", path, e))?;
+        Self::from_unix_stream(stream)
+    }
+
+    // waits for the other emulator to connect, the socket file is replaced if it exists
+    #[cfg(unix)]
+    pub fn listen_unix(path: &str) -> Result<Self, String> {
+        let _ = std::fs::remove_file(path);
+        let listener = std::os::unix::net::UnixListener::bind(path)
+            .map_err(|e| format!("unable to listen on {}: {}", path, e))?;
+        let (stream, _) = listener
+            .accept()
+            .map_err(|e| format!("unable to accept a link cable: {}", e))?;
+        Self::from_unix_stream(stream)
+    }
+
+    #[cfg(unix)]
+    fn from_unix_stream(stream: std::os::unix::net::UnixStream) -> Result<Self, String> {
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+        let socket = stream.try_clone().map_err(|e| e.to_string())?;
+        let mut link_cable = Self::from_streams(reader, stream);
+        link_cable.shutdown = Some(Box::new(move || {
+            let _ = socket.shutdown(std::net::Shutdown::Both);
+        }));
+        Ok(link_cable)
+    }
+}
+
+impl LinkCable for SocketLinkCable {
+    fn send(&mut self, message: LinkMessage) {
+        if self.writer.write_all(&message.encode()).is_err() || self.writer.flush().is_err() {
+            self.connected = false;
+        }
+    }
+
+    fn receive(&mut self) -> Option<LinkMessage> {
+        match self.receiver.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.connected = false;
+                None
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+impl Drop for SocketLinkCable {
+    fn drop(&mut self) {
+        if let Some(shutdown) = &self.shutdown {
+            shutdown();
+        }
+    }
+}
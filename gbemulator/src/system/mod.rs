@@ -1,17 +1,129 @@
+pub mod apu;
+pub mod audio_sink;
+pub mod boot;
 pub mod controllers;
+pub mod divider;
+pub mod framebuffer;
+pub mod joypad;
+pub mod link_cable;
 pub mod master_clock;
+pub mod movie;
+pub mod printer;
 pub mod ram;
+pub mod screenshot;
+pub mod serial;
 pub mod sm83;
+pub mod test_rom;
+pub mod video_sink;
 
+use audio_sink::{AudioSink, NullAudioSink, RodioAudioSink, DEFAULT_SAMPLE_RATE};
+use boot::{BootMode, GameBoyModel};
+use controllers::key_bindings::{HotkeyState, KeyBindings};
+use controllers::lcd_controller::LCDController;
+use framebuffer::{Framebuffer, FramebufferFormat};
+use joypad::{Button, JoypadInput};
+use link_cable::LinkCable;
 use master_clock::MasterClock;
-use ram::MemoryRegister;
+use movie::{fnv1a_hash, Movie};
+use serial::SerialCallback;
+use sm83::bus_trace::BusCycle;
+use sm83::registers::RegisterName;
 use sm83::snapshot::SM83Snapshot;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc, Mutex,
 };
+use std::time::Duration;
+use test_rom::{result_from_serial_output, SerialVerdict, TestRomResult};
+use video_sink::{NullVideoSink, VideoSink, WindowSink};
 
-use crate::system::ram::mapping_chip::DynamicMappingChip;
+use crate::system::ram::mapping_chip::rtc::ManualClock;
+use crate::system::ram::mapping_chip::{battery_save_path, DynamicMappingChip};
+
+// 70224 dots per frame, 4 dots per M-cycle
+pub const M_CYCLES_PER_FRAME: u128 = 17556;
+const M_CYCLE_NANOS: f64 = 1e9 / 1_048_576.0;
+// how often the LCD is stepped when clocked from the cycle count, close to the MasterClock rate
+const LCD_STEP_M_CYCLES: u128 = 20;
+// how long the CPU thread sleeps, out of the RAM lock, while the link cable partner catches up
+const LINK_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+// Runs the CPU up to the next frame boundary with the LCD clocked from the cycle count instead
+// of the wall clock, so the result only depends on the state and the inputs. Returns the number
+// of instructions executed.
+fn emulate_frame(
+    cpu: &Mutex<sm83::SM83>,
+    ram: &Arc<Mutex<ram::RAM>>,
+    lcd: &Mutex<LCDController>,
+) -> usize {
+    let mut cycle = cpu.lock().unwrap().cycle_count;
+    let frame_end = (cycle / M_CYCLES_PER_FRAME + 1) * M_CYCLES_PER_FRAME;
+    let mut instructions = 0;
+    while cycle < frame_end {
+        match emulate_instruction(cpu, ram, lcd) {
+            Some(next_cycle) => {
+                cycle = next_cycle;
+                instructions += 1;
+            }
+            None => std::thread::sleep(LINK_POLL_INTERVAL),
+        }
+    }
+    instructions
+}
+
+// Runs one instruction and clocks the LCD from the cycle count, returns the new cycle count.
+// Nothing runs while the link cable waits for the other Game Boy, then None is returned.
+fn emulate_instruction(
+    cpu: &Mutex<sm83::SM83>,
+    ram: &Arc<Mutex<ram::RAM>>,
+    lcd: &Mutex<LCDController>,
+) -> Option<u128> {
+    let (previous_cycle, cycle) = {
+        let mut ram = ram.lock().unwrap();
+        if ram.is_link_stalled() {
+            ram.poll_link();
+            return None;
+        }
+        let mut cpu = cpu.lock().unwrap();
+        let previous_cycle = cpu.cycle_count;
+        cpu.next(&mut ram);
+        ram.tick((cpu.cycle_count - previous_cycle) as u64);
+        (previous_cycle, cpu.cycle_count)
+    };
+    if cycle / LCD_STEP_M_CYCLES != previous_cycle / LCD_STEP_M_CYCLES {
+        let now = Duration::from_nanos((cycle as f64 * M_CYCLE_NANOS) as u64);
+        lcd.lock().unwrap().next_at(ram, now);
+    }
+    Some(cycle)
+}
+
+// keeps a copy of the RAM and of the CPU, the snapshot includes the already fetched opcode
+fn save_state(
+    cpu: &Mutex<sm83::SM83>,
+    ram: &Mutex<ram::RAM>,
+    saved_state: &Mutex<Option<(ram::RAM, SM83Snapshot)>>,
+) {
+    let ram = ram.lock().unwrap().clone();
+    let snapshot = cpu.lock().unwrap().to_snapshot();
+    *saved_state.lock().unwrap() = Some((ram, snapshot));
+}
+
+// puts back the last saved state, false when there is none
+fn load_state(
+    cpu: &Mutex<sm83::SM83>,
+    ram: &Mutex<ram::RAM>,
+    saved_state: &Mutex<Option<(ram::RAM, SM83Snapshot)>>,
+) -> bool {
+    let (saved_ram, snapshot) = match saved_state.lock().unwrap().clone() {
+        Some(state) => state,
+        None => return false,
+    };
+    let mut ram = ram.lock().unwrap();
+    let mut cpu = cpu.lock().unwrap();
+    *ram = saved_ram;
+    cpu.load_snapshot(snapshot);
+    true
+}
 
 fn format_frequency(frequency: f32) -> String {
     if frequency < 1e3 {
@@ -27,79 +139,221 @@ fn format_frequency(frequency: f32) -> String {
     }
 }
 
+// The window and the audio output of an interactive run, unless other sinks were given, the
+// ones replaced are never opened. Headless systems get a null video sink and no sound
+// controller, so they never touch a display or an audio device.
+fn front_end(
+    headless: bool,
+    video_sink: Option<Box<dyn VideoSink>>,
+    audio_sink: Option<Box<dyn AudioSink>>,
+    joypad_input: &JoypadInput,
+    hotkeys: &HotkeyState,
+    key_bindings: &Arc<Mutex<KeyBindings>>,
+) -> (
+    Box<dyn VideoSink>,
+    Option<Arc<Mutex<controllers::sound_controller::SoundController>>>,
+) {
+    let video_sink: Box<dyn VideoSink> = match video_sink {
+        Some(video_sink) => video_sink,
+        None if headless => Box::new(NullVideoSink),
+        None => Box::new(WindowSink::new(
+            joypad_input.clone(),
+            hotkeys.clone(),
+            key_bindings.clone(),
+        )),
+    };
+    let audio_sink: Box<dyn AudioSink> = match audio_sink {
+        Some(audio_sink) => audio_sink,
+        None if headless => return (video_sink, None),
+        None => match RodioAudioSink::new(DEFAULT_SAMPLE_RATE) {
+            Ok(audio_sink) => Box::new(audio_sink),
+            Err(e) => {
+                println!("{}, sound is disabled", e);
+                Box::new(NullAudioSink::default())
+            }
+        },
+    };
+    let sound_controller = controllers::sound_controller::SoundController::new(audio_sink);
+    (video_sink, Some(Arc::new(Mutex::new(sound_controller))))
+}
+
 pub struct System {
     cpu: Arc<Mutex<sm83::SM83>>,
     ram: Arc<Mutex<ram::RAM>>,
-    boot_rom: ram::BootRom,
-    bootlock_register: Arc<Mutex<ram::BootLockMemoryRegister>>,
+    boot_mode: BootMode,
     lcd_controller: Arc<Mutex<controllers::lcd_controller::LCDController>>,
-    sound_controller: Arc<Mutex<controllers::sound_controller::SoundController>>,
-    should_reload_cartridge: bool,
+    // none when headless and no audio sink was given, so that no audio device is opened
+    sound_controller: Option<Arc<Mutex<controllers::sound_controller::SoundController>>>,
     master_clock: MasterClock,
     cpu_ready: Arc<AtomicBool>,
     lcd_ready: Arc<AtomicBool>,
     sound_ready: Arc<AtomicBool>,
+    hotkeys: HotkeyState,
+    key_bindings: Arc<Mutex<KeyBindings>>,
+    saved_state: Arc<Mutex<Option<(ram::RAM, SM83Snapshot)>>>,
+    // buttons held on the host, copied into the emulated joypad by the CPU thread
+    joypad_input: JoypadInput,
+    movie_recording: Option<(String, Movie, ManualClock)>,
 }
 
 impl System {
     pub fn new(dynamic_chip: Option<DynamicMappingChip>, headless: bool) -> System {
-        return System {
-            cpu: Arc::new(Mutex::new(sm83::SM83::new())),
-            ram: Arc::new(Mutex::new(ram::RAM::new(dynamic_chip))),
-            boot_rom: ram::BootRom::new(),
-            bootlock_register: Arc::new(Mutex::new(ram::BootLockMemoryRegister::new())),
-            lcd_controller: Arc::new(Mutex::new(controllers::lcd_controller::LCDController::new(
-                headless,
-            ))),
-            sound_controller: Arc::new(Mutex::new(
-                controllers::sound_controller::SoundController::new(),
-            )),
-            should_reload_cartridge: false,
-            master_clock: MasterClock::new(),
-            cpu_ready: Arc::new(AtomicBool::new(false)),
-            lcd_ready: Arc::new(AtomicBool::new(false)),
-            sound_ready: Arc::new(AtomicBool::new(false)),
-        };
+        System::build(
+            sm83::SM83::new(),
+            ram::RAM::new(dynamic_chip),
+            headless,
+            None,
+            None,
+        )
     }
 
     pub fn from_ram_snapshot(ram: ram::RAM, snapshot: SM83Snapshot, headless: bool) -> System {
         let mut cpu = sm83::SM83::new();
         cpu.load_snapshot(snapshot);
         cpu.fetch_cycle(&ram);
-        return System {
+        System::build(cpu, ram, headless, None, None)
+    }
+
+    // an interactive system showing and playing on the given sinks instead of the window and
+    // the audio device, which are then not opened at all
+    pub fn with_sinks(
+        dynamic_chip: Option<DynamicMappingChip>,
+        video_sink: Option<Box<dyn VideoSink>>,
+        audio_sink: Option<Box<dyn AudioSink>>,
+    ) -> System {
+        System::build(
+            sm83::SM83::new(),
+            ram::RAM::new(dynamic_chip),
+            false,
+            video_sink,
+            audio_sink,
+        )
+    }
+
+    fn build(
+        cpu: sm83::SM83,
+        ram: ram::RAM,
+        headless: bool,
+        video_sink: Option<Box<dyn VideoSink>>,
+        audio_sink: Option<Box<dyn AudioSink>>,
+    ) -> System {
+        let joypad_input = JoypadInput::new();
+        let hotkeys = HotkeyState::new();
+        let key_bindings = Arc::new(Mutex::new(KeyBindings::new()));
+        let (video_sink, sound_controller) = front_end(
+            headless,
+            video_sink,
+            audio_sink,
+            &joypad_input,
+            &hotkeys,
+            &key_bindings,
+        );
+        let system = System {
             cpu: Arc::new(Mutex::new(cpu)),
             ram: Arc::new(Mutex::new(ram)),
-            boot_rom: ram::BootRom::new(),
-            bootlock_register: Arc::new(Mutex::new(ram::BootLockMemoryRegister::new())),
-            lcd_controller: Arc::new(Mutex::new(controllers::lcd_controller::LCDController::new(
-                headless,
-            ))),
-            sound_controller: Arc::new(Mutex::new(
-                controllers::sound_controller::SoundController::new(),
-            )),
-            should_reload_cartridge: false,
+            boot_mode: BootMode::BootRom(ram::BootRom::new()),
+            lcd_controller: Arc::new(Mutex::new(LCDController::new(video_sink))),
+            sound_controller,
             master_clock: MasterClock::new(),
             cpu_ready: Arc::new(AtomicBool::new(false)),
             lcd_ready: Arc::new(AtomicBool::new(false)),
             sound_ready: Arc::new(AtomicBool::new(false)),
+            hotkeys,
+            key_bindings,
+            saved_state: Arc::new(Mutex::new(None)),
+            joypad_input,
+            movie_recording: None,
         };
+        system.attach_sound_controller();
+        system
+    }
+
+    // the APU renders at the rate of the audio sink, when there is one
+    fn attach_sound_controller(&self) {
+        if let Some(sound_controller) = &self.sound_controller {
+            sound_controller
+                .lock()
+                .unwrap()
+                .attach(&mut self.ram.lock().unwrap());
+        }
+    }
+
+    // hands what the APU rendered so far to the audio sink
+    fn drain_sound(&self) {
+        if let Some(sound_controller) = &self.sound_controller {
+            sound_controller.lock().unwrap().next(&self.ram);
+        }
+    }
+
+    pub fn with_boot_rom(mut self, boot_rom: ram::BootRom) -> Self {
+        self.boot_mode = BootMode::BootRom(boot_rom);
+        self
+    }
+
+    pub fn with_skip_boot(mut self, model: GameBoyModel) -> Self {
+        self.boot_mode = BootMode::Skip(model);
+        self
+    }
+
+    pub fn with_key_bindings(self, key_bindings: KeyBindings) -> Self {
+        *self.key_bindings.lock().unwrap() = key_bindings;
+        self
+    }
+
+    // frames go to the given sink instead of the window, or the null sink when headless
+    pub fn with_video_sink<S: VideoSink + 'static>(self, video_sink: S) -> Self {
+        self.lcd_controller
+            .lock()
+            .unwrap()
+            .set_video_sink(Box::new(video_sink));
+        self
+    }
+
+    // records the joypad into a movie written to the given path when run returns. While
+    // recording the LCD is clocked from the CPU and the cartridge clock from the frames, so that
+    // the run can be reproduced with Movie::play.
+    pub fn with_movie_recording(mut self, path: &str) -> Result<Self, String> {
+        let movie = Movie::for_system(&self)?;
+        let clock = movie.start_cartridge(self.ram.lock().unwrap().get_mapping_chip_mut());
+        self.movie_recording = Some((path.to_string(), movie, clock));
+        Ok(self)
+    }
+
+    pub fn with_serial_callback(self, callback: SerialCallback) -> Self {
+        self.ram.lock().unwrap().set_serial_callback(callback);
+        self
+    }
+
+    pub fn with_link_cable<C: LinkCable + 'static>(self, link_cable: C) -> Self {
+        self.ram.lock().unwrap().set_link_cable(link_cable);
+        self
+    }
+
+    // the CPU records its bus activity, see SM83::get_bus_trace
+    pub fn with_bus_tracing(self) -> Self {
+        self.cpu.lock().unwrap().set_bus_tracing(true);
+        self
+    }
+
+    pub fn get_bus_trace(&self) -> Vec<BusCycle> {
+        self.cpu.lock().unwrap().get_bus_trace().to_vec()
     }
 
     pub fn next(&mut self) {
         let mut ram = self.ram.lock().unwrap();
         let mut cpu = self.cpu.lock().unwrap();
+        let start_cycle = cpu.cycle_count;
         cpu.next(&mut ram);
+        ram.tick((cpu.cycle_count - start_cycle) as u64);
     }
 
     pub fn run(mut self, n_iter: usize) -> Self {
         self.boot();
         let cpu_ram_ref: Arc<Mutex<ram::RAM>> = self.ram.clone();
-        let bootlocker_ref = self.bootlock_register.clone();
         let cpu_ref = self.cpu.clone();
         let lcd_ram_ref = self.ram.clone();
         let lcd_ref = self.lcd_controller.clone();
         let sound_ram_ref = self.ram.clone();
-        let sound_ref = self.sound_controller.clone();
         let loop_finished_ref = Arc::new(AtomicBool::new(false));
         let cpu_loop_finished_ref = loop_finished_ref.clone();
         let lcd_loop_finished_ref = loop_finished_ref.clone();
@@ -107,21 +361,73 @@ impl System {
         let cpu_ready_ref = self.cpu_ready.clone();
         let lcd_ready_ref = self.lcd_ready.clone();
         let sound_ready_ref = self.sound_ready.clone();
+        let cpu_hotkeys_ref = self.hotkeys.clone();
+        let lcd_hotkeys_ref = self.hotkeys.clone();
+        let saved_state_ref = self.saved_state.clone();
+        let cpu_lcd_ref = self.lcd_controller.clone();
+        let joypad_input_ref = self.joypad_input.clone();
+        let mut movie_recording = self.movie_recording.take();
+        let recording = movie_recording.is_some();
+        let frame_duration =
+            Duration::from_nanos((M_CYCLES_PER_FRAME as f64 * M_CYCLE_NANOS) as u64);
         let cpu_thread_handle = std::thread::spawn(move || {
             let start = std::time::Instant::now();
-            for _ in 0..n_iter {
+            let mut next_frame = std::time::Instant::now();
+            let mut iteration = 0;
+            while iteration < n_iter {
+                if cpu_hotkeys_ref.take_save_state_request() {
+                    save_state(&cpu_ref, &cpu_ram_ref, &saved_state_ref);
+                    println!("state saved");
+                }
+                if cpu_hotkeys_ref.take_load_state_request() {
+                    // the movie only has the inputs, it could not go back in time with the run
+                    if recording {
+                        println!("states cannot be loaded while recording a movie");
+                    } else if load_state(&cpu_ref, &cpu_ram_ref, &saved_state_ref) {
+                        println!("state loaded");
+                    } else {
+                        println!("no state saved yet");
+                    }
+                }
+                // paused time does not count towards the iteration budget
+                if cpu_hotkeys_ref.is_paused() {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    next_frame = std::time::Instant::now();
+                    continue;
+                }
+                if let Some((_, movie, clock)) = movie_recording.as_mut() {
+                    // the joypad only changes on frame boundaries, where playback applies it
+                    let state = joypad_input_ref.get_state();
+                    cpu_ram_ref.lock().unwrap().set_joypad_state(state);
+                    movie.record_frame(state);
+                    iteration += emulate_frame(&cpu_ref, &cpu_ram_ref, &cpu_lcd_ref);
+                    clock.set(movie.clock_after(movie.get_frames().len()));
+                    next_frame += frame_duration;
+                    if cpu_hotkeys_ref.is_fast_forwarding() {
+                        next_frame = std::time::Instant::now();
+                    } else {
+                        std::thread::sleep(
+                            next_frame.saturating_duration_since(std::time::Instant::now()),
+                        );
+                    }
+                    continue;
+                }
                 if cpu_ready_ref.load(Ordering::Relaxed) {
                     let mut ram = cpu_ram_ref.lock().unwrap();
+                    // the LCD and sound threads go on while the link cable partner catches up
+                    if ram.is_link_stalled() {
+                        ram.poll_link();
+                        drop(ram);
+                        std::thread::sleep(LINK_POLL_INTERVAL);
+                        continue;
+                    }
                     let mut cpu = cpu_ref.lock().unwrap();
+                    ram.set_joypad_state(joypad_input_ref.get_state());
+                    let start_cycle = cpu.cycle_count;
                     cpu.next(&mut ram);
-
-                    let mut blr = bootlocker_ref.lock().unwrap();
-                    blr.read_from_ram(&ram);
-                    if blr.is_unlocked() && self.should_reload_cartridge {
-                        println!("bootlock unlocked");
-                        self.should_reload_cartridge = false;
-                        ram.load_base_rom_bank();
-                    }
+                    ram.tick((cpu.cycle_count - start_cycle) as u64);
+                    // only executed instructions count, waiting on the link cable does not
+                    iteration += 1;
 
                     if cpu.get_register(sm83::registers::RegisterName::PC) == 0xFF {
                         println!("boot rom ended");
@@ -142,12 +448,17 @@ impl System {
                 "CPU Execution frequency {}",
                 format_frequency(cycles_per_second as f32)
             );
+            movie_recording
         });
         let lcd_thread_handle = std::thread::spawn(move || {
             let start = std::time::Instant::now();
             let mut lcd_n_iter = 0;
             loop {
-                if lcd_ready_ref.load(Ordering::Relaxed) {
+                // while recording the CPU thread clocks the LCD itself
+                if lcd_ready_ref.load(Ordering::Relaxed)
+                    && !lcd_hotkeys_ref.is_paused()
+                    && !recording
+                {
                     let mut lcd = lcd_ref.lock().unwrap();
                     lcd.next(&lcd_ram_ref);
                     lcd_n_iter += 1;
@@ -164,51 +475,198 @@ impl System {
                 "LCD Execution frequency {}",
                 format_frequency(cycles_per_second as f32)
             );
-            lcd_ref.lock().unwrap().stop_window_thread();
+            lcd_ref.lock().unwrap().close_video_sink();
         });
-        let sound_thread_handle = std::thread::spawn(move || {
-            let start = std::time::Instant::now();
-            let mut sound_n_iter = 0;
-            loop {
-                if sound_ready_ref.load(Ordering::Relaxed) {
-                    let mut sound = sound_ref.lock().unwrap();
-                    sound.next(&sound_ram_ref);
-                    sound_n_iter += 1;
-                }
-                if sound_loop_finished_ref.load(Ordering::Relaxed) {
-                    break;
+        let sound_thread_handle = self.sound_controller.clone().map(|sound_ref| {
+            std::thread::spawn(move || {
+                let start = std::time::Instant::now();
+                let mut sound_n_iter = 0;
+                loop {
+                    if sound_ready_ref.load(Ordering::Relaxed) {
+                        let mut sound = sound_ref.lock().unwrap();
+                        sound.next(&sound_ram_ref);
+                        sound_n_iter += 1;
+                    }
+                    if sound_loop_finished_ref.load(Ordering::Relaxed) {
+                        break;
+                    }
                 }
-            }
-            let dur = std::time::Instant::now().duration_since(start);
-            let elapsed_nanos = dur.as_nanos() as f64;
-            let cycles_per_nano = (sound_n_iter as f64) / elapsed_nanos;
-            let cycles_per_second = cycles_per_nano * 1e9;
-            println!(
-                "Sound Execution frequency {}",
-                format_frequency(cycles_per_second as f32)
-            );
-            sound_ref.lock().unwrap().stop_sound_thread();
+                let dur = std::time::Instant::now().duration_since(start);
+                let elapsed_nanos = dur.as_nanos() as f64;
+                let cycles_per_nano = (sound_n_iter as f64) / elapsed_nanos;
+                let cycles_per_second = cycles_per_nano * 1e9;
+                println!(
+                    "Sound Execution frequency {}",
+                    format_frequency(cycles_per_second as f32)
+                );
+                sound_ref.lock().unwrap().close_audio_sink();
+            })
         });
 
         loop {
-            self.master_clock
-                .next(&self.cpu_ready, &self.lcd_ready, &self.sound_ready);
+            if self.hotkeys.is_fast_forwarding() {
+                // no pacing, every component runs as fast as it can
+                self.cpu_ready.store(true, Ordering::Relaxed);
+                self.lcd_ready.store(true, Ordering::Relaxed);
+                self.sound_ready.store(true, Ordering::Relaxed);
+            } else {
+                self.master_clock
+                    .next(&self.cpu_ready, &self.lcd_ready, &self.sound_ready);
+            }
             if loop_finished_ref.load(Ordering::Relaxed) {
                 break;
             }
         }
 
         println!("Wating for join");
-        cpu_thread_handle.join().unwrap();
+        let movie_recording = cpu_thread_handle.join().unwrap();
         println!("CPU joined");
         lcd_thread_handle.join().unwrap();
         println!("LCD joined");
-        sound_thread_handle.join().unwrap();
-        println!("Sound joined");
+        if let Some(sound_thread_handle) = sound_thread_handle {
+            sound_thread_handle.join().unwrap();
+            println!("Sound joined");
+        }
+
+        if let Err(e) = self.save_battery() {
+            println!("unable to write battery save: {}", e);
+        }
+        if let Some((path, movie, _)) = movie_recording {
+            match movie.save(&path) {
+                Ok(_) => println!("movie saved to {}", path),
+                Err(e) => println!("unable to write movie {}: {}", path, e),
+            }
+        }
 
         self
     }
 
+    pub fn set_button(&self, button: Button, pressed: bool) {
+        self.ram.lock().unwrap().set_button(button, pressed);
+    }
+
+    pub fn set_joypad_state(&self, state: u8) {
+        self.ram.lock().unwrap().set_joypad_state(state);
+    }
+
+    pub fn get_joypad_input(&self) -> JoypadInput {
+        self.joypad_input.clone()
+    }
+
+    // runs up to the next frame boundary, deterministically, see emulate_frame
+    pub fn step_frame(&mut self) {
+        emulate_frame(&self.cpu, &self.ram, &self.lcd_controller);
+        self.drain_sound();
+    }
+
+    // a single instruction, clocked like step_frame so that test ROMs can be watched between
+    // instructions, or none while the link cable waits for the other Game Boy
+    pub fn step_instruction(&mut self) {
+        emulate_instruction(&self.cpu, &self.ram, &self.lcd_controller);
+        self.drain_sound();
+    }
+
+    pub fn get_register(&self, register: RegisterName) -> u16 {
+        self.cpu.lock().unwrap().get_register(register)
+    }
+
+    // grey levels of the last drawn frame, one byte per pixel
+    pub fn get_image_data(&self) -> Vec<u8> {
+        self.lcd_controller.lock().unwrap().get_image_data()
+    }
+
+    // the last drawn frame in the given format, tagged with the frame counter
+    pub fn framebuffer(&self, format: FramebufferFormat) -> Framebuffer {
+        let lcd = self.lcd_controller.lock().unwrap();
+        Framebuffer::from_grey(&lcd.get_image_data(), format, lcd.get_frame_count())
+    }
+
+    // the number of frames the LCD has drawn since the system was built
+    pub fn frame_count(&self) -> u64 {
+        self.lcd_controller.lock().unwrap().get_frame_count()
+    }
+
+    pub fn frame_hash(&self) -> u64 {
+        fnv1a_hash(&self.get_image_data())
+    }
+
+    // the bytes sent over serial so far
+    pub fn get_serial_output(&self) -> Vec<u8> {
+        self.ram.lock().unwrap().get_serial_output().to_vec()
+    }
+
+    pub fn get_serial_text(&self) -> String {
+        self.get_serial_output()
+            .iter()
+            .map(|byte| match byte {
+                b'\n' | 0x20..=0x7E => *byte as char,
+                _ => '?',
+            })
+            .collect()
+    }
+
+    // boots and runs frame by frame until the serial output holds a test verdict or the budget
+    // of M-cycles is spent
+    pub fn run_until_serial_verdict(&mut self, max_cycles: u128) -> SerialVerdict {
+        self.boot();
+        let start_cycle = self.cycle_count();
+        loop {
+            let output = self.get_serial_text();
+            let cycles = self.cycle_count() - start_cycle;
+            let result = match result_from_serial_output(&output) {
+                Some(result) => result,
+                None if cycles >= max_cycles => TestRomResult::Timeout,
+                None => {
+                    self.step_frame();
+                    continue;
+                }
+            };
+            return SerialVerdict {
+                result,
+                output,
+                cycles,
+            };
+        }
+    }
+
+    pub fn get_rom_path(&self) -> Option<String> {
+        let ram = self.ram.lock().unwrap();
+        ram.get_mapping_chip().get_rom_path().map(String::from)
+    }
+
+    pub fn get_hotkeys(&self) -> HotkeyState {
+        self.hotkeys.clone()
+    }
+
+    // the RAM and CPU state taken when the save state hotkey was last pressed, the snapshot
+    // includes the already fetched opcode so it is restored with SM83::load_snapshot alone
+    pub fn get_saved_state(&self) -> Option<(ram::RAM, SM83Snapshot)> {
+        self.saved_state.lock().unwrap().clone()
+    }
+
+    // what the save state hotkey does
+    pub fn save_state(&self) {
+        save_state(&self.cpu, &self.ram, &self.saved_state);
+    }
+
+    // what the load state hotkey does, false when no state was saved
+    pub fn load_state(&self) -> bool {
+        load_state(&self.cpu, &self.ram, &self.saved_state)
+    }
+
+    pub fn is_rumbling(&self) -> bool {
+        self.ram.lock().unwrap().get_mapping_chip().is_rumbling()
+    }
+
+    pub fn save_battery(&self) -> std::io::Result<()> {
+        let ram = self.ram.lock().unwrap();
+        let mapping_chip = ram.get_mapping_chip();
+        match mapping_chip.get_rom_path() {
+            Some(rom_path) => mapping_chip.save_battery(&battery_save_path(rom_path)),
+            None => Ok(()),
+        }
+    }
+
     pub fn cycle_count(&self) -> u128 {
         return self.cpu.lock().unwrap().cycle_count;
     }
@@ -224,16 +682,15 @@ impl System {
     pub fn boot(&mut self) {
         let mut ram = self.ram.lock().unwrap();
         let mut cpu = self.cpu.lock().unwrap();
-        ram.load_base_rom_bank();
-        // override memory from 0000 to 00FF with boot rom
-        self.boot_rom.load_in_ram(&mut ram);
-        {
-            let mut bootlock_register_ref = self.bootlock_register.lock().unwrap();
-            bootlock_register_ref.lock();
-            bootlock_register_ref.load_in_ram(&mut ram);
-            self.should_reload_cartridge = true;
+        match &self.boot_mode {
+            BootMode::BootRom(boot_rom) => {
+                ram.load_base_rom_bank();
+                // the boot rom hides the start of the cartridge until it writes to 0xFF50
+                ram.map_boot_rom(boot_rom.clone());
+                cpu.reset(&ram);
+            }
+            BootMode::Skip(model) => boot::skip_boot(*model, &mut cpu, &mut ram),
         }
-        cpu.reset(&ram);
         self.master_clock.start();
     }
 }
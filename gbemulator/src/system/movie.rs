@@ -0,0 +1,324 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::boot::{BootMode, GameBoyModel};
+use super::ram::mapping_chip::rtc::{ClockSource, ManualClock, SystemClock, CYCLES_PER_SECOND};
+use super::ram::mapping_chip::{DynamicMappingChip, MappingChip};
+use super::ram::BootRom;
+use super::{System, M_CYCLES_PER_FRAME};
+
+const MOVIE_HEADER: &str = "gbmovie 1";
+
+// 64 bit FNV-1a, used for ROM and frame hashes
+pub fn fnv1a_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x00000100000001B3);
+    }
+    hash
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MovieStart {
+    // the embedded DMG boot ROM, other boot ROMs are files the movie cannot vouch for
+    BootRom,
+    SkipBoot(GameBoyModel),
+}
+
+impl MovieStart {
+    fn from_boot_mode(boot_mode: &BootMode) -> Result<Self, String> {
+        match boot_mode {
+            BootMode::BootRom(boot_rom)
+                if boot_rom.get_contents() == BootRom::new().get_contents() =>
+            {
+                Ok(MovieStart::BootRom)
+            }
+            BootMode::BootRom(_) => {
+                Err("movies start from the embedded boot ROM or a skipped boot".to_string())
+            }
+            BootMode::Skip(model) => Ok(MovieStart::SkipBoot(*model)),
+        }
+    }
+}
+
+// A recording of the joypad over a run, with everything needed to reproduce it. Movies are
+// text files:
+//
+// gbmovie 1
+// rom 5c1e4a1d0b9e2f3a        FNV-1a hash of the ROM file
+// battery 00ff00...           the battery save the movie starts from as hex, or none
+// clock 1760000000            unix time the cartridge clock reads when the movie starts
+// start skip dmg              "boot" for the embedded boot ROM, "skip <model>" to skip it
+// check 120 0f3a9c2e11d4b605  optional, hash of the frame drawn once 120 frames ran
+// frames 600
+// 00                          one line per frame, the joypad state as two hex digits with
+// 10                          Right, Left, Up, Down, A, B, Select and Start from bit 0 up
+// ...
+//
+// A frame is 17556 M-cycles of emulation with the LCD clocked from the cycle count, see
+// System::step_frame. The battery save is kept in the movie because the run being recorded
+// rewrites the one on disk, and the cartridge clock follows the frames instead of the wall
+// clock, see Movie::start_cartridge.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Movie {
+    rom_hash: u64,
+    battery: Option<Vec<u8>>,
+    clock: u64,
+    start: MovieStart,
+    checks: Vec<(usize, u64)>,
+    frames: Vec<u8>,
+}
+
+impl Movie {
+    pub fn new(rom_hash: u64, battery: Option<Vec<u8>>, clock: u64, start: MovieStart) -> Self {
+        Movie {
+            rom_hash,
+            battery,
+            clock,
+            start,
+            checks: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    // an empty movie starting from the current state of a system that has not run yet
+    pub fn for_system(system: &System) -> Result<Self, String> {
+        let rom_path = system
+            .get_rom_path()
+            .ok_or("movies need a system built from a ROM file".to_string())?;
+        let rom = std::fs::read(&rom_path)
+            .map_err(|e| format!("unable to read ROM {}: {}", rom_path, e))?;
+        let battery = system
+            .ram
+            .lock()
+            .unwrap()
+            .get_mapping_chip()
+            .get_battery_data();
+        Ok(Movie::new(
+            fnv1a_hash(&rom),
+            battery,
+            SystemClock {}.unix_time(),
+            MovieStart::from_boot_mode(&system.boot_mode)?,
+        ))
+    }
+
+    // Puts the cartridge in the state the movie starts from, both when recording and playing:
+    // the battery save is the one of the movie and the clock reads the movie time. The returned
+    // clock is moved along with the frames, see clock_after.
+    pub fn start_cartridge(&self, chip: &mut DynamicMappingChip) -> ManualClock {
+        let clock = ManualClock::new(self.clock);
+        chip.set_clock_source(Arc::new(clock.clone()));
+        if let Some(battery) = &self.battery {
+            chip.load_battery_data(battery);
+        }
+        clock
+    }
+
+    // unix time of the cartridge clock once the given number of frames ran
+    pub fn clock_after(&self, frames: usize) -> u64 {
+        self.clock + frames as u64 * M_CYCLES_PER_FRAME as u64 / CYCLES_PER_SECOND
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read movie {}: {}", path, e))?;
+        Movie::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        match lines.next() {
+            Some((_, MOVIE_HEADER)) => {}
+            _ => return Err(format!("a movie starts with \"{}\"", MOVIE_HEADER)),
+        }
+        let mut rom_hash = None;
+        let mut battery = None;
+        let mut clock = None;
+        let mut start = None;
+        let mut checks = Vec::new();
+        let mut frame_count = None;
+        for (number, line) in lines.by_ref() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| format!("line {}: {}", number, message);
+            match fields.as_slice() {
+                ["rom", hash] => rom_hash = Some(parse_hash(hash).map_err(|e| error(&e))?),
+                ["battery", "none"] => battery = Some(None),
+                ["battery", contents] => {
+                    battery = Some(Some(parse_bytes(contents).map_err(|e| error(&e))?))
+                }
+                ["clock", time] => {
+                    clock = Some(
+                        time.parse::<u64>()
+                            .map_err(|_| error("the clock should be a unix time"))?,
+                    )
+                }
+                ["start", "boot"] => start = Some(MovieStart::BootRom),
+                ["start", "skip", model] => {
+                    let model = GameBoyModel::from_str(model).map_err(|e| error(&e))?;
+                    start = Some(MovieStart::SkipBoot(model))
+                }
+                ["check", frame, hash] => {
+                    let frame = frame
+                        .parse::<usize>()
+                        .map_err(|_| error("the check frame should be a number"))?;
+                    checks.push((frame, parse_hash(hash).map_err(|e| error(&e))?));
+                }
+                ["frames", count] => {
+                    frame_count = Some(
+                        count
+                            .parse::<usize>()
+                            .map_err(|_| error("the frame count should be a number"))?,
+                    );
+                    break;
+                }
+                _ => return Err(error(&format!("unexpected \"{}\"", line))),
+            }
+        }
+        let mut movie = Movie::new(
+            rom_hash.ok_or("missing rom hash")?,
+            battery.ok_or("missing battery save")?,
+            clock.ok_or("missing clock")?,
+            start.ok_or("missing start")?,
+        );
+        movie.checks = checks;
+        for (number, line) in lines {
+            let state = u8::from_str_radix(line, 16)
+                .map_err(|_| format!("line {}: {} is not a joypad state", number, line))?;
+            movie.frames.push(state);
+        }
+        let frame_count = frame_count.ok_or("missing frame count")?;
+        if movie.frames.len() != frame_count {
+            return Err(format!(
+                "expected {} frames, got {}",
+                frame_count,
+                movie.frames.len()
+            ));
+        }
+        Ok(movie)
+    }
+
+    pub fn with_check(mut self, frame: usize, frame_hash: u64) -> Self {
+        self.checks.push((frame, frame_hash));
+        self
+    }
+
+    pub fn record_frame(&mut self, joypad_state: u8) {
+        self.frames.push(joypad_state);
+    }
+
+    pub fn get_frames(&self) -> &[u8] {
+        &self.frames
+    }
+
+    pub fn get_rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    pub fn get_start(&self) -> MovieStart {
+        self.start
+    }
+
+    // Builds a headless system from the ROM in the starting state of the movie, then feeds it
+    // the recorded joypad one frame at a time. The battery save on disk is left alone. Returns
+    // the hash of every frame, or an error if the ROM does not match or a check fails.
+    pub fn play(&self, rom_path: &str) -> Result<Vec<u64>, String> {
+        let rom = std::fs::read(rom_path)
+            .map_err(|e| format!("unable to read ROM {}: {}", rom_path, e))?;
+        if fnv1a_hash(&rom) != self.rom_hash {
+            return Err(format!(
+                "{} does not match the ROM the movie was recorded with",
+                rom_path
+            ));
+        }
+        let mut chip = DynamicMappingChip::from_rom_path_without_battery(rom_path)?;
+        let clock = self.start_cartridge(&mut chip);
+        let mut system = System::new(Some(chip), true);
+        if let MovieStart::SkipBoot(model) = self.start {
+            system = system.with_skip_boot(model);
+        }
+        system.boot();
+
+        let mut frame_hashes = Vec::with_capacity(self.frames.len());
+        for (i, state) in self.frames.iter().enumerate() {
+            system.set_joypad_state(*state);
+            system.step_frame();
+            let frame = i + 1;
+            clock.set(self.clock_after(frame));
+            let frame_hash = system.frame_hash();
+            for (_, expected) in self.checks.iter().filter(|(f, _)| *f == frame) {
+                if *expected != frame_hash {
+                    return Err(format!(
+                        "frame {} hashed to {:016x}, expected {:016x}",
+                        frame, frame_hash, expected
+                    ));
+                }
+            }
+            frame_hashes.push(frame_hash);
+        }
+        Ok(frame_hashes)
+    }
+}
+
+impl std::fmt::Display for Movie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut contents = String::new();
+        writeln!(contents, "{}", MOVIE_HEADER)?;
+        writeln!(contents, "rom {:016x}", self.rom_hash)?;
+        match &self.battery {
+            Some(battery) => {
+                write!(contents, "battery ")?;
+                for byte in battery {
+                    write!(contents, "{:02x}", byte)?;
+                }
+                writeln!(contents)?;
+            }
+            None => writeln!(contents, "battery none")?,
+        }
+        writeln!(contents, "clock {}", self.clock)?;
+        match self.start {
+            MovieStart::BootRom => writeln!(contents, "start boot")?,
+            MovieStart::SkipBoot(model) => writeln!(
+                contents,
+                "start skip {}",
+                format!("{:?}", model).to_lowercase()
+            )?,
+        }
+        for (frame, hash) in &self.checks {
+            writeln!(contents, "check {} {:016x}", frame, hash)?;
+        }
+        writeln!(contents, "frames {}", self.frames.len())?;
+        for state in &self.frames {
+            writeln!(contents, "{:02x}", state)?;
+        }
+        write!(f, "{}", contents)
+    }
+}
+
+fn parse_hash(hash: &str) -> Result<u64, String> {
+    u64::from_str_radix(hash, 16).map_err(|_| format!("{} is not a hex hash", hash))
+}
+
+fn parse_bytes(contents: &str) -> Result<Vec<u8>, String> {
+    if !contents.len().is_multiple_of(2) {
+        return Err("the battery save should be hex bytes".to_string());
+    }
+    (0..contents.len())
+        .step_by(2)
+        .map(|i| {
+            contents
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or("the battery save should be hex bytes".to_string())
+        })
+        .collect()
+}
@@ -0,0 +1,331 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::controllers::lcd_controller::BG_SHADES;
+use super::link_cable::{LinkCable, LinkMessage};
+
+const MAGIC_1: u8 = 0x88;
+const MAGIC_2: u8 = 0x33;
+
+pub const COMMAND_INIT: u8 = 0x01;
+pub const COMMAND_PRINT: u8 = 0x02;
+pub const COMMAND_DATA: u8 = 0x04;
+pub const COMMAND_STATUS: u8 = 0x0F;
+
+// answered in place of the first of the two bytes following the checksum
+const DEVICE_ID: u8 = 0x81;
+
+pub const STATUS_CHECKSUM_ERROR: u8 = 0x01;
+pub const STATUS_PRINTING: u8 = 0x02;
+pub const STATUS_IMAGE_FULL: u8 = 0x04;
+pub const STATUS_UNPROCESSED_DATA: u8 = 0x08;
+
+pub const PAPER_WIDTH: usize = 160;
+// a band is 2 rows of 20 tiles
+const BAND_BYTES: usize = 640;
+const BAND_HEIGHT: usize = 16;
+// the printer holds 9 bands, a full screen
+const BUFFER_SIZE: usize = BAND_BYTES * 9;
+// status replies reporting the printer busy after a print, games wait for the bit to clear
+const BUSY_STATUS_REPLIES: u8 = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PacketState {
+    Magic1,
+    Magic2,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    DeviceId,
+    Status,
+}
+
+struct PrinterState {
+    packet_state: PacketState,
+    command: u8,
+    compressed: bool,
+    length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    status: u8,
+    busy_replies: u8,
+    // decompressed tile data waiting for a print command
+    buffer: Vec<u8>,
+    // every printed band one under the other, one byte per pixel
+    paper: Vec<u8>,
+    image_path: Option<String>,
+    replies: VecDeque<u8>,
+}
+
+// The Game Boy Printer, plugged in place of a link cable. The Game Boy provides the clock and
+// sends packets:
+//
+// 0x88 0x33 command compression length_low length_high data... checksum_low checksum_high 0x00 0x00
+//
+// The checksum is the 16 bit sum of every byte from the command to the end of the data. The
+// printer answers 0x00 to every byte but the last two, where it answers 0x81 and its status.
+// DATA packets carry tiles, RLE compressed when the compression byte is 1, and PRINT renders
+// them with the palette it carries. Printed bands pile up on a strip of paper 160 pixels wide.
+#[derive(Clone)]
+pub struct Printer {
+    state: Arc<Mutex<PrinterState>>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Printer {
+            state: Arc::new(Mutex::new(PrinterState {
+                packet_state: PacketState::Magic1,
+                command: 0,
+                compressed: false,
+                length: 0,
+                data: Vec::new(),
+                checksum: 0,
+                received_checksum: 0,
+                status: 0,
+                busy_replies: 0,
+                buffer: Vec::new(),
+                paper: Vec::new(),
+                image_path: None,
+                replies: VecDeque::new(),
+            })),
+        }
+    }
+
+    // the whole paper is written to this path as a PGM after every print
+    pub fn with_image_path(self, path: &str) -> Self {
+        self.state.lock().unwrap().image_path = Some(path.to_string());
+        self
+    }
+
+    // greyscale pixels, PAPER_WIDTH per row
+    pub fn get_paper(&self) -> Vec<u8> {
+        self.state.lock().unwrap().paper.clone()
+    }
+
+    pub fn get_paper_height(&self) -> usize {
+        self.state.lock().unwrap().paper.len() / PAPER_WIDTH
+    }
+
+    pub fn get_status(&self) -> u8 {
+        self.state.lock().unwrap().status
+    }
+
+    pub fn save_image(&self, path: &str) -> std::io::Result<()> {
+        save_paper(path, &self.state.lock().unwrap().paper)
+    }
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkCable for Printer {
+    fn send(&mut self, message: LinkMessage) {
+        // the printer never provides the clock, so it only answers transfers
+        if let LinkMessage::Transfer { byte, .. } = message {
+            let mut state = self.state.lock().unwrap();
+            let reply = state.receive(byte);
+            state.replies.push_back(reply);
+        }
+    }
+
+    fn receive(&mut self) -> Option<LinkMessage> {
+        self.state
+            .lock()
+            .unwrap()
+            .replies
+            .pop_front()
+            .map(LinkMessage::Reply)
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn sends_clock(&self) -> bool {
+        false
+    }
+}
+
+impl PrinterState {
+    // takes a byte from the Game Boy and returns the one shifted back
+    fn receive(&mut self, byte: u8) -> u8 {
+        match self.packet_state {
+            PacketState::Magic1 => {
+                if byte == MAGIC_1 {
+                    self.packet_state = PacketState::Magic2;
+                }
+            }
+            PacketState::Magic2 => {
+                self.packet_state = match byte {
+                    MAGIC_2 => PacketState::Command,
+                    MAGIC_1 => PacketState::Magic2,
+                    _ => PacketState::Magic1,
+                }
+            }
+            PacketState::Command => {
+                self.command = byte;
+                self.checksum = byte as u16;
+                self.packet_state = PacketState::Compression;
+            }
+            PacketState::Compression => {
+                self.compressed = byte & 0x01 != 0;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.packet_state = PacketState::LengthLow;
+            }
+            PacketState::LengthLow => {
+                self.length = byte as u16;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.packet_state = PacketState::LengthHigh;
+            }
+            PacketState::LengthHigh => {
+                self.length |= (byte as u16) << 8;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                self.data.clear();
+                self.packet_state = if self.length == 0 {
+                    PacketState::ChecksumLow
+                } else {
+                    PacketState::Data
+                };
+            }
+            PacketState::Data => {
+                self.data.push(byte);
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                if self.data.len() == self.length as usize {
+                    self.packet_state = PacketState::ChecksumLow;
+                }
+            }
+            PacketState::ChecksumLow => {
+                self.received_checksum = byte as u16;
+                self.packet_state = PacketState::ChecksumHigh;
+            }
+            PacketState::ChecksumHigh => {
+                self.received_checksum |= (byte as u16) << 8;
+                self.packet_state = PacketState::DeviceId;
+            }
+            PacketState::DeviceId => {
+                self.packet_state = PacketState::Status;
+                return DEVICE_ID;
+            }
+            PacketState::Status => {
+                self.packet_state = PacketState::Magic1;
+                self.run_command();
+                return self.status;
+            }
+        }
+        0x00
+    }
+
+    fn run_command(&mut self) {
+        if self.checksum != self.received_checksum {
+            self.status |= STATUS_CHECKSUM_ERROR;
+            return;
+        }
+        self.status &= !STATUS_CHECKSUM_ERROR;
+        match self.command {
+            COMMAND_INIT => {
+                self.buffer.clear();
+                self.status = 0;
+                self.busy_replies = 0;
+            }
+            COMMAND_DATA if self.data.is_empty() => self.status |= STATUS_IMAGE_FULL,
+            COMMAND_DATA => {
+                let data = if self.compressed {
+                    decompress(&self.data)
+                } else {
+                    self.data.clone()
+                };
+                let room = BUFFER_SIZE - self.buffer.len();
+                self.buffer.extend(data.into_iter().take(room));
+                self.status |= STATUS_UNPROCESSED_DATA;
+            }
+            COMMAND_PRINT if self.data.len() == 4 => self.print(self.data[0], self.data[2]),
+            COMMAND_STATUS if self.busy_replies > 0 => {
+                self.busy_replies -= 1;
+                if self.busy_replies == 0 {
+                    self.status &= !STATUS_PRINTING;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the margins and exposure of the print command only matter to real paper
+    fn print(&mut self, sheets: u8, palette: u8) {
+        // sheets 0 only feeds the paper
+        if sheets > 0 {
+            // games that leave the palette at 0 expect the usual one
+            let palette = if palette == 0 { 0xE4 } else { palette };
+            for band in self.buffer.chunks(BAND_BYTES) {
+                self.paper
+                    .extend(render_band(band, palette).into_iter().flatten());
+            }
+            if let Some(path) = &self.image_path {
+                if let Err(e) = save_paper(path, &self.paper) {
+                    println!("unable to save printer output {}: {}", path, e);
+                }
+            }
+        }
+        self.buffer.clear();
+        self.status = STATUS_PRINTING;
+        self.busy_replies = BUSY_STATUS_REPLIES;
+    }
+}
+
+// A run of bytes starts with a control byte: with bit 7 set the byte after it repeats
+// (control & 0x7F) + 2 times, otherwise the next control + 1 bytes are copied as they are.
+pub fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let control = data[i];
+        i += 1;
+        if control & 0x80 != 0 {
+            if let Some(byte) = data.get(i) {
+                result.extend(std::iter::repeat_n(*byte, (control & 0x7F) as usize + 2));
+            }
+            i += 1;
+        } else {
+            let end = (i + control as usize + 1).min(data.len());
+            result.extend_from_slice(&data[i..end]);
+            i = end;
+        }
+    }
+    result
+}
+
+// 40 tiles in the usual 2 bits per pixel format, 20 to a row
+fn render_band(band: &[u8], palette: u8) -> Vec<[u8; PAPER_WIDTH]> {
+    let mut rows = vec![[BG_SHADES[0]; PAPER_WIDTH]; BAND_HEIGHT];
+    for (tile_index, tile) in band.chunks(16).enumerate() {
+        let tile_x = (tile_index % 20) * 8;
+        let tile_y = (tile_index / 20) * 8;
+        for (line, bytes) in tile.chunks(2).enumerate() {
+            if bytes.len() < 2 {
+                break;
+            }
+            for pixel in 0..8 {
+                let bit = 7 - pixel;
+                let color = ((bytes[0] >> bit) & 0x01) | (((bytes[1] >> bit) & 0x01) << 1);
+                let shade = (palette >> (color * 2)) & 0x03;
+                rows[tile_y + line][tile_x + pixel] = BG_SHADES[shade as usize];
+            }
+        }
+    }
+    rows
+}
+
+fn save_paper(path: &str, paper: &[u8]) -> std::io::Result<()> {
+    let mut contents =
+        format!("P5\n{} {}\n255\n", PAPER_WIDTH, paper.len() / PAPER_WIDTH).into_bytes();
+    contents.extend_from_slice(paper);
+    std::fs::write(path, contents)
+}
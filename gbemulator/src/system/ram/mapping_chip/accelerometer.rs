@@ -0,0 +1,65 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+// value the MBC7 sensor reports on both axes when the console lies flat
+pub const ACCELEROMETER_CENTER: u16 = 0x81D0;
+// roughly how far one g of tilt moves the reading away from the center
+pub const ACCELEROMETER_COUNTS_PER_G: f32 = 112.0;
+
+// Host side of the MBC7 two axis accelerometer, read by the cart every time the game latches
+// a new sample. Tilts are in g along the sensor X and Y axes.
+pub trait Accelerometer: Send + Sync {
+    fn tilt(&self) -> (f32, f32);
+}
+
+pub struct FlatAccelerometer {}
+
+impl Accelerometer for FlatAccelerometer {
+    fn tilt(&self) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+}
+
+// Tilt set by the frontend (from a mouse, a gamepad stick, a real sensor...), clones share it
+#[derive(Clone)]
+pub struct SharedAccelerometer {
+    x: Arc<AtomicU32>,
+    y: Arc<AtomicU32>,
+}
+
+impl SharedAccelerometer {
+    pub fn new() -> Self {
+        SharedAccelerometer {
+            x: Arc::new(AtomicU32::new(0f32.to_bits())),
+            y: Arc::new(AtomicU32::new(0f32.to_bits())),
+        }
+    }
+
+    pub fn set_tilt(&self, x: f32, y: f32) {
+        self.x.store(x.to_bits(), Ordering::Relaxed);
+        self.y.store(y.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for SharedAccelerometer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accelerometer for SharedAccelerometer {
+    fn tilt(&self) -> (f32, f32) {
+        (
+            f32::from_bits(self.x.load(Ordering::Relaxed)),
+            f32::from_bits(self.y.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+// converts a tilt in g to the raw 16 bit sensor reading
+pub fn tilt_to_reading(tilt: f32) -> u16 {
+    let offset = (tilt * ACCELEROMETER_COUNTS_PER_G).round() as i32;
+    (ACCELEROMETER_CENTER as i32 + offset).clamp(0, u16::MAX as i32) as u16
+}
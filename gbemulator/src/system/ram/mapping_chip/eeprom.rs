@@ -0,0 +1,233 @@
+// 93LC56 serial EEPROM in its 128 x 16 bit organisation, as wired on the MBC7.
+//
+// While CS is high every rising CLK edge shifts one DI bit in. A command is a start bit (1),
+// a 2 bit opcode and 8 address bits (only the low 7 are used); WRITE and WRAL are followed by
+// 16 data bits. READ answers with a dummy 0 and then the word MSB first on DO, continuing with
+// the next words as long as the clock keeps running. Bringing CS low aborts whatever was in
+// progress. Programming is instantaneous, DO reads as ready (1) once a command is done.
+#[derive(Clone)]
+pub struct Eeprom93LC56 {
+    words: Vec<u16>,
+    write_enabled: bool,
+    cs: bool,
+    clk: bool,
+    di: bool,
+    do_: bool,
+    state: EepromState,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum EepromState {
+    // waiting for the start bit
+    Idle,
+    // collecting opcode and address, bit count includes the start bit
+    Command {
+        bits: u8,
+        value: u16,
+    },
+    // collecting the 16 data bits of a WRITE (Some(address)) or WRAL (None)
+    Data {
+        address: Option<u8>,
+        bits: u8,
+        value: u16,
+    },
+    // shifting a word out, bit is the next one to output
+    Reading {
+        address: u8,
+        bit: u8,
+    },
+}
+
+impl Eeprom93LC56 {
+    pub const WORD_COUNT: usize = 128;
+    const COMMAND_BITS: u8 = 11;
+
+    const OPCODE_WRITE: u16 = 0b01;
+    const OPCODE_READ: u16 = 0b10;
+    const OPCODE_ERASE: u16 = 0b11;
+
+    // extended commands (opcode 0b00) are told apart by the two address bits after the opcode
+    const EXTENDED_EWDS: u16 = 0b00;
+    const EXTENDED_ERAL: u16 = 0b10;
+    const EXTENDED_EWEN: u16 = 0b11;
+
+    pub fn new() -> Self {
+        Eeprom93LC56 {
+            // erased cells read as all ones
+            words: vec![0xFFFF; Eeprom93LC56::WORD_COUNT],
+            write_enabled: false,
+            cs: false,
+            clk: false,
+            di: false,
+            do_: true,
+            state: EepromState::Idle,
+        }
+    }
+
+    pub fn get_word(&self, address: u8) -> u16 {
+        self.words[address as usize % Eeprom93LC56::WORD_COUNT]
+    }
+
+    pub fn is_write_enabled(&self) -> bool {
+        self.write_enabled
+    }
+
+    pub fn get_cs(&self) -> bool {
+        self.cs
+    }
+
+    pub fn get_clk(&self) -> bool {
+        self.clk
+    }
+
+    pub fn get_di(&self) -> bool {
+        self.di
+    }
+
+    pub fn get_do(&self) -> bool {
+        self.do_
+    }
+
+    pub fn set_pins(&mut self, cs: bool, clk: bool, di: bool) {
+        let rising_edge = !self.clk && clk;
+        self.cs = cs;
+        self.clk = clk;
+        self.di = di;
+        if !cs {
+            self.state = EepromState::Idle;
+            self.do_ = true;
+            return;
+        }
+        if rising_edge {
+            self.clock_bit(di);
+        }
+    }
+
+    fn clock_bit(&mut self, bit: bool) {
+        self.state = match self.state {
+            EepromState::Idle if bit => EepromState::Command { bits: 1, value: 1 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, value } => {
+                let value = (value << 1) | bit as u16;
+                if bits + 1 == Eeprom93LC56::COMMAND_BITS {
+                    self.execute_command(value)
+                } else {
+                    EepromState::Command {
+                        bits: bits + 1,
+                        value,
+                    }
+                }
+            }
+            EepromState::Data {
+                address,
+                bits,
+                value,
+            } => {
+                let value = (value << 1) | bit as u16;
+                if bits + 1 == 16 {
+                    self.program(address, value);
+                    self.do_ = true;
+                    EepromState::Idle
+                } else {
+                    EepromState::Data {
+                        address,
+                        bits: bits + 1,
+                        value,
+                    }
+                }
+            }
+            EepromState::Reading { address, bit } => {
+                self.do_ = self.get_word(address) & (0x8000 >> bit) != 0;
+                if bit == 15 {
+                    EepromState::Reading {
+                        address: (address + 1) % Eeprom93LC56::WORD_COUNT as u8,
+                        bit: 0,
+                    }
+                } else {
+                    EepromState::Reading {
+                        address,
+                        bit: bit + 1,
+                    }
+                }
+            }
+        };
+    }
+
+    fn execute_command(&mut self, command: u16) -> EepromState {
+        let opcode = (command >> 8) & 0b11;
+        let address = (command & 0x7F) as u8;
+        match opcode {
+            Eeprom93LC56::OPCODE_READ => {
+                self.do_ = false;
+                EepromState::Reading { address, bit: 0 }
+            }
+            Eeprom93LC56::OPCODE_WRITE => EepromState::Data {
+                address: Some(address),
+                bits: 0,
+                value: 0,
+            },
+            Eeprom93LC56::OPCODE_ERASE => {
+                self.program(Some(address), 0xFFFF);
+                self.do_ = true;
+                EepromState::Idle
+            }
+            // 0b00, the command is in the two address bits that follow
+            _ => self.execute_extended_command(command),
+        }
+    }
+
+    fn execute_extended_command(&mut self, command: u16) -> EepromState {
+        match (command >> 6) & 0b11 {
+            Eeprom93LC56::EXTENDED_EWEN => {
+                self.write_enabled = true;
+                EepromState::Idle
+            }
+            Eeprom93LC56::EXTENDED_EWDS => {
+                self.write_enabled = false;
+                EepromState::Idle
+            }
+            Eeprom93LC56::EXTENDED_ERAL => {
+                self.program(None, 0xFFFF);
+                self.do_ = true;
+                EepromState::Idle
+            }
+            // WRAL
+            _ => EepromState::Data {
+                address: None,
+                bits: 0,
+                value: 0,
+            },
+        }
+    }
+
+    // None programs every word
+    fn program(&mut self, address: Option<u8>, value: u16) {
+        if !self.write_enabled {
+            return;
+        }
+        match address {
+            Some(address) => self.words[address as usize % Eeprom93LC56::WORD_COUNT] = value,
+            None => self.words.iter_mut().for_each(|word| *word = value),
+        }
+    }
+
+    // words are stored little endian, which is what other emulators put in MBC7 .sav files
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    pub fn load_bytes(&mut self, data: &[u8]) {
+        for (word, bytes) in self.words.iter_mut().zip(data.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+}
+
+impl Default for Eeprom93LC56 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,157 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::infrared::{InfraredPort, NoInfrared};
+use super::{
+    ram_size_from_header, read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip,
+    MemoryBank, EXTERNAL_RAM_START, RAM_BANK_SIZE,
+};
+
+#[derive(Clone)]
+pub struct HuC1 {
+    rom_bank: u8,
+    ram_bank: u8,
+    // 0x0E in the first register swaps the RAM window for the IR port
+    ir_mode: bool,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    infrared: Arc<dyn InfraredPort>,
+    rom_path: String,
+}
+
+impl HuC1 {
+    const MODE_RANGE_START: u16 = 0x0000;
+    const MODE_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_RANGE_END: u16 = 0x3FFF;
+    const RAM_BANK_RANGE_START: u16 = 0x4000;
+    const RAM_BANK_RANGE_END: u16 = 0x5FFF;
+    const IR_MODE: u8 = 0x0E;
+    // reads of the IR port have the upper bits set, bit 0 tells if light is received
+    const IR_IDLE: u8 = 0xC0;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        HuC1 {
+            ram: vec![0; ram_size_from_header(&rom)],
+            rom,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_infrared_port(mut self, infrared: Arc<dyn InfraredPort>) -> Self {
+        self.infrared = infrared;
+        self
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn is_ir_mode(&self) -> bool {
+        self.ir_mode
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if self.ram.is_empty() {
+            return None;
+        }
+        let offset =
+            self.ram_bank as usize * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for HuC1 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (HuC1::ROM_BANK_RANGE_START..=HuC1::ROM_BANK_RANGE_END).contains(&address) {
+            self.rom_bank = value & 0x3F;
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        if (HuC1::RAM_BANK_RANGE_START..=HuC1::RAM_BANK_RANGE_END).contains(&address) {
+            self.ram_bank = value & 0x03;
+            return true;
+        }
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    // the RAM has no enable register, it is always accessible outside of IR mode
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        if self.ir_mode {
+            return Some(HuC1::IR_IDLE | self.infrared.is_receiving_light() as u8);
+        }
+        match self.ram_address(address) {
+            Some(offset) => Some(self.ram[offset]),
+            None => Some(0xFF),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if self.ir_mode {
+            self.infrared.set_led(value & 0x01 != 0);
+        } else if let Some(offset) = self.ram_address(address) {
+            self.ram[offset] = value;
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (HuC1::MODE_RANGE_START..=HuC1::MODE_RANGE_END).contains(&address) {
+            self.ir_mode = value & 0x0F == HuC1::IR_MODE;
+        }
+        !self.ir_mode
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    // every HuC1 cart has a battery
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        Some(self.ram.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    fn new() -> Self {
+        HuC1 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            ir_mode: false,
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            ram: Vec::new(),
+            infrared: Arc::new(NoInfrared {}),
+            rom_path: String::new(),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::infrared::{InfraredPort, NoInfrared};
+use super::rtc::{ClockSource, SystemClock, CYCLES_PER_SECOND};
+use super::{
+    ram_size_from_header, read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip,
+    MemoryBank, EXTERNAL_RAM_START, RAM_BANK_SIZE,
+};
+
+// minutes and days (as little endian u32) followed by a 64 bit unix timestamp
+pub const HUC3_FOOTER_SIZE: usize = 16;
+const MINUTES_PER_DAY: u64 = 24 * 60;
+const DAY_COUNTER_MASK: u16 = 0x0FFF;
+
+// The HuC3 clock is a small microcontroller with 256 nibbles of memory, driven by commands
+// written to 0xA000. The upper nibble of a command selects the operation, the lower one is
+// its argument. Commands are latched in mode 0xB and run when the game clears the semaphore.
+#[derive(Clone)]
+pub struct HuC3Clock {
+    minutes: u16,
+    days: u16,
+    cycles: u64,
+    memory: Vec<u8>,
+    address: u8,
+    command: u8,
+    response: u8,
+    clock_source: Arc<dyn ClockSource>,
+}
+
+impl HuC3Clock {
+    const COMMAND_READ: u8 = 0x1;
+    const COMMAND_WRITE: u8 = 0x3;
+    const COMMAND_ADDRESS_LOW: u8 = 0x4;
+    const COMMAND_ADDRESS_HIGH: u8 = 0x5;
+    const COMMAND_EXTENDED: u8 = 0x6;
+
+    const EXTENDED_TIME_TO_MEMORY: u8 = 0x0;
+    const EXTENDED_MEMORY_TO_TIME: u8 = 0x1;
+    const EXTENDED_STATUS: u8 = 0x2;
+
+    // the time is exchanged through the first memory nibbles: 3 for the minute of the day
+    // and 3 for the day counter, least significant nibble first
+    const TIME_MINUTES_ADDRESS: usize = 0x00;
+    const TIME_DAYS_ADDRESS: usize = 0x03;
+
+    pub fn new(clock_source: Arc<dyn ClockSource>) -> Self {
+        HuC3Clock {
+            minutes: 0,
+            days: 0,
+            cycles: 0,
+            memory: vec![0; 256],
+            address: 0,
+            command: 0,
+            response: 0,
+            clock_source,
+        }
+    }
+
+    pub fn set_clock_source(&mut self, clock_source: Arc<dyn ClockSource>) {
+        self.clock_source = clock_source;
+    }
+
+    pub fn get_minutes(&self) -> u16 {
+        self.minutes
+    }
+
+    pub fn get_days(&self) -> u16 {
+        self.days
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        self.cycles += cycles;
+        while self.cycles >= 60 * CYCLES_PER_SECOND {
+            self.cycles -= 60 * CYCLES_PER_SECOND;
+            self.advance_minutes(1);
+        }
+    }
+
+    pub fn advance_minutes(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % MINUTES_PER_DAY) as u16;
+        self.days = (self.days as u64 + total / MINUTES_PER_DAY) as u16 & DAY_COUNTER_MASK;
+    }
+
+    pub fn set_command(&mut self, value: u8) {
+        self.command = value;
+    }
+
+    pub fn read_response(&self) -> u8 {
+        (self.command & 0xF0) | (self.response & 0x0F)
+    }
+
+    pub fn execute(&mut self) {
+        let argument = self.command & 0x0F;
+        match (self.command >> 4) & 0x07 {
+            HuC3Clock::COMMAND_READ => {
+                self.response = self.memory[self.address as usize];
+                self.address = self.address.wrapping_add(1);
+            }
+            HuC3Clock::COMMAND_WRITE => {
+                self.memory[self.address as usize] = argument;
+                self.address = self.address.wrapping_add(1);
+            }
+            HuC3Clock::COMMAND_ADDRESS_LOW => self.address = (self.address & 0xF0) | argument,
+            HuC3Clock::COMMAND_ADDRESS_HIGH => {
+                self.address = (self.address & 0x0F) | (argument << 4)
+            }
+            HuC3Clock::COMMAND_EXTENDED => match argument {
+                HuC3Clock::EXTENDED_TIME_TO_MEMORY => self.time_to_memory(),
+                HuC3Clock::EXTENDED_MEMORY_TO_TIME => self.memory_to_time(),
+                HuC3Clock::EXTENDED_STATUS => self.response = 0x1,
+                // tone generator and the other extended commands are not emulated
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn time_to_memory(&mut self) {
+        for i in 0..3 {
+            self.memory[HuC3Clock::TIME_MINUTES_ADDRESS + i] =
+                ((self.minutes >> (i * 4)) & 0x0F) as u8;
+            self.memory[HuC3Clock::TIME_DAYS_ADDRESS + i] = ((self.days >> (i * 4)) & 0x0F) as u8;
+        }
+    }
+
+    fn memory_to_time(&mut self) {
+        let mut minutes = 0u16;
+        let mut days = 0u16;
+        for i in 0..3 {
+            minutes |= (self.memory[HuC3Clock::TIME_MINUTES_ADDRESS + i] as u16 & 0x0F) << (i * 4);
+            days |= (self.memory[HuC3Clock::TIME_DAYS_ADDRESS + i] as u16 & 0x0F) << (i * 4);
+        }
+        self.minutes = (minutes as u64 % MINUTES_PER_DAY) as u16;
+        self.days = days & DAY_COUNTER_MASK;
+        self.cycles = 0;
+    }
+
+    pub fn to_footer(&self) -> [u8; HUC3_FOOTER_SIZE] {
+        let mut footer = [0u8; HUC3_FOOTER_SIZE];
+        footer[0..4].copy_from_slice(&(self.minutes as u32).to_le_bytes());
+        footer[4..8].copy_from_slice(&(self.days as u32).to_le_bytes());
+        footer[8..16].copy_from_slice(&self.clock_source.unix_time().to_le_bytes());
+        footer
+    }
+
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() < HUC3_FOOTER_SIZE {
+            return;
+        }
+        let minutes = u32::from_le_bytes(footer[0..4].try_into().unwrap()) as u64;
+        let days = u32::from_le_bytes(footer[4..8].try_into().unwrap());
+        let timestamp = u64::from_le_bytes(footer[8..16].try_into().unwrap());
+        self.minutes = (minutes % MINUTES_PER_DAY) as u16;
+        self.days = days as u16 & DAY_COUNTER_MASK;
+        self.cycles = 0;
+        let now = self.clock_source.unix_time();
+        self.advance_minutes(now.saturating_sub(timestamp) / 60);
+    }
+}
+
+#[derive(Clone)]
+pub struct HuC3 {
+    rom_bank: u8,
+    ram_bank: u8,
+    // what 0xA000-0xBFFF is connected to, see the MODE_ constants
+    mode: u8,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    clock: HuC3Clock,
+    infrared: Arc<dyn InfraredPort>,
+    rom_path: String,
+}
+
+impl HuC3 {
+    const MODE_RANGE_START: u16 = 0x0000;
+    const MODE_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_RANGE_END: u16 = 0x3FFF;
+    const RAM_BANK_RANGE_START: u16 = 0x4000;
+    const RAM_BANK_RANGE_END: u16 = 0x5FFF;
+
+    const MODE_RAM_READ: u8 = 0x0;
+    const MODE_RAM_READ_WRITE: u8 = 0xA;
+    const MODE_RTC_COMMAND: u8 = 0xB;
+    const MODE_RTC_RESPONSE: u8 = 0xC;
+    const MODE_RTC_SEMAPHORE: u8 = 0xD;
+    const MODE_IR: u8 = 0xE;
+
+    // commands run instantly, so the clock always reports itself as ready
+    const SEMAPHORE_READY: u8 = 0x01;
+    const IR_IDLE: u8 = 0xC0;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        HuC3 {
+            ram: vec![0; ram_size_from_header(&rom)],
+            rom,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_infrared_port(mut self, infrared: Arc<dyn InfraredPort>) -> Self {
+        self.infrared = infrared;
+        self
+    }
+
+    pub fn with_clock_source(mut self, clock_source: Arc<dyn ClockSource>) -> Self {
+        self.set_clock_source(clock_source);
+        self
+    }
+
+    pub fn set_clock_source(&mut self, clock_source: Arc<dyn ClockSource>) {
+        self.clock.set_clock_source(clock_source);
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn get_clock(&self) -> &HuC3Clock {
+        &self.clock
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if self.ram.is_empty() {
+            return None;
+        }
+        let offset =
+            self.ram_bank as usize * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for HuC3 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (HuC3::ROM_BANK_RANGE_START..=HuC3::ROM_BANK_RANGE_END).contains(&address) {
+            self.rom_bank = value & 0x7F;
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        if (HuC3::RAM_BANK_RANGE_START..=HuC3::RAM_BANK_RANGE_END).contains(&address) {
+            self.ram_bank = value & 0x03;
+            return true;
+        }
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        Some(match self.mode {
+            HuC3::MODE_RAM_READ | HuC3::MODE_RAM_READ_WRITE => match self.ram_address(address) {
+                Some(offset) => self.ram[offset],
+                None => 0xFF,
+            },
+            HuC3::MODE_RTC_RESPONSE => self.clock.read_response(),
+            HuC3::MODE_RTC_SEMAPHORE => 0xFE | HuC3::SEMAPHORE_READY,
+            HuC3::MODE_IR => HuC3::IR_IDLE | self.infrared.is_receiving_light() as u8,
+            _ => 0xFF,
+        })
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        match self.mode {
+            HuC3::MODE_RAM_READ_WRITE => {
+                if let Some(offset) = self.ram_address(address) {
+                    self.ram[offset] = value;
+                }
+            }
+            HuC3::MODE_RTC_COMMAND => self.clock.set_command(value),
+            // clearing bit 0 hands the latched command over to the clock
+            HuC3::MODE_RTC_SEMAPHORE if value & 0x01 == 0 => self.clock.execute(),
+            HuC3::MODE_IR => self.infrared.set_led(value & 0x01 != 0),
+            _ => {}
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (HuC3::MODE_RANGE_START..=HuC3::MODE_RANGE_END).contains(&address) {
+            self.mode = value & 0x0F;
+        }
+        self.mode == HuC3::MODE_RAM_READ_WRITE
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.clock.tick(cycles);
+    }
+
+    // every HuC3 cart has a battery for both the RAM and the clock
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        let mut data = self.ram.clone();
+        data.extend_from_slice(&self.clock.to_footer());
+        Some(data)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+        if data.len() > self.ram.len() {
+            self.clock.load_footer(&data[self.ram.len()..]);
+        }
+    }
+
+    fn new() -> Self {
+        HuC3 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            mode: HuC3::MODE_RAM_READ,
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            ram: Vec::new(),
+            clock: HuC3Clock::new(Arc::new(SystemClock {})),
+            infrared: Arc::new(NoInfrared {}),
+            rom_path: String::new(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// Host side of the IR port found on the Hudson carts. The cart turns its LED on and off and
+// reads back whether its sensor currently sees light, the frontend decides what is on the
+// other side (nothing, another emulator, a real adapter...).
+pub trait InfraredPort: Send + Sync {
+    fn set_led(&self, on: bool);
+    fn is_receiving_light(&self) -> bool;
+}
+
+// nothing in front of the sensor, the LED goes nowhere
+pub struct NoInfrared {}
+
+impl InfraredPort for NoInfrared {
+    fn set_led(&self, _on: bool) {}
+
+    fn is_receiving_light(&self) -> bool {
+        false
+    }
+}
+
+// Keeps both sides of the port in shared flags, clones observe the same state so the
+// frontend can keep one while the cart owns the other
+#[derive(Clone)]
+pub struct SharedInfrared {
+    led: Arc<AtomicBool>,
+    light: Arc<AtomicBool>,
+}
+
+impl SharedInfrared {
+    pub fn new() -> Self {
+        SharedInfrared {
+            led: Arc::new(AtomicBool::new(false)),
+            light: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_led_on(&self) -> bool {
+        self.led.load(Ordering::Relaxed)
+    }
+
+    pub fn set_light(&self, receiving: bool) {
+        self.light.store(receiving, Ordering::Relaxed);
+    }
+}
+
+impl Default for SharedInfrared {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InfraredPort for SharedInfrared {
+    fn set_led(&self, on: bool) {
+        self.led.store(on, Ordering::Relaxed);
+    }
+
+    fn is_receiving_light(&self) -> bool {
+        self.light.load(Ordering::Relaxed)
+    }
+}
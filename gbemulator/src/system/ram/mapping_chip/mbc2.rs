@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use super::{
+    read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip, MemoryBank,
+    CARTRIDGE_TYPE_ADDRESS,
+};
+
+#[derive(Clone)]
+pub struct MBC2 {
+    rom_bank: u8,
+    ram_enabled: bool,
+    has_battery: bool,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rom_path: String,
+}
+
+impl MBC2 {
+    const REGISTERS_RANGE_START: u16 = 0x0000;
+    const REGISTERS_RANGE_END: u16 = 0x3FFF;
+    // address bit 8 tells the RAM enable register (clear) from the ROM bank one (set)
+    const REGISTER_SELECT_BIT: u16 = 0x0100;
+    const RAM_SIZE: usize = 512;
+    const BATTERY_CARTRIDGE_TYPE: u8 = 0x06;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        let has_battery = rom.get(CARTRIDGE_TYPE_ADDRESS) == Some(&MBC2::BATTERY_CARTRIDGE_TYPE);
+        MBC2 {
+            rom_bank: 0x01,
+            ram_enabled: false,
+            has_battery,
+            rom,
+            ram: vec![0; MBC2::RAM_SIZE],
+            rom_path: String::new(),
+        }
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn is_register_address(address: u16) -> bool {
+        (MBC2::REGISTERS_RANGE_START..=MBC2::REGISTERS_RANGE_END).contains(&address)
+    }
+}
+
+impl MappingChip for MBC2 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if MBC2::is_register_address(address) && address & MBC2::REGISTER_SELECT_BIT != 0 {
+            self.rom_bank = match value & 0x0F {
+                0 => 1,
+                bank => bank,
+            };
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    // only the lower nibble of each cell exists, the upper one floats high. The 512 cells are
+    // mirrored across the whole external RAM window.
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        if !self.ram_enabled {
+            return Some(0xFF);
+        }
+        Some(0xF0 | self.ram[address as usize % MBC2::RAM_SIZE])
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if self.ram_enabled {
+            self.ram[address as usize % MBC2::RAM_SIZE] = value & 0x0F;
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if MBC2::is_register_address(address) && address & MBC2::REGISTER_SELECT_BIT == 0 {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        }
+        self.ram_enabled
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery {
+            return None;
+        }
+        Some(self.ram.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        for (cell, value) in self.ram.iter_mut().zip(data.iter()) {
+            *cell = value & 0x0F;
+        }
+    }
+
+    fn new() -> Self {
+        MBC2 {
+            rom_bank: 0x01,
+            ram_enabled: false,
+            has_battery: false,
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            ram: vec![0; MBC2::RAM_SIZE],
+            rom_path: String::new(),
+        }
+    }
+}
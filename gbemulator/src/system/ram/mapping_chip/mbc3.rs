@@ -0,0 +1,204 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::rtc::{ClockSource, RealTimeClock, SystemClock, RTC_DAYS_HIGH, RTC_SECONDS};
+use super::{
+    ram_size_from_header, read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip,
+    MemoryBank, CARTRIDGE_TYPE_ADDRESS, EXTERNAL_RAM_START, RAM_BANK_SIZE,
+};
+
+#[derive(Clone)]
+pub struct MBC3 {
+    rom_bank: u8,
+    // 0x00-0x03 selects a RAM bank, 0x08-0x0C one of the clock registers
+    ram_bank: u8,
+    ram_enabled: bool,
+    has_battery: bool,
+    has_rtc: bool,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: RealTimeClock,
+    rom_path: String,
+}
+
+impl MBC3 {
+    const RAM_ENABLE_RANGE_START: u16 = 0x0000;
+    const RAM_ENABLE_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_RANGE_END: u16 = 0x3FFF;
+    const RAM_BANK_RANGE_START: u16 = 0x4000;
+    const RAM_BANK_RANGE_END: u16 = 0x5FFF;
+    const LATCH_RANGE_START: u16 = 0x6000;
+    const LATCH_RANGE_END: u16 = 0x7FFF;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        let cartridge_type = rom.get(CARTRIDGE_TYPE_ADDRESS).copied().unwrap_or(0x11);
+        MBC3 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            ram_enabled: false,
+            has_battery: matches!(cartridge_type, 0x0F | 0x10 | 0x13),
+            has_rtc: matches!(cartridge_type, 0x0F | 0x10),
+            ram: vec![0; ram_size_from_header(&rom)],
+            rom,
+            rtc: RealTimeClock::new(Arc::new(SystemClock {})),
+            rom_path: String::new(),
+        }
+    }
+
+    pub fn with_clock_source(mut self, clock_source: Arc<dyn ClockSource>) -> Self {
+        self.set_clock_source(clock_source);
+        self
+    }
+
+    pub fn set_clock_source(&mut self, clock_source: Arc<dyn ClockSource>) {
+        self.rtc.set_clock_source(clock_source);
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn get_rtc(&self) -> &RealTimeClock {
+        &self.rtc
+    }
+
+    pub fn has_rtc(&self) -> bool {
+        self.has_rtc
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn is_rtc_selected(&self) -> bool {
+        self.has_rtc && (RTC_SECONDS..=RTC_DAYS_HIGH).contains(&self.ram_bank)
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if self.ram.is_empty() || self.ram_bank > 0x03 {
+            return None;
+        }
+        let offset =
+            self.ram_bank as usize * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for MBC3 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (MBC3::ROM_BANK_RANGE_START..=MBC3::ROM_BANK_RANGE_END).contains(&address) {
+            self.rom_bank = match value & 0x7F {
+                0 => 1,
+                bank => bank,
+            };
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        if (MBC3::RAM_BANK_RANGE_START..=MBC3::RAM_BANK_RANGE_END).contains(&address) {
+            self.ram_bank = value & 0x0F;
+            return true;
+        }
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        if !self.ram_enabled {
+            return Some(0xFF);
+        }
+        if self.is_rtc_selected() {
+            return Some(self.rtc.read(self.ram_bank));
+        }
+        match self.ram_address(address) {
+            Some(offset) => Some(self.ram[offset]),
+            None => Some(0xFF),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if !self.ram_enabled {
+            return true;
+        }
+        if self.is_rtc_selected() {
+            self.rtc.write(self.ram_bank, value);
+        } else if let Some(offset) = self.ram_address(address) {
+            self.ram[offset] = value;
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (MBC3::RAM_ENABLE_RANGE_START..=MBC3::RAM_ENABLE_RANGE_END).contains(&address) {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        }
+        self.ram_enabled
+    }
+
+    // MBC3 has no banking mode, 0x6000-0x7FFF latches the clock instead
+    fn is_selecting_mode(&mut self, address: u16, value: u8) -> bool {
+        if self.has_rtc && (MBC3::LATCH_RANGE_START..=MBC3::LATCH_RANGE_END).contains(&address) {
+            self.rtc.latch(value);
+        }
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        if self.has_rtc {
+            self.rtc.tick(cycles);
+        }
+    }
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery {
+            return None;
+        }
+        let mut data = self.ram.clone();
+        if self.has_rtc {
+            data.extend_from_slice(&self.rtc.to_footer());
+        }
+        Some(data)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+        if self.has_rtc && data.len() > self.ram.len() {
+            self.rtc.load_footer(&data[self.ram.len()..]);
+        }
+    }
+
+    fn new() -> Self {
+        MBC3 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            ram_enabled: false,
+            has_battery: false,
+            has_rtc: false,
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            ram: Vec::new(),
+            rtc: RealTimeClock::new(Arc::new(SystemClock {})),
+            rom_path: String::new(),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::{
+    ram_size_from_header, read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip,
+    MemoryBank, CARTRIDGE_TYPE_ADDRESS, EXTERNAL_RAM_START, RAM_BANK_SIZE,
+};
+
+// Called with the new motor state every time a rumble cart turns it on or off
+pub type RumbleCallback = Arc<dyn Fn(bool) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct MBC5 {
+    rom_bank: u16,
+    ram_bank: u8,
+    ram_enabled: bool,
+    has_battery: bool,
+    has_rumble: bool,
+    rumbling: bool,
+    rumble_callback: Option<RumbleCallback>,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rom_path: String,
+}
+
+impl MBC5 {
+    const RAM_ENABLE_RANGE_START: u16 = 0x0000;
+    const RAM_ENABLE_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_LOW_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_LOW_RANGE_END: u16 = 0x2FFF;
+    const ROM_BANK_HIGH_RANGE_START: u16 = 0x3000;
+    const ROM_BANK_HIGH_RANGE_END: u16 = 0x3FFF;
+    const RAM_BANK_RANGE_START: u16 = 0x4000;
+    const RAM_BANK_RANGE_END: u16 = 0x5FFF;
+    const RUMBLE_MOTOR_BIT: u8 = 0x08;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        let cartridge_type = rom.get(CARTRIDGE_TYPE_ADDRESS).copied().unwrap_or(0x19);
+        MBC5 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            ram_enabled: false,
+            has_battery: matches!(cartridge_type, 0x1B | 0x1E),
+            has_rumble: matches!(cartridge_type, 0x1C..=0x1E),
+            rumbling: false,
+            rumble_callback: None,
+            ram: vec![0; ram_size_from_header(&rom)],
+            rom,
+            rom_path: String::new(),
+        }
+    }
+
+    pub fn with_rumble_callback(mut self, callback: RumbleCallback) -> Self {
+        self.rumble_callback = Some(callback);
+        self
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn has_rumble(&self) -> bool {
+        self.has_rumble
+    }
+
+    pub fn is_rumbling(&self) -> bool {
+        self.rumbling
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn set_rumble(&mut self, rumbling: bool) {
+        if rumbling == self.rumbling {
+            return;
+        }
+        self.rumbling = rumbling;
+        if let Some(callback) = &self.rumble_callback {
+            callback(rumbling);
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+        let offset =
+            self.ram_bank as usize * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for MBC5 {
+    // unlike the older MBCs, bank 0 can be mapped in the upper window as well
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (MBC5::ROM_BANK_LOW_RANGE_START..=MBC5::ROM_BANK_LOW_RANGE_END).contains(&address) {
+            self.rom_bank = (self.rom_bank & 0x100) | value as u16;
+            return true;
+        }
+        if (MBC5::ROM_BANK_HIGH_RANGE_START..=MBC5::ROM_BANK_HIGH_RANGE_END).contains(&address) {
+            self.rom_bank = (self.rom_bank & 0xFF) | (((value & 0x01) as u16) << 8);
+            return true;
+        }
+        false
+    }
+
+    // on rumble carts the motor is wired to the RAM bank bit 3 instead of the RAM chip
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        if (MBC5::RAM_BANK_RANGE_START..=MBC5::RAM_BANK_RANGE_END).contains(&address) {
+            if self.has_rumble {
+                self.ram_bank = value & 0x07;
+                self.set_rumble(value & MBC5::RUMBLE_MOTOR_BIT != 0);
+            } else {
+                self.ram_bank = value & 0x0F;
+            }
+            return true;
+        }
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        match self.ram_address(address) {
+            Some(offset) => Some(self.ram[offset]),
+            None => Some(0xFF),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if let Some(offset) = self.ram_address(address) {
+            self.ram[offset] = value;
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (MBC5::RAM_ENABLE_RANGE_START..=MBC5::RAM_ENABLE_RANGE_END).contains(&address) {
+            self.ram_enabled = value == 0x0A;
+        }
+        self.ram_enabled
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery {
+            return None;
+        }
+        Some(self.ram.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    fn new() -> Self {
+        MBC5 {
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+            ram_enabled: false,
+            has_battery: false,
+            has_rumble: false,
+            rumbling: false,
+            rumble_callback: None,
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            ram: Vec::new(),
+            rom_path: String::new(),
+        }
+    }
+}
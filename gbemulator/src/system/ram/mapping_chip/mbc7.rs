@@ -0,0 +1,213 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::accelerometer::{tilt_to_reading, Accelerometer, FlatAccelerometer};
+use super::eeprom::Eeprom93LC56;
+use super::{read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip, MemoryBank};
+
+#[derive(Clone)]
+pub struct MBC7 {
+    rom_bank: u8,
+    // the external window needs both enable registers set
+    ram_enabled_1: bool,
+    ram_enabled_2: bool,
+    // erased latch, the next 0xAA write samples the sensor
+    latch_ready: bool,
+    x: u16,
+    y: u16,
+    eeprom: Eeprom93LC56,
+    accelerometer: Arc<dyn Accelerometer>,
+    rom: Vec<u8>,
+    rom_path: String,
+}
+
+impl MBC7 {
+    const RAM_ENABLE_1_RANGE_START: u16 = 0x0000;
+    const RAM_ENABLE_1_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_RANGE_END: u16 = 0x3FFF;
+    const RAM_ENABLE_2_RANGE_START: u16 = 0x4000;
+    const RAM_ENABLE_2_RANGE_END: u16 = 0x5FFF;
+    // only 0xA000-0xAFFF decodes the registers, 0xB000-0xBFFF always reads 0xFF
+    const REGISTERS_RANGE_END: u16 = 0xAFFF;
+
+    // registers are selected by address bits 4-7
+    const REGISTER_ERASE_LATCH: u16 = 0x0;
+    const REGISTER_LATCH: u16 = 0x1;
+    const REGISTER_X_LOW: u16 = 0x2;
+    const REGISTER_X_HIGH: u16 = 0x3;
+    const REGISTER_Y_LOW: u16 = 0x4;
+    const REGISTER_Y_HIGH: u16 = 0x5;
+    const REGISTER_ZERO: u16 = 0x6;
+    const REGISTER_ONES: u16 = 0x7;
+    const REGISTER_EEPROM: u16 = 0x8;
+
+    const ERASE_LATCH_VALUE: u8 = 0x55;
+    const LATCH_VALUE: u8 = 0xAA;
+    const LATCHED_RESET_VALUE: u16 = 0x8000;
+
+    const EEPROM_CS: u8 = 0x80;
+    const EEPROM_CLK: u8 = 0x40;
+    const EEPROM_DI: u8 = 0x02;
+    const EEPROM_DO: u8 = 0x01;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        MBC7 { rom, ..Self::new() }
+    }
+
+    pub fn with_accelerometer(mut self, accelerometer: Arc<dyn Accelerometer>) -> Self {
+        self.accelerometer = accelerometer;
+        self
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn get_eeprom(&self) -> &Eeprom93LC56 {
+        &self.eeprom
+    }
+
+    pub fn get_latched_tilt(&self) -> (u16, u16) {
+        (self.x, self.y)
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        self.rom_bank as usize % rom_bank_count(&self.rom)
+    }
+
+    fn is_register_window_enabled(&self, address: u16) -> bool {
+        self.ram_enabled_1 && self.ram_enabled_2 && address <= MBC7::REGISTERS_RANGE_END
+    }
+
+    fn read_eeprom_register(&self) -> u8 {
+        let mut value = 0;
+        if self.eeprom.get_cs() {
+            value |= MBC7::EEPROM_CS;
+        }
+        if self.eeprom.get_clk() {
+            value |= MBC7::EEPROM_CLK;
+        }
+        if self.eeprom.get_di() {
+            value |= MBC7::EEPROM_DI;
+        }
+        if self.eeprom.get_do() {
+            value |= MBC7::EEPROM_DO;
+        }
+        value
+    }
+}
+
+impl MappingChip for MBC7 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (MBC7::ROM_BANK_RANGE_START..=MBC7::ROM_BANK_RANGE_END).contains(&address) {
+            self.rom_bank = value & 0x7F;
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, 0, 0x0000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        if !self.is_register_window_enabled(address) {
+            return Some(0xFF);
+        }
+        Some(match (address >> 4) & 0x0F {
+            MBC7::REGISTER_X_LOW => (self.x & 0xFF) as u8,
+            MBC7::REGISTER_X_HIGH => (self.x >> 8) as u8,
+            MBC7::REGISTER_Y_LOW => (self.y & 0xFF) as u8,
+            MBC7::REGISTER_Y_HIGH => (self.y >> 8) as u8,
+            MBC7::REGISTER_ZERO => 0x00,
+            MBC7::REGISTER_ONES => 0xFF,
+            MBC7::REGISTER_EEPROM => self.read_eeprom_register(),
+            _ => 0xFF,
+        })
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if !self.is_register_window_enabled(address) {
+            return true;
+        }
+        match (address >> 4) & 0x0F {
+            MBC7::REGISTER_ERASE_LATCH if value == MBC7::ERASE_LATCH_VALUE => {
+                self.x = MBC7::LATCHED_RESET_VALUE;
+                self.y = MBC7::LATCHED_RESET_VALUE;
+                self.latch_ready = true;
+            }
+            MBC7::REGISTER_LATCH if value == MBC7::LATCH_VALUE && self.latch_ready => {
+                let (x, y) = self.accelerometer.tilt();
+                self.x = tilt_to_reading(x);
+                self.y = tilt_to_reading(y);
+                self.latch_ready = false;
+            }
+            MBC7::REGISTER_EEPROM => self.eeprom.set_pins(
+                value & MBC7::EEPROM_CS != 0,
+                value & MBC7::EEPROM_CLK != 0,
+                value & MBC7::EEPROM_DI != 0,
+            ),
+            _ => {}
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (MBC7::RAM_ENABLE_1_RANGE_START..=MBC7::RAM_ENABLE_1_RANGE_END).contains(&address) {
+            self.ram_enabled_1 = value == 0x0A;
+        }
+        if (MBC7::RAM_ENABLE_2_RANGE_START..=MBC7::RAM_ENABLE_2_RANGE_END).contains(&address) {
+            self.ram_enabled_2 = value == 0x40;
+        }
+        self.ram_enabled_1 && self.ram_enabled_2
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    // the EEPROM is non volatile, it is always saved
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        Some(self.eeprom.to_bytes())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        self.eeprom.load_bytes(data);
+    }
+
+    fn new() -> Self {
+        MBC7 {
+            rom_bank: 0x01,
+            ram_enabled_1: false,
+            ram_enabled_2: false,
+            latch_ready: false,
+            x: MBC7::LATCHED_RESET_VALUE,
+            y: MBC7::LATCHED_RESET_VALUE,
+            eeprom: Eeprom93LC56::new(),
+            accelerometer: Arc::new(FlatAccelerometer {}),
+            rom: vec![0; 2 * super::ROM_BANK_SIZE],
+            rom_path: String::new(),
+        }
+    }
+}
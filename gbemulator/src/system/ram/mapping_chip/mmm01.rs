@@ -0,0 +1,229 @@
+use std::str::FromStr;
+
+use super::{
+    ram_size_from_header, read_rom_file, rom_bank_count, rom_bank_from_contents, MappingChip,
+    MemoryBank, CARTRIDGE_TYPE_ADDRESS, EXTERNAL_RAM_START, RAM_BANK_SIZE, ROM_BANK_SIZE,
+};
+
+// MMM01 multicarts boot into a menu stored in the last 32KiB of the ROM, which is also where
+// the cartridge header describing the MMM01 lives. The menu configures the outer banks and the
+// masks of the game it launches, then sets the map enable bit: from then on the chip behaves
+// like an MBC1 confined to the selected game and the menu registers are frozen.
+#[derive(Clone)]
+pub struct MMM01 {
+    rom_bank_low: u8,
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    // bits set here keep ROM bank bits 1-4 at the value the menu chose
+    rom_bank_mask: u8,
+    mapped: bool,
+    ram_enabled: bool,
+    has_battery: bool,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rom_path: String,
+}
+
+impl MMM01 {
+    const RAM_ENABLE_RANGE_START: u16 = 0x0000;
+    const RAM_ENABLE_RANGE_END: u16 = 0x1FFF;
+    const ROM_BANK_RANGE_START: u16 = 0x2000;
+    const ROM_BANK_RANGE_END: u16 = 0x3FFF;
+    const RAM_BANK_RANGE_START: u16 = 0x4000;
+    const RAM_BANK_RANGE_END: u16 = 0x5FFF;
+    const MODE_RANGE_START: u16 = 0x6000;
+    const MODE_RANGE_END: u16 = 0x7FFF;
+    const MAP_ENABLE_BIT: u8 = 0x40;
+    const MENU_SIZE: usize = 2 * ROM_BANK_SIZE;
+    const BATTERY_CARTRIDGE_TYPE: u8 = 0x0D;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        let menu = &rom[MMM01::menu_offset(&rom)..];
+        MMM01 {
+            has_battery: menu.get(CARTRIDGE_TYPE_ADDRESS) == Some(&MMM01::BATTERY_CARTRIDGE_TYPE),
+            ram: vec![0; ram_size_from_header(menu)],
+            rom,
+            ..Self::new()
+        }
+    }
+
+    // the header at the start of the file belongs to the first game, the MMM01 one is in the menu
+    pub fn is_mmm01_rom(rom: &[u8]) -> bool {
+        rom.len() >= MMM01::MENU_SIZE
+            && matches!(
+                rom.get(MMM01::menu_offset(rom) + CARTRIDGE_TYPE_ADDRESS),
+                Some(0x0B..=0x0D)
+            )
+    }
+
+    fn menu_offset(rom: &[u8]) -> usize {
+        rom.len().saturating_sub(MMM01::MENU_SIZE)
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.mapped
+    }
+
+    // ROM bank bits the game can still change once the menu has mapped it
+    fn writable_rom_bits(&self) -> u8 {
+        0x1F & !(self.rom_bank_mask << 1)
+    }
+
+    fn outer_rom_bank(&self) -> usize {
+        ((self.rom_bank_high as usize) << 7) | ((self.rom_bank_mid as usize) << 5)
+    }
+
+    pub fn get_base_rom_bank_number(&self) -> usize {
+        let count = rom_bank_count(&self.rom);
+        if !self.mapped {
+            return (count - 2) % count;
+        }
+        let low = self.rom_bank_low & !self.writable_rom_bits();
+        (self.outer_rom_bank() | low as usize) % count
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        let count = rom_bank_count(&self.rom);
+        if !self.mapped {
+            return count - 1;
+        }
+        let mut low = self.rom_bank_low;
+        // same as MBC1, bank 0 of the game cannot be selected in the upper window
+        if low & self.writable_rom_bits() == 0 {
+            low |= 0x01;
+        }
+        (self.outer_rom_bank() | low as usize) % count
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+        let bank = ((self.ram_bank_high << 2) | self.ram_bank_low) as usize;
+        let offset = bank * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for MMM01 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (MMM01::ROM_BANK_RANGE_START..=MMM01::ROM_BANK_RANGE_END).contains(&address) {
+            if self.mapped {
+                let writable = self.writable_rom_bits();
+                self.rom_bank_low = (self.rom_bank_low & !writable) | (value & writable);
+            } else {
+                self.rom_bank_low = value & 0x1F;
+                self.rom_bank_mid = (value >> 5) & 0x03;
+            }
+            return true;
+        }
+        if !self.mapped
+            && (MMM01::RAM_BANK_RANGE_START..=MMM01::RAM_BANK_RANGE_END).contains(&address)
+        {
+            self.rom_bank_high = (value >> 4) & 0x03;
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        if (MMM01::RAM_BANK_RANGE_START..=MMM01::RAM_BANK_RANGE_END).contains(&address) {
+            self.ram_bank_low = value & 0x03;
+            if !self.mapped {
+                self.ram_bank_high = (value >> 2) & 0x03;
+            }
+            return true;
+        }
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_base_rom_bank_number(), 0x0000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        match self.ram_address(address) {
+            Some(offset) => Some(self.ram[offset]),
+            None => Some(0xFF),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if let Some(offset) = self.ram_address(address) {
+            self.ram[offset] = value;
+        }
+        true
+    }
+
+    fn is_ram_enabled(&mut self, _address: u16, _value: u8) -> bool {
+        self.ram_enabled
+    }
+
+    // The map enable bit shares the RAM enable register, it is handled here as it remaps both
+    // ROM windows and this is the first register hook to run
+    fn is_selecting_mode(&mut self, address: u16, value: u8) -> bool {
+        if (MMM01::RAM_ENABLE_RANGE_START..=MMM01::RAM_ENABLE_RANGE_END).contains(&address) {
+            self.ram_enabled = value & 0x0F == 0x0A;
+            if !self.mapped && value & MMM01::MAP_ENABLE_BIT != 0 {
+                self.mapped = true;
+                return true;
+            }
+            return false;
+        }
+        if !self.mapped && (MMM01::MODE_RANGE_START..=MMM01::MODE_RANGE_END).contains(&address) {
+            self.rom_bank_mask = (value >> 2) & 0x0F;
+        }
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery {
+            return None;
+        }
+        Some(self.ram.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    fn new() -> Self {
+        MMM01 {
+            rom_bank_low: 0x00,
+            rom_bank_mid: 0x00,
+            rom_bank_high: 0x00,
+            ram_bank_low: 0x00,
+            ram_bank_high: 0x00,
+            rom_bank_mask: 0x00,
+            mapped: false,
+            ram_enabled: false,
+            has_battery: false,
+            rom: vec![0; MMM01::MENU_SIZE],
+            ram: Vec::new(),
+            rom_path: String::new(),
+        }
+    }
+}
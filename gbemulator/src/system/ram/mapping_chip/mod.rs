@@ -0,0 +1,786 @@
+pub mod accelerometer;
+pub mod eeprom;
+pub mod huc1;
+pub mod huc3;
+pub mod infrared;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod mbc7;
+pub mod mmm01;
+pub mod rtc;
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+pub use huc1::HuC1;
+pub use huc3::HuC3;
+pub use mbc2::MBC2;
+pub use mbc3::MBC3;
+pub use mbc5::MBC5;
+pub use mbc7::MBC7;
+pub use mmm01::MMM01;
+use rtc::ClockSource;
+
+pub const ROM_BANK_SIZE: usize = 16 * 1024;
+pub const RAM_BANK_SIZE: usize = 8 * 1024;
+pub const EXTERNAL_RAM_START: u16 = 0xA000;
+pub const EXTERNAL_RAM_END: u16 = 0xBFFF;
+const LOGO_ADDRESS: usize = 0x0104;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x0147;
+const RAM_SIZE_ADDRESS: usize = 0x0149;
+
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+fn read_rom_file(rom_path: &str) -> Vec<u8> {
+    let buffer = BufReader::new(File::open(rom_path).unwrap());
+    buffer.bytes().map(|x| x.unwrap()).collect()
+}
+
+// number of 16KiB banks in the ROM, rounded up to a power of two as the bank lines are
+// simply not connected on smaller carts
+fn rom_bank_count(rom: &[u8]) -> usize {
+    rom.len().div_ceil(ROM_BANK_SIZE).max(2).next_power_of_two()
+}
+
+fn rom_bank_from_contents(rom: &[u8], bank_number: usize, address: u16) -> MemoryBank {
+    let mut bank = MemoryBank::new(address);
+    let start = bank_number * ROM_BANK_SIZE;
+    if start < rom.len() {
+        let end = rom.len().min(start + ROM_BANK_SIZE);
+        bank.contents[..(end - start)].copy_from_slice(&rom[start..end]);
+    }
+    bank
+}
+
+// .sav files live next to the ROM, as most emulators do
+pub fn battery_save_path(rom_path: &str) -> String {
+    Path::new(rom_path)
+        .with_extension("sav")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn ram_size_from_header(rom: &[u8]) -> usize {
+    match rom.get(RAM_SIZE_ADDRESS) {
+        Some(0x01) => 2 * 1024,
+        Some(0x02) => 8 * 1024,
+        Some(0x03) => 32 * 1024,
+        Some(0x04) => 128 * 1024,
+        Some(0x05) => 64 * 1024,
+        _ => 0,
+    }
+}
+
+pub struct MemoryBank {
+    pub address: u16,
+    pub contents: [u8; 16 * 1024],
+}
+
+impl MemoryBank {
+    pub fn new(address: u16) -> Self {
+        MemoryBank {
+            address: address,
+            contents: [0u8; 16 * 1024],
+        }
+    }
+}
+
+pub trait MappingChip: Clone {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool;
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool;
+    fn get_selected_rom_bank(&self) -> MemoryBank;
+    fn get_base_rom_bank(&self) -> MemoryBank;
+    // Reads from the 0xA000-0xBFFF window, None leaves the access to the flat RAM
+    fn read_ram(&self, address: u16) -> Option<u8>;
+    // Writes to the 0xA000-0xBFFF window, false leaves the access to the flat RAM
+    fn write_ram(&mut self, address: u16, value: u8) -> bool;
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool;
+    fn is_selecting_mode(&mut self, address: u16, value: u8) -> bool;
+    // Whether writes to 0x0000-0x7FFF also land in memory after the chip has seen them
+    fn is_rom_writable(&self) -> bool;
+    // Advances the cartridge's own hardware (clocks, timers) by the given machine cycles
+    fn tick(&mut self, cycles: u64);
+    // Contents to persist in the .sav file, None for carts without a battery
+    fn get_battery_data(&self) -> Option<Vec<u8>>;
+    fn load_battery_data(&mut self, data: &[u8]);
+    fn new() -> Self;
+}
+
+#[derive(Clone)]
+pub struct FakeChip {}
+
+impl MappingChip for FakeChip {
+    fn is_selecting_chip_rom(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        let mut bank = MemoryBank::new(0x0000);
+
+        let start_address = 0x0104 as usize;
+
+        let contents = vec![
+            0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C,
+            0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6,
+            0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC,
+            0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+        ];
+        for i in 0..contents.len() {
+            bank.contents[start_address + i] = contents[i];
+        }
+        bank.contents[0x014d as usize] = 0xE7;
+        bank.contents[0x0100 as usize] = 0x00;
+        bank.contents[0x0101 as usize] = 0xC3;
+        bank.contents[0x0102 as usize] = 0x50;
+        bank.contents[0x0103 as usize] = 0x01;
+        bank
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        self.get_selected_rom_bank()
+    }
+
+    fn read_ram(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_ram_enabled(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        true
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn load_battery_data(&mut self, _data: &[u8]) {}
+
+    fn new() -> Self {
+        FakeChip {}
+    }
+}
+
+#[derive(Clone)]
+pub enum MemoryMode {
+    ROM,
+    RAM,
+}
+
+#[derive(Clone)]
+pub struct NoChip {
+    rom_path: String,
+}
+
+impl NoChip {
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        NoChip {
+            rom_path: String::from_str(rom_path).unwrap(),
+        }
+    }
+    pub fn read_rom_contents(&self) -> Vec<u8> {
+        let buffer = BufReader::new(File::open(self.rom_path.as_str()).unwrap());
+        let bytes: Vec<u8> = buffer.bytes().map(|x| x.unwrap()).collect();
+        // let mut file = File::create("output.hex").unwrap();
+
+        // for (i, byte) in bytes.iter().enumerate() {
+        //     write!(file, "{:04X} {:02x} \n", i, byte).unwrap();
+        // }
+        bytes
+    }
+}
+
+impl MappingChip for NoChip {
+    fn is_selecting_chip_rom(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_selecting_chip_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        let mut bank = MemoryBank::new(0x4000);
+        let contents = self.read_rom_contents();
+        for (i, value) in contents.iter().enumerate() {
+            if i > (0x3FFF as usize) {
+                bank.contents[i - 0x4000 as usize] = *value;
+            }
+        }
+        bank
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        let mut bank = MemoryBank::new(0x0000);
+        let contents = self.read_rom_contents();
+        for (i, value) in contents.iter().enumerate() {
+            if i < (0x4000 as usize) {
+                bank.contents[i] = *value;
+            }
+        }
+        bank
+    }
+
+    fn read_ram(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write_ram(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_ram_enabled(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_selecting_mode(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn load_battery_data(&mut self, _data: &[u8]) {}
+
+    fn new() -> Self {
+        NoChip {
+            rom_path: String::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MBC1 {
+    bank1: u8,
+    bank2: u8,
+    ram_enabled: bool,
+    mode: MemoryMode,
+    multicart: bool,
+    has_battery: bool,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rom_path: String,
+}
+
+impl MBC1 {
+    const RAM_ENABLE_RANGE_START: u16 = 0x0000;
+    const RAM_ENABLE_RANGE_END: u16 = 0x1FFF;
+    const BANK1_RANGE_START: u16 = 0x2000;
+    const BANK1_RANGE_END: u16 = 0x3FFF;
+    const BANK2_RANGE_START: u16 = 0x4000;
+    const BANK2_RANGE_END: u16 = 0x5FFF;
+    const MODE_RANGE_START: u16 = 0x6000;
+    const MODE_RANGE_END: u16 = 0x7FFF;
+    const MULTICART_ROM_SIZE: usize = 1024 * 1024;
+    const MULTICART_GAME_BANK: usize = 0x10;
+    const BATTERY_CARTRIDGE_TYPE: u8 = 0x03;
+
+    pub fn from_rom_path(rom_path: &str) -> Self {
+        let mut chip = Self::from_rom_contents(read_rom_file(rom_path));
+        chip.rom_path = String::from_str(rom_path).unwrap();
+        chip
+    }
+
+    pub fn from_rom_contents(rom: Vec<u8>) -> Self {
+        let ram_size = ram_size_from_header(&rom);
+        let multicart = MBC1::detect_multicart(&rom);
+        let has_battery = rom.get(CARTRIDGE_TYPE_ADDRESS) == Some(&MBC1::BATTERY_CARTRIDGE_TYPE);
+        MBC1 {
+            bank1: 0x01,
+            bank2: 0x00,
+            ram_enabled: false,
+            mode: MemoryMode::ROM,
+            multicart,
+            has_battery,
+            rom,
+            ram: vec![0; ram_size],
+            rom_path: String::new(),
+        }
+    }
+
+    pub fn read_rom_contents(&self) -> Vec<u8> {
+        self.rom.clone()
+    }
+
+    pub fn is_multicart(&self) -> bool {
+        self.multicart
+    }
+
+    pub fn get_rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    // MBC1M carts are 8 Mbit MBC1 boards with BANK2 wired one bit lower. The cartridge header
+    // does not tell them apart, so look for a second game header (and its logo) at bank 0x10.
+    fn detect_multicart(rom: &[u8]) -> bool {
+        if rom.len() != MBC1::MULTICART_ROM_SIZE {
+            return false;
+        }
+        let logo_start = MBC1::MULTICART_GAME_BANK * ROM_BANK_SIZE + LOGO_ADDRESS;
+        rom[logo_start..(logo_start + NINTENDO_LOGO.len())] == NINTENDO_LOGO
+    }
+
+    fn bank2_shift(&self) -> u8 {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn bank1_mask(&self) -> u8 {
+        if self.multicart {
+            0x0F
+        } else {
+            0x1F
+        }
+    }
+
+    pub fn get_base_rom_bank_number(&self) -> usize {
+        let bank = match self.mode {
+            MemoryMode::ROM => 0,
+            MemoryMode::RAM => (self.bank2 as usize) << self.bank2_shift(),
+        };
+        bank % rom_bank_count(&self.rom)
+    }
+
+    pub fn get_selected_rom_bank_number(&self) -> usize {
+        let bank = ((self.bank2 as usize) << self.bank2_shift())
+            | (self.bank1 & self.bank1_mask()) as usize;
+        bank % rom_bank_count(&self.rom)
+    }
+
+    pub fn get_selected_ram_bank_number(&self) -> usize {
+        match self.mode {
+            MemoryMode::ROM => 0,
+            MemoryMode::RAM => self.bank2 as usize,
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+        let offset = self.get_selected_ram_bank_number() * RAM_BANK_SIZE
+            + (address - EXTERNAL_RAM_START) as usize;
+        Some(offset % self.ram.len())
+    }
+}
+
+impl MappingChip for MBC1 {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        if (MBC1::BANK1_RANGE_START..=MBC1::BANK1_RANGE_END).contains(&address) {
+            // the zero check happens on all five bits, even on multicarts where only four are wired
+            self.bank1 = match value & 0x1F {
+                0 => 1,
+                bank => bank,
+            };
+            return true;
+        }
+        if (MBC1::BANK2_RANGE_START..=MBC1::BANK2_RANGE_END).contains(&address) {
+            self.bank2 = value & 0x03;
+            return true;
+        }
+        false
+    }
+
+    fn is_selecting_mode(&mut self, address: u16, value: u8) -> bool {
+        if (MBC1::MODE_RANGE_START..=MBC1::MODE_RANGE_END).contains(&address) {
+            self.mode = if value & 0x01 == 0 {
+                MemoryMode::ROM
+            } else {
+                MemoryMode::RAM
+            };
+            return true;
+        }
+        false
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        if (MBC1::RAM_ENABLE_RANGE_START..=MBC1::RAM_ENABLE_RANGE_END).contains(&address) {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        }
+        self.ram_enabled
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, _value: u8) -> bool {
+        // BANK2 is shared with the ROM banking and already latched by is_selecting_chip_rom
+        (MBC1::BANK2_RANGE_START..=MBC1::BANK2_RANGE_END).contains(&address)
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_base_rom_bank_number(), 0x0000)
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        rom_bank_from_contents(&self.rom, self.get_selected_rom_bank_number(), 0x4000)
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        match self.ram_address(address) {
+            Some(offset) => Some(self.ram[offset]),
+            None => Some(0xFF),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if let Some(offset) = self.ram_address(address) {
+            self.ram[offset] = value;
+        }
+        true
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _cycles: u64) {}
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        if !self.has_battery {
+            return None;
+        }
+        Some(self.ram.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let length = self.ram.len().min(data.len());
+        self.ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    fn new() -> Self {
+        MBC1 {
+            bank1: 0x01,
+            bank2: 0x00,
+            ram_enabled: false,
+            mode: MemoryMode::ROM,
+            multicart: false,
+            has_battery: false,
+            rom: vec![0; 2 * ROM_BANK_SIZE],
+            ram: Vec::new(),
+            rom_path: String::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum DynamicMappingChip {
+    FakeChip(FakeChip),
+    NoChip(NoChip),
+    MBC1(MBC1),
+    MBC2(MBC2),
+    MBC3(MBC3),
+    MBC5(MBC5),
+    MBC7(MBC7),
+    MMM01(MMM01),
+    HuC1(HuC1),
+    HuC3(HuC3),
+}
+
+impl DynamicMappingChip {
+    // the chip for the ROM, with the battery save next to it loaded when there is one
+    pub fn from_rom_path(rom_path: &str) -> Result<Self, String> {
+        let mut chip = Self::from_rom_path_without_battery(rom_path)?;
+        let save_path = battery_save_path(rom_path);
+        if chip.has_battery() && Path::new(&save_path).exists() {
+            chip.load_battery(&save_path)
+                .map_err(|e| format!("unable to load {}: {}", save_path, e))?;
+        }
+        Ok(chip)
+    }
+
+    pub fn from_rom_path_without_battery(rom_path: &str) -> Result<Self, String> {
+        let header = read_rom_file(rom_path);
+        if header.len() <= CARTRIDGE_TYPE_ADDRESS {
+            return Err(format!("{} is too short to be a GameBoy ROM", rom_path));
+        }
+        let chip = match header[CARTRIDGE_TYPE_ADDRESS] {
+            _ if MMM01::is_mmm01_rom(&header) => {
+                DynamicMappingChip::MMM01(MMM01::from_rom_path(rom_path))
+            }
+            0x00 => DynamicMappingChip::NoChip(NoChip::from_rom_path(rom_path)),
+            0x01..=0x03 => DynamicMappingChip::MBC1(MBC1::from_rom_path(rom_path)),
+            0x05 | 0x06 => DynamicMappingChip::MBC2(MBC2::from_rom_path(rom_path)),
+            0x0F..=0x13 => DynamicMappingChip::MBC3(MBC3::from_rom_path(rom_path)),
+            0x19..=0x1E => DynamicMappingChip::MBC5(MBC5::from_rom_path(rom_path)),
+            0x22 => DynamicMappingChip::MBC7(MBC7::from_rom_path(rom_path)),
+            0xFE => DynamicMappingChip::HuC3(HuC3::from_rom_path(rom_path)),
+            0xFF => DynamicMappingChip::HuC1(HuC1::from_rom_path(rom_path)),
+            cartridge_type => {
+                return Err(format!(
+                    "unsupported cartridge type 0x{:02X} in {}",
+                    cartridge_type, rom_path
+                ))
+            }
+        };
+        Ok(chip)
+    }
+
+    pub fn get_rom_path(&self) -> Option<&str> {
+        match self {
+            DynamicMappingChip::FakeChip(_) => None,
+            DynamicMappingChip::NoChip(nc) => Some(nc.rom_path.as_str()),
+            DynamicMappingChip::MBC1(mbc1) => Some(mbc1.get_rom_path()),
+            DynamicMappingChip::MBC2(mbc2) => Some(mbc2.get_rom_path()),
+            DynamicMappingChip::MBC3(mbc3) => Some(mbc3.get_rom_path()),
+            DynamicMappingChip::MBC5(mbc5) => Some(mbc5.get_rom_path()),
+            DynamicMappingChip::MBC7(mbc7) => Some(mbc7.get_rom_path()),
+            DynamicMappingChip::MMM01(mmm01) => Some(mmm01.get_rom_path()),
+            DynamicMappingChip::HuC1(huc1) => Some(huc1.get_rom_path()),
+            DynamicMappingChip::HuC3(huc3) => Some(huc3.get_rom_path()),
+        }
+    }
+
+    // where the cartridge clock, if there is one, reads the time when a battery save is loaded
+    // or written
+    pub fn set_clock_source(&mut self, clock_source: Arc<dyn ClockSource>) {
+        match self {
+            DynamicMappingChip::MBC3(mbc3) => mbc3.set_clock_source(clock_source),
+            DynamicMappingChip::HuC3(huc3) => huc3.set_clock_source(clock_source),
+            _ => {}
+        }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.get_battery_data().is_some()
+    }
+
+    pub fn is_rumbling(&self) -> bool {
+        match self {
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_rumbling(),
+            _ => false,
+        }
+    }
+
+    pub fn save_battery(&self, save_path: &str) -> std::io::Result<()> {
+        match self.get_battery_data() {
+            Some(data) => std::fs::write(save_path, data),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load_battery(&mut self, save_path: &str) -> std::io::Result<()> {
+        let data = std::fs::read(save_path)?;
+        self.load_battery_data(&data);
+        Ok(())
+    }
+}
+
+impl MappingChip for DynamicMappingChip {
+    fn is_selecting_chip_rom(&mut self, address: u16, value: u8) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::HuC1(huc1) => huc1.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::HuC3(huc3) => huc3.is_selecting_chip_rom(address, value),
+            DynamicMappingChip::NoChip(nc) => nc.is_selecting_chip_rom(address, value),
+        }
+    }
+
+    fn is_selecting_chip_ram(&mut self, address: u16, value: u8) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::HuC1(huc1) => huc1.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::HuC3(huc3) => huc3.is_selecting_chip_ram(address, value),
+            DynamicMappingChip::NoChip(nc) => nc.is_selecting_chip_ram(address, value),
+        }
+    }
+
+    fn get_base_rom_bank(&self) -> MemoryBank {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.get_base_rom_bank(),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.get_base_rom_bank(),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.get_base_rom_bank(),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.get_base_rom_bank(),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.get_base_rom_bank(),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.get_base_rom_bank(),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.get_base_rom_bank(),
+            DynamicMappingChip::HuC1(huc1) => huc1.get_base_rom_bank(),
+            DynamicMappingChip::HuC3(huc3) => huc3.get_base_rom_bank(),
+            DynamicMappingChip::NoChip(nc) => nc.get_base_rom_bank(),
+        }
+    }
+
+    fn get_selected_rom_bank(&self) -> MemoryBank {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.get_selected_rom_bank(),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.get_selected_rom_bank(),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.get_selected_rom_bank(),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.get_selected_rom_bank(),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.get_selected_rom_bank(),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.get_selected_rom_bank(),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.get_selected_rom_bank(),
+            DynamicMappingChip::HuC1(huc1) => huc1.get_selected_rom_bank(),
+            DynamicMappingChip::HuC3(huc3) => huc3.get_selected_rom_bank(),
+            DynamicMappingChip::NoChip(nc) => nc.get_selected_rom_bank(),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> Option<u8> {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.read_ram(address),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.read_ram(address),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.read_ram(address),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.read_ram(address),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.read_ram(address),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.read_ram(address),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.read_ram(address),
+            DynamicMappingChip::HuC1(huc1) => huc1.read_ram(address),
+            DynamicMappingChip::HuC3(huc3) => huc3.read_ram(address),
+            DynamicMappingChip::NoChip(nc) => nc.read_ram(address),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.write_ram(address, value),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.write_ram(address, value),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.write_ram(address, value),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.write_ram(address, value),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.write_ram(address, value),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.write_ram(address, value),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.write_ram(address, value),
+            DynamicMappingChip::HuC1(huc1) => huc1.write_ram(address, value),
+            DynamicMappingChip::HuC3(huc3) => huc3.write_ram(address, value),
+            DynamicMappingChip::NoChip(nc) => nc.write_ram(address, value),
+        }
+    }
+
+    fn is_ram_enabled(&mut self, address: u16, value: u8) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.is_ram_enabled(address, value),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.is_ram_enabled(address, value),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.is_ram_enabled(address, value),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.is_ram_enabled(address, value),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_ram_enabled(address, value),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.is_ram_enabled(address, value),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.is_ram_enabled(address, value),
+            DynamicMappingChip::HuC1(huc1) => huc1.is_ram_enabled(address, value),
+            DynamicMappingChip::HuC3(huc3) => huc3.is_ram_enabled(address, value),
+            DynamicMappingChip::NoChip(nc) => nc.is_ram_enabled(address, value),
+        }
+    }
+
+    fn is_selecting_mode(&mut self, address: u16, value: u8) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.is_selecting_mode(address, value),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.is_selecting_mode(address, value),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.is_selecting_mode(address, value),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.is_selecting_mode(address, value),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_selecting_mode(address, value),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.is_selecting_mode(address, value),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.is_selecting_mode(address, value),
+            DynamicMappingChip::HuC1(huc1) => huc1.is_selecting_mode(address, value),
+            DynamicMappingChip::HuC3(huc3) => huc3.is_selecting_mode(address, value),
+            DynamicMappingChip::NoChip(nc) => nc.is_selecting_mode(address, value),
+        }
+    }
+
+    fn is_rom_writable(&self) -> bool {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.is_rom_writable(),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.is_rom_writable(),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.is_rom_writable(),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.is_rom_writable(),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.is_rom_writable(),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.is_rom_writable(),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.is_rom_writable(),
+            DynamicMappingChip::HuC1(huc1) => huc1.is_rom_writable(),
+            DynamicMappingChip::HuC3(huc3) => huc3.is_rom_writable(),
+            DynamicMappingChip::NoChip(nc) => nc.is_rom_writable(),
+        }
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.tick(cycles),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.tick(cycles),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.tick(cycles),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.tick(cycles),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.tick(cycles),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.tick(cycles),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.tick(cycles),
+            DynamicMappingChip::HuC1(huc1) => huc1.tick(cycles),
+            DynamicMappingChip::HuC3(huc3) => huc3.tick(cycles),
+            DynamicMappingChip::NoChip(nc) => nc.tick(cycles),
+        }
+    }
+
+    fn get_battery_data(&self) -> Option<Vec<u8>> {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.get_battery_data(),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.get_battery_data(),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.get_battery_data(),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.get_battery_data(),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.get_battery_data(),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.get_battery_data(),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.get_battery_data(),
+            DynamicMappingChip::HuC1(huc1) => huc1.get_battery_data(),
+            DynamicMappingChip::HuC3(huc3) => huc3.get_battery_data(),
+            DynamicMappingChip::NoChip(nc) => nc.get_battery_data(),
+        }
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        match self {
+            DynamicMappingChip::FakeChip(fc) => fc.load_battery_data(data),
+            DynamicMappingChip::MBC1(mbc1) => mbc1.load_battery_data(data),
+            DynamicMappingChip::MBC2(mbc2) => mbc2.load_battery_data(data),
+            DynamicMappingChip::MBC3(mbc3) => mbc3.load_battery_data(data),
+            DynamicMappingChip::MBC5(mbc5) => mbc5.load_battery_data(data),
+            DynamicMappingChip::MBC7(mbc7) => mbc7.load_battery_data(data),
+            DynamicMappingChip::MMM01(mmm01) => mmm01.load_battery_data(data),
+            DynamicMappingChip::HuC1(huc1) => huc1.load_battery_data(data),
+            DynamicMappingChip::HuC3(huc3) => huc3.load_battery_data(data),
+            DynamicMappingChip::NoChip(nc) => nc.load_battery_data(data),
+        }
+    }
+
+    fn new() -> Self {
+        DynamicMappingChip::FakeChip(FakeChip::new())
+    }
+}
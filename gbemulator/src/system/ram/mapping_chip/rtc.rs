@@ -0,0 +1,284 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the CPU counts machine cycles, which run at a quarter of the 4.19MHz crystal
+pub const CYCLES_PER_SECOND: u64 = 1_048_576;
+// 5 live registers, 5 latched registers (all as little endian u32) and a 64 bit unix timestamp
+pub const RTC_FOOTER_SIZE: usize = 48;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DAY_COUNTER_LIMIT: u64 = 512;
+
+pub const RTC_SECONDS: u8 = 0x08;
+pub const RTC_MINUTES: u8 = 0x09;
+pub const RTC_HOURS: u8 = 0x0A;
+pub const RTC_DAYS_LOW: u8 = 0x0B;
+pub const RTC_DAYS_HIGH: u8 = 0x0C;
+
+const DAYS_HIGH_BIT8: u8 = 0x01;
+const DAYS_HIGH_HALT: u8 = 0x40;
+const DAYS_HIGH_CARRY: u8 = 0x80;
+
+// Source of wall-clock time for the RTC. It is only used to catch up on the time that passed
+// while the emulator was not running, during play the clock advances with the emulated cycles.
+pub trait ClockSource: Send + Sync {
+    fn unix_time(&self) -> u64;
+}
+
+pub struct SystemClock {}
+
+impl ClockSource for SystemClock {
+    fn unix_time(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone)]
+pub struct ManualClock {
+    time: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(unix_time: u64) -> Self {
+        ManualClock {
+            time: Arc::new(AtomicU64::new(unix_time)),
+        }
+    }
+
+    pub fn set(&self, unix_time: u64) {
+        self.time.store(unix_time, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.time.fetch_add(seconds, Ordering::Relaxed);
+    }
+}
+
+impl ClockSource for ManualClock {
+    fn unix_time(&self) -> u64 {
+        self.time.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct RtcRegisters {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    pub days_low: u8,
+    pub days_high: u8,
+}
+
+impl RtcRegisters {
+    pub fn get_days(&self) -> u16 {
+        (((self.days_high & DAYS_HIGH_BIT8) as u16) << 8) | self.days_low as u16
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.days_high & DAYS_HIGH_HALT != 0
+    }
+
+    pub fn has_day_carry(&self) -> bool {
+        self.days_high & DAYS_HIGH_CARRY != 0
+    }
+
+    fn set_days(&mut self, days: u16) {
+        self.days_low = (days & 0xFF) as u8;
+        self.days_high = (self.days_high & !DAYS_HIGH_BIT8) | ((days >> 8) as u8 & DAYS_HIGH_BIT8);
+    }
+
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            RTC_SECONDS => self.seconds,
+            RTC_MINUTES => self.minutes,
+            RTC_HOURS => self.hours,
+            RTC_DAYS_LOW => self.days_low,
+            RTC_DAYS_HIGH => self.days_high,
+            _ => 0xFF,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 20] {
+        let mut bytes = [0u8; 20];
+        for (i, value) in [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days_low,
+            self.days_high,
+        ]
+        .iter()
+        .enumerate()
+        {
+            bytes[(i * 4)..(i * 4 + 4)].copy_from_slice(&(*value as u32).to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let value = |i: usize| bytes[i * 4];
+        RtcRegisters {
+            seconds: value(0) & 0x3F,
+            minutes: value(1) & 0x3F,
+            hours: value(2) & 0x1F,
+            days_low: value(3),
+            days_high: value(4) & (DAYS_HIGH_BIT8 | DAYS_HIGH_HALT | DAYS_HIGH_CARRY),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RealTimeClock {
+    registers: RtcRegisters,
+    latched: RtcRegisters,
+    cycles: u64,
+    latch_armed: bool,
+    clock_source: Arc<dyn ClockSource>,
+}
+
+impl RealTimeClock {
+    pub fn new(clock_source: Arc<dyn ClockSource>) -> Self {
+        RealTimeClock {
+            registers: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            cycles: 0,
+            latch_armed: false,
+            clock_source,
+        }
+    }
+
+    pub fn set_clock_source(&mut self, clock_source: Arc<dyn ClockSource>) {
+        self.clock_source = clock_source;
+    }
+
+    pub fn get_registers(&self) -> RtcRegisters {
+        self.registers
+    }
+
+    pub fn get_latched_registers(&self) -> RtcRegisters {
+        self.latched
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        if self.registers.is_halted() {
+            return;
+        }
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.increment_second();
+        }
+    }
+
+    // the counters are only 6/5 bits wide: out of range values written by the game keep
+    // counting until they wrap without carrying into the next register
+    fn increment_second(&mut self) {
+        let r = &mut self.registers;
+        r.seconds = (r.seconds + 1) & 0x3F;
+        if r.seconds != 60 {
+            return;
+        }
+        r.seconds = 0;
+        r.minutes = (r.minutes + 1) & 0x3F;
+        if r.minutes != 60 {
+            return;
+        }
+        r.minutes = 0;
+        r.hours = (r.hours + 1) & 0x1F;
+        if r.hours != 24 {
+            return;
+        }
+        r.hours = 0;
+        let days = r.get_days() + 1;
+        if days as u64 == DAY_COUNTER_LIMIT {
+            r.days_high |= DAYS_HIGH_CARRY;
+        }
+        r.set_days(days % DAY_COUNTER_LIMIT as u16);
+    }
+
+    pub fn advance_seconds(&mut self, seconds: u64) {
+        if self.registers.is_halted() || seconds == 0 {
+            return;
+        }
+        let r = self.registers;
+        if r.seconds >= 60 || r.minutes >= 60 || r.hours >= 24 {
+            for _ in 0..seconds {
+                self.increment_second();
+            }
+            return;
+        }
+        let total = r.seconds as u64
+            + r.minutes as u64 * 60
+            + r.hours as u64 * 3600
+            + r.get_days() as u64 * SECONDS_PER_DAY
+            + seconds;
+        let days = total / SECONDS_PER_DAY;
+        let r = &mut self.registers;
+        if days >= DAY_COUNTER_LIMIT {
+            r.days_high |= DAYS_HIGH_CARRY;
+        }
+        r.set_days((days % DAY_COUNTER_LIMIT) as u16);
+        r.hours = ((total % SECONDS_PER_DAY) / 3600) as u8;
+        r.minutes = ((total % 3600) / 60) as u8;
+        r.seconds = (total % 60) as u8;
+    }
+
+    // writing 0x00 then 0x01 copies the running counters into the readable registers
+    pub fn latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers;
+        }
+        self.latch_armed = value == 0x00;
+    }
+
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        let r = &mut self.registers;
+        match register {
+            RTC_SECONDS => {
+                r.seconds = value & 0x3F;
+                self.cycles = 0;
+            }
+            RTC_MINUTES => r.minutes = value & 0x3F,
+            RTC_HOURS => r.hours = value & 0x1F,
+            RTC_DAYS_LOW => r.days_low = value,
+            RTC_DAYS_HIGH => {
+                r.days_high = value & (DAYS_HIGH_BIT8 | DAYS_HIGH_HALT | DAYS_HIGH_CARRY)
+            }
+            _ => {}
+        }
+    }
+
+    pub fn to_footer(&self) -> [u8; RTC_FOOTER_SIZE] {
+        let mut footer = [0u8; RTC_FOOTER_SIZE];
+        footer[0..20].copy_from_slice(&self.registers.to_bytes());
+        footer[20..40].copy_from_slice(&self.latched.to_bytes());
+        footer[40..48].copy_from_slice(&self.clock_source.unix_time().to_le_bytes());
+        footer
+    }
+
+    // also accepts the older 44 byte variant with a 32 bit timestamp
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() < RTC_FOOTER_SIZE - 4 {
+            return;
+        }
+        self.registers = RtcRegisters::from_bytes(&footer[0..20]);
+        self.latched = RtcRegisters::from_bytes(&footer[20..40]);
+        let timestamp = if footer.len() >= RTC_FOOTER_SIZE {
+            u64::from_le_bytes(footer[40..48].try_into().unwrap())
+        } else {
+            u32::from_le_bytes(footer[40..44].try_into().unwrap()) as u64
+        };
+        self.cycles = 0;
+        let now = self.clock_source.unix_time();
+        self.advance_seconds(now.saturating_sub(timestamp));
+    }
+}
@@ -1,14 +1,25 @@
 pub mod lcd_registers;
 pub mod mapping_chip;
-pub mod sound_registers;
 
-const BOOT_ROM_END: u16 = 0x00FF;
+const CARTRIDGE_HEADER_START: u16 = 0x0100;
+const CARTRIDGE_HEADER_END: u16 = 0x01FF;
+const CARTRIDGE_ROM_END: u16 = 0x7FFF;
 const BOOTLOCKER_ADDRESS: u16 = 0xFF50;
 const BOOTLOCKER_LOCKED: u8 = 0x00;
 const BOOTLOCKER_UNLOCKED: u8 = 0x01;
 const DMA_ADDRESS: u16 = 0xFF46;
-
-use mapping_chip::MappingChip;
+const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+
+use crate::system::apu::Apu;
+use crate::system::boot::GameBoyModel;
+use crate::system::divider::{Divider, DIV_ADDRESS};
+use crate::system::joypad::{Button, Joypad, JoypadInput, JOYPAD_ADDRESS, JOYPAD_INTERRUPT};
+use crate::system::link_cable::LinkCable;
+use crate::system::serial::{
+    Serial, SerialCallback, SERIAL_CONTROL_ADDRESS, SERIAL_DATA_ADDRESS, SERIAL_INTERRUPT,
+};
+use mapping_chip::{MappingChip, EXTERNAL_RAM_END, EXTERNAL_RAM_START};
+use std::sync::{Arc, Mutex};
 
 macro_rules! default_memory_register_trait_impl {
     ($name:ident,$reset_value:expr) => {
@@ -57,6 +68,12 @@ pub struct RAM {
     #[allow(dead_code)]
     capacity: usize,
     mapping_chip: DynamicMappingChip,
+    // overlays the start of the cartridge on reads until 0xFF50 is written
+    boot_rom: Option<BootRom>,
+    joypad: Joypad,
+    serial: Serial,
+    divider: Divider,
+    apu: Apu,
 }
 
 impl Clone for RAM {
@@ -65,6 +82,11 @@ impl Clone for RAM {
             data: self.data.clone(),
             capacity: self.capacity.clone(),
             mapping_chip: self.mapping_chip.clone(),
+            boot_rom: self.boot_rom.clone(),
+            joypad: self.joypad.clone(),
+            serial: self.serial.clone(),
+            divider: self.divider.clone(),
+            apu: self.apu.clone(),
         }
     }
 }
@@ -82,19 +104,105 @@ impl RAM {
             data: data,
             capacity: capacity,
             mapping_chip: dynamic_chip,
+            boot_rom: None,
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            divider: Divider::new(),
+            apu: Apu::new(),
         }
     }
 
     pub fn get_at(&self, address: u16) -> Option<u8> {
+        self.read_on_cycle(address, 0)
+    }
+
+    // an access by an instruction after pending_cycles of its M-cycles, which RAM is ticked for
+    // once it has run
+    pub fn read_on_cycle(&self, address: u16, pending_cycles: u64) -> Option<u8> {
+        if let Some(value) = self.boot_rom.as_ref().and_then(|b| b.read(address)) {
+            return Some(value);
+        }
+        match address {
+            JOYPAD_ADDRESS => return Some(self.joypad.read()),
+            SERIAL_DATA_ADDRESS => return Some(self.serial.read_data()),
+            SERIAL_CONTROL_ADDRESS => return Some(self.serial.read_control()),
+            DIV_ADDRESS => return Some(self.divider.read()),
+            _ => {}
+        }
+        if Apu::handles(address) {
+            return Some(self.apu.read(address, pending_cycles));
+        }
+        if (EXTERNAL_RAM_START..=EXTERNAL_RAM_END).contains(&address) {
+            if let Some(value) = self.mapping_chip.read_ram(address) {
+                return Some(value);
+            }
+        }
         self.data.get(address as usize).copied()
     }
 
     pub fn set_at(&mut self, address: u16, value: u8) -> Option<()> {
+        self.write_on_cycle(address, value, 0)
+    }
+
+    pub fn write_on_cycle(&mut self, address: u16, value: u8, pending_cycles: u64) -> Option<()> {
+        // the overlay goes away with the first non zero write and the register latches, so it
+        // cannot be brought back
+        if address == BOOTLOCKER_ADDRESS {
+            if self.data[BOOTLOCKER_ADDRESS as usize] != BOOTLOCKER_LOCKED {
+                return Some(());
+            }
+            if value != BOOTLOCKER_LOCKED {
+                self.boot_rom = None;
+            }
+        }
+        if address <= CARTRIDGE_ROM_END {
+            let mode_changed = self.mapping_chip.is_selecting_mode(address, value);
+            let rom_changed = self.mapping_chip.is_selecting_chip_rom(address, value);
+            self.mapping_chip.is_selecting_chip_ram(address, value);
+            self.mapping_chip.is_ram_enabled(address, value);
+            // the mode and BANK2 registers can also remap 0x0000-0x3FFF, so refresh both windows
+            if mode_changed || rom_changed {
+                self.load_base_rom_bank();
+            }
+            if !self.mapping_chip.is_rom_writable() {
+                return Some(());
+            }
+        }
+        if (EXTERNAL_RAM_START..=EXTERNAL_RAM_END).contains(&address)
+            && self.mapping_chip.write_ram(address, value)
+        {
+            return Some(());
+        }
+        if address == JOYPAD_ADDRESS {
+            if self.joypad.write(value) {
+                self.request_interrupt(JOYPAD_INTERRUPT);
+            }
+            return Some(());
+        }
+        if address == SERIAL_DATA_ADDRESS {
+            self.serial.write_data(value);
+            return Some(());
+        }
+        if address == SERIAL_CONTROL_ADDRESS {
+            self.serial.write_control(value);
+            return Some(());
+        }
+        if address == DIV_ADDRESS {
+            if self.divider.write() {
+                self.apu.clock_frame_sequencer();
+            }
+            return Some(());
+        }
+        if Apu::handles(address) {
+            self.apu.write(address, value, pending_cycles);
+            return Some(());
+        }
         if address == DMA_ADDRESS {
             let source_address = (value as u16) << 8;
             let target_address = 0xFE00;
             for i in 0..0xA0 {
-                self.data[(target_address + i) as usize] = self.data[(source_address + i) as usize];
+                // through the bus, the source can be banked external RAM or mapped registers
+                self.data[(target_address + i) as usize] = self.get_at(source_address + i).unwrap();
             }
         }
         match self.data.get_mut(address as usize) {
@@ -109,19 +217,6 @@ impl RAM {
         if address == 0xFF0F {
             println!("setting active interrupts to {}", value);
         }
-
-        if self.data[BOOTLOCKER_ADDRESS as usize] == BOOTLOCKER_UNLOCKED {
-            if self.mapping_chip.is_selecting_mode(address, value) {
-            } else if self.mapping_chip.is_selecting_chip_rom(address, value) {
-                let bank = self.mapping_chip.get_selected_rom_bank();
-                self.data[(bank.address as usize)..(bank.address as usize + (16 * 1024))]
-                    .copy_from_slice(&bank.contents);
-            } else if self.mapping_chip.is_selecting_chip_ram(address, value) {
-                let bank = self.mapping_chip.get_selected_ram_bank();
-                self.data[(bank.address as usize)..(bank.address as usize + (16 * 1024))]
-                    .copy_from_slice(&bank.contents);
-            }
-        }
         Some(())
     }
 
@@ -144,6 +239,96 @@ impl RAM {
         result
     }
 
+    pub fn tick(&mut self, cycles: u64) {
+        self.mapping_chip.tick(cycles);
+        if self.joypad.update() {
+            self.request_interrupt(JOYPAD_INTERRUPT);
+        }
+        if self.serial.tick(cycles) {
+            self.request_interrupt(SERIAL_INTERRUPT);
+        }
+        let divider_counter = self.divider.get_counter();
+        self.divider.tick(cycles);
+        self.apu.tick(cycles, divider_counter);
+    }
+
+    // the CPU should wait for the other end of the link cable, see Serial::is_stalled
+    pub fn is_link_stalled(&self) -> bool {
+        self.serial.is_stalled()
+    }
+
+    // takes in what the other end of the link cable sent, without running any time
+    pub fn poll_link(&mut self) {
+        if self.serial.tick(0) {
+            self.request_interrupt(SERIAL_INTERRUPT);
+        }
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: u8) {
+        self.data[INTERRUPT_FLAG_ADDRESS as usize] |= interrupt;
+    }
+
+    // the frontend can also press buttons through the shared input, they are seen on the next tick
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.joypad.get_input().set_button(button, pressed);
+        if self.joypad.update() {
+            self.request_interrupt(JOYPAD_INTERRUPT);
+        }
+    }
+
+    pub fn set_joypad_state(&mut self, state: u8) {
+        self.joypad.get_input().set_state(state);
+        if self.joypad.update() {
+            self.request_interrupt(JOYPAD_INTERRUPT);
+        }
+    }
+
+    pub fn get_serial_output(&self) -> &[u8] {
+        self.serial.get_output()
+    }
+
+    pub fn set_serial_callback(&mut self, callback: SerialCallback) {
+        self.serial.set_output_callback(callback);
+    }
+
+    pub fn set_link_cable<C: LinkCable + 'static>(&mut self, link_cable: C) {
+        self.serial.set_link_cable(Arc::new(Mutex::new(link_cable)));
+    }
+
+    pub fn get_joypad_input(&self) -> JoypadInput {
+        self.joypad.get_input()
+    }
+
+    pub fn map_boot_rom(&mut self, boot_rom: BootRom) {
+        self.data[BOOTLOCKER_ADDRESS as usize] = BOOTLOCKER_LOCKED;
+        self.boot_rom = Some(boot_rom);
+    }
+
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    // for states that were not reached by running, writing DIV would clear it
+    pub fn load_div(&mut self, div: u8) {
+        self.divider.load(div);
+    }
+
+    pub fn get_apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn get_apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub fn get_mapping_chip(&self) -> &DynamicMappingChip {
+        &self.mapping_chip
+    }
+
+    pub fn get_mapping_chip_mut(&mut self) -> &mut DynamicMappingChip {
+        &mut self.mapping_chip
+    }
+
     pub fn load_base_rom_bank(&mut self) {
         let bank = self.mapping_chip.get_base_rom_bank();
         self.data[(bank.address as usize)..(bank.address as usize + (16 * 1024))]
@@ -174,8 +359,10 @@ pub struct BootLockMemoryRegister {
     value: u8,
 }
 
+#[derive(Clone)]
 pub struct BootRom {
     contents: Vec<u8>,
+    model: GameBoyModel,
 }
 
 impl BootLockMemoryRegister {
@@ -185,25 +372,15 @@ impl BootLockMemoryRegister {
             value: BOOTLOCKER_UNLOCKED,
         }
     }
-
-    pub fn lock(&mut self) {
-        self.value = BOOTLOCKER_LOCKED;
-    }
-
-    pub fn unlock(&mut self) {
-        self.value = BOOTLOCKER_UNLOCKED;
-    }
-
-    pub fn is_unlocked(&self) -> bool {
-        self.value == BOOTLOCKER_UNLOCKED
-    }
 }
 
 default_memory_register_trait_impl!(BootLockMemoryRegister, BOOTLOCKER_UNLOCKED);
 
 impl BootRom {
+    // the embedded DMG boot ROM
     pub fn new() -> Self {
         BootRom {
+            model: GameBoyModel::DMG,
             contents: vec![
                 0x31, 0xfe, 0xff, 0xaf, 0x21, 0xff, 0x9f, 0x32, 0xcb, 0x7c, 0x20, 0xfb, 0x21, 0x26,
                 0xff, 0x0e, 0x11, 0x3e, 0x80, 0x32, 0xe2, 0x0c, 0x3e, 0xf3, 0xe2, 0x32, 0x3e, 0x77,
@@ -227,6 +404,41 @@ impl BootRom {
             ],
         }
     }
+
+    pub fn from_bytes(contents: Vec<u8>, model: GameBoyModel) -> Result<Self, String> {
+        if contents.len() != model.boot_rom_size() {
+            return Err(format!(
+                "a {:?} boot ROM is {} bytes long, got {}",
+                model,
+                model.boot_rom_size(),
+                contents.len()
+            ));
+        }
+        Ok(BootRom { contents, model })
+    }
+
+    pub fn from_file(path: &str, model: GameBoyModel) -> Result<Self, String> {
+        let contents =
+            std::fs::read(path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+        BootRom::from_bytes(contents, model).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn get_model(&self) -> GameBoyModel {
+        self.model
+    }
+
+    pub fn get_contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    // None where the cartridge shows through: past the end of the boot ROM and, for the CGB
+    // one, the cartridge header between its two halves
+    pub fn read(&self, address: u16) -> Option<u8> {
+        if (CARTRIDGE_HEADER_START..=CARTRIDGE_HEADER_END).contains(&address) {
+            return None;
+        }
+        self.contents.get(address as usize).copied()
+    }
 }
 
 impl MemoryRegister for BootRom {
@@ -237,12 +449,7 @@ impl MemoryRegister for BootRom {
     default_nonimplemented_memory_register_trait_impl!();
 
     fn load_in_ram(&self, ram: &mut RAM) -> Option<()> {
-        for (address, value) in self.contents.iter().enumerate() {
-            let res = ram.set_at(address as u16, value.to_owned());
-            if res.is_none() {
-                return res;
-            }
-        }
+        ram.map_boot_rom(self.clone());
         Some(())
     }
 
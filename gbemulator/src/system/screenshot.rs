@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use super::boot::GameBoyModel;
+use super::framebuffer::shade_of_grey;
+use super::ram::mapping_chip::DynamicMappingChip;
+use super::System;
+
+pub use super::framebuffer::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// mismatching pixels are drawn in red over a faded copy of the expected image
+const DIFF_COLOR: [u8; 3] = [255, 0, 0];
+
+// Runs a ROM headless from the post boot state for the given number of frames and returns the
+// grey levels of the last one.
+pub fn run_rom_frames(rom_path: &str, frames: usize) -> Result<Vec<u8>, String> {
+    let chip = DynamicMappingChip::from_rom_path(rom_path)?;
+    let mut system = System::new(Some(chip), true).with_skip_boot(GameBoyModel::DMG);
+    system.boot();
+    for _ in 0..frames {
+        system.step_frame();
+    }
+    Ok(system.get_image_data())
+}
+
+// Reads a 160x144 PNG into grey levels, colours are converted by luminance.
+pub fn load_png(path: &str) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("unable to open {}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("unable to read {}: {}", path, e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| format!("unable to read {}: {}", path, e))?;
+    if info.width as usize != SCREEN_WIDTH || info.height as usize != SCREEN_HEIGHT {
+        return Err(format!(
+            "{} is {}x{}, expected {}x{}",
+            path, info.width, info.height, SCREEN_WIDTH, SCREEN_HEIGHT
+        ));
+    }
+    let channels = info.color_type.samples();
+    let grey = buffer[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| match pixel {
+            [grey] | [grey, _] => *grey,
+            [r, g, b, ..] => ((*r as u32 * 299 + *g as u32 * 587 + *b as u32 * 114) / 1000) as u8,
+            _ => 0,
+        })
+        .collect();
+    Ok(grey)
+}
+
+fn write_png(path: &str, data: &[u8], color_type: png::ColorType) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("unable to create {}: {}", path, e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+    );
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(data))
+        .map_err(|e| format!("unable to write {}: {}", path, e))
+}
+
+// writes grey levels, to make reference images from a known good run
+pub fn save_png(path: &str, data: &[u8]) -> Result<(), String> {
+    write_png(path, data, png::ColorType::Grayscale)
+}
+
+// Compares a frame with a reference image pixel by pixel. On a mismatch the diff image is
+// written to diff_path and the error tells how many pixels differ and where the first one is.
+pub fn compare_with_reference(
+    frame: &[u8],
+    reference_path: &str,
+    diff_path: &str,
+) -> Result<(), String> {
+    let reference = load_png(reference_path)?;
+    if frame.len() != reference.len() {
+        return Err(format!(
+            "the frame has {} pixels, expected {}",
+            frame.len(),
+            reference.len()
+        ));
+    }
+    let mismatches: Vec<usize> = (0..frame.len())
+        .filter(|i| shade_of_grey(frame[*i]) != shade_of_grey(reference[*i]))
+        .collect();
+    let first = match mismatches.first() {
+        Some(first) => *first,
+        None => return Ok(()),
+    };
+    let mut diff: Vec<u8> = reference
+        .iter()
+        .flat_map(|grey| [128 + grey / 2; 3])
+        .collect();
+    for i in &mismatches {
+        diff[i * 3..i * 3 + 3].copy_from_slice(&DIFF_COLOR);
+    }
+    write_png(diff_path, &diff, png::ColorType::Rgb)?;
+    Err(format!(
+        "{} pixels differ from {}, the first at ({}, {}) is shade {} instead of {}, see {}",
+        mismatches.len(),
+        reference_path,
+        first % SCREEN_WIDTH,
+        first / SCREEN_WIDTH,
+        shade_of_grey(frame[first]),
+        shade_of_grey(reference[first]),
+        diff_path
+    ))
+}
+
+// runs the ROM and compares its last frame with the reference, the diff image is written next
+// to the reference as <reference>.diff.png
+pub fn check_screenshot(rom_path: &str, frames: usize, reference_path: &str) -> Result<(), String> {
+    let frame = run_rom_frames(rom_path, frames)?;
+    let diff_path = format!("{}.diff.png", reference_path.trim_end_matches(".png"));
+    compare_with_reference(&frame, reference_path, &diff_path)
+}
@@ -0,0 +1,253 @@
+use std::sync::{Arc, Mutex};
+
+use super::link_cable::{LinkCable, LinkMessage};
+
+pub const SERIAL_DATA_ADDRESS: u16 = 0xFF01;
+pub const SERIAL_CONTROL_ADDRESS: u16 = 0xFF02;
+// bit 3 of IF
+pub const SERIAL_INTERRUPT: u8 = 0x08;
+
+const TRANSFER_START: u8 = 0x80;
+const INTERNAL_CLOCK: u8 = 0x01;
+const CONTROL_UNUSED_BITS: u8 = 0x7E;
+// the internal clock runs at 8192 Hz, one bit every 128 M-cycles
+const M_CYCLES_PER_BIT: u64 = 128;
+const M_CYCLES_PER_BYTE: u64 = 8 * M_CYCLES_PER_BIT;
+// how often a linked Game Boy tells the other one where its emulated time is
+const CLOCK_INTERVAL: u64 = M_CYCLES_PER_BIT;
+// How far a linked Game Boy may run ahead of the last clock it heard from the other one. Well
+// under a byte, so that the slave cannot be past the end of a transfer when it hears of it,
+// whatever the length of the instruction that ran last.
+const MAX_LEAD: u64 = M_CYCLES_PER_BYTE / 2;
+// with nothing plugged in the input line is pulled up
+const DISCONNECTED_BIT: u8 = 1;
+const DISCONNECTED_BYTE: u8 = 0xFF;
+
+// called with every byte the Game Boy sends
+pub type SerialCallback = Arc<dyn Fn(u8) + Send + Sync>;
+
+// SB and SC. Writing SC with bit 7 set starts a transfer: SB is shifted out MSB first while
+// the bits coming from the other side are shifted in. When the Game Boy provides the clock the
+// 8 bits take 1024 M-cycles, then bit 7 of SC is cleared and the serial interrupt requested.
+// With the external clock selected the partner drives the transfer, so without one it never
+// completes, like on hardware.
+//
+// With a link cable plugged in bytes are exchanged whole. The side providing the clock sends
+// its byte as the transfer starts, stamped with the cycle its 8 bits are done, and both sides
+// complete on the first tick that reaches that cycle. The two Game Boys tell each other their
+// emulated time and each one stalls when it gets too far ahead, so the stamp is never in the
+// past of the other side and transfers end at the same emulated time whatever the host
+// scheduling. The side providing the clock also stalls at the end of its transfer until the
+// answer is there. Stalls are not waited for here, see is_stalled.
+#[derive(Clone)]
+pub struct Serial {
+    data: u8,
+    control: u8,
+    bits_left: u8,
+    cycles: u64,
+    // bytes sent with the internal clock, which is how test ROMs print their results
+    output: Vec<u8>,
+    output_callback: Option<SerialCallback>,
+    link_cable: Option<Arc<Mutex<dyn LinkCable>>>,
+    // M-cycles ticked so far, the emulated time linked Game Boys agree on
+    clock: u64,
+    clock_sent: u64,
+    // the last emulated time heard from the other Game Boy
+    other_clock: u64,
+    // the cycle the transfer this side provides the clock for ends, once its byte went out
+    transfer_end: Option<u64>,
+    reply: Option<u8>,
+    // a byte from the other side and the cycle it is exchanged on
+    incoming: Option<(u8, u64)>,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Serial {
+            data: 0x00,
+            control: 0x00,
+            bits_left: 0,
+            cycles: 0,
+            output: Vec::new(),
+            output_callback: None,
+            link_cable: None,
+            clock: 0,
+            clock_sent: 0,
+            other_clock: 0,
+            transfer_end: None,
+            reply: None,
+            incoming: None,
+        }
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data = value;
+    }
+
+    pub fn read_control(&self) -> u8 {
+        CONTROL_UNUSED_BITS | self.control
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.control = value & (TRANSFER_START | INTERNAL_CLOCK);
+        if self.is_transferring() {
+            self.bits_left = 8;
+            self.cycles = 0;
+            self.transfer_end = None;
+            self.reply = None;
+            if self.is_internal_clock() {
+                self.output.push(self.data);
+                if let Some(callback) = &self.output_callback {
+                    callback(self.data);
+                }
+            }
+        }
+    }
+
+    pub fn get_output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn set_output_callback(&mut self, callback: SerialCallback) {
+        self.output_callback = Some(callback);
+    }
+
+    pub fn set_link_cable(&mut self, link_cable: Arc<Mutex<dyn LinkCable>>) {
+        self.link_cable = Some(link_cable);
+    }
+
+    pub fn is_transferring(&self) -> bool {
+        self.control & TRANSFER_START != 0
+    }
+
+    pub fn is_internal_clock(&self) -> bool {
+        self.control & INTERNAL_CLOCK != 0
+    }
+
+    // returns true when a transfer completed
+    pub fn tick(&mut self, cycles: u64) -> bool {
+        if let Some(link_cable) = self.link_cable.clone() {
+            let mut link_cable = link_cable.lock().unwrap();
+            // transfers under way still end on their cycle once the other end went away
+            if link_cable.is_connected() || self.transfer_end.is_some() || self.incoming.is_some() {
+                return self.tick_linked(cycles, &mut *link_cable);
+            }
+        }
+        self.clock += cycles;
+        if !self.is_transferring() || !self.is_internal_clock() {
+            return false;
+        }
+        self.cycles += cycles;
+        while self.cycles >= M_CYCLES_PER_BIT && self.bits_left > 0 {
+            self.cycles -= M_CYCLES_PER_BIT;
+            self.shift(DISCONNECTED_BIT);
+        }
+        if self.bits_left == 0 {
+            self.control &= !TRANSFER_START;
+            return true;
+        }
+        false
+    }
+
+    // True when the CPU should not run until the other end of the link cable caught up, or
+    // answered the byte this side clocked out. Ticking 0 cycles takes in what it sent.
+    pub fn is_stalled(&self) -> bool {
+        match &self.link_cable {
+            Some(link_cable) => self.is_waiting_for(&*link_cable.lock().unwrap()),
+            None => false,
+        }
+    }
+
+    fn is_waiting_for(&self, link_cable: &dyn LinkCable) -> bool {
+        if !link_cable.is_connected() {
+            return false;
+        }
+        let awaiting_reply = self.reply.is_none()
+            && self
+                .transfer_end
+                .is_some_and(|transfer_end| self.clock >= transfer_end);
+        awaiting_reply || (link_cable.sends_clock() && self.clock > self.other_clock + MAX_LEAD)
+    }
+
+    fn tick_linked(&mut self, cycles: u64, link_cable: &mut dyn LinkCable) -> bool {
+        if self.is_transferring() && self.is_internal_clock() && self.transfer_end.is_none() {
+            let transfer_end = self.clock + M_CYCLES_PER_BYTE;
+            link_cable.send(LinkMessage::Transfer {
+                byte: self.data,
+                cycle: transfer_end,
+            });
+            self.transfer_end = Some(transfer_end);
+        }
+        self.clock += cycles;
+        while let Some(message) = link_cable.receive() {
+            match message {
+                LinkMessage::Transfer { byte, cycle } => {
+                    // the other side started the transfer a byte earlier
+                    let started = cycle.saturating_sub(M_CYCLES_PER_BYTE);
+                    self.other_clock = self.other_clock.max(started);
+                    self.incoming = Some((byte, cycle));
+                }
+                LinkMessage::Reply(byte) => self.reply = Some(byte),
+                LinkMessage::Clock(cycle) => self.other_clock = self.other_clock.max(cycle),
+            }
+        }
+        if !link_cable.is_connected() && self.transfer_end.is_some() && self.reply.is_none() {
+            self.reply = Some(DISCONNECTED_BYTE);
+        }
+        let mut completed = false;
+        if let Some((byte, cycle)) = self.incoming {
+            if self.clock >= cycle {
+                self.incoming = None;
+                if self.is_transferring() && !self.is_internal_clock() {
+                    link_cable.send(LinkMessage::Reply(self.data));
+                    self.data = byte;
+                    self.complete_linked();
+                    completed = true;
+                } else {
+                    // no transfer was started, or both sides provide a clock and neither of
+                    // them listens to the other, nothing gets shifted
+                    link_cable.send(LinkMessage::Reply(DISCONNECTED_BYTE));
+                }
+            }
+        }
+        if let (Some(transfer_end), Some(byte)) = (self.transfer_end, self.reply) {
+            if self.clock >= transfer_end {
+                self.data = byte;
+                self.complete_linked();
+                completed = true;
+            }
+        }
+        // a stalled side tells where it stopped right away, the other one may be waiting for it
+        let stalled = self.is_waiting_for(link_cable);
+        if self.clock >= self.clock_sent + CLOCK_INTERVAL
+            || (stalled && self.clock != self.clock_sent)
+        {
+            link_cable.send(LinkMessage::Clock(self.clock));
+            self.clock_sent = self.clock;
+        }
+        completed
+    }
+
+    fn complete_linked(&mut self) {
+        self.bits_left = 0;
+        self.cycles = 0;
+        self.transfer_end = None;
+        self.reply = None;
+        self.control &= !TRANSFER_START;
+    }
+
+    fn shift(&mut self, bit_in: u8) {
+        self.data = (self.data << 1) | bit_in;
+        self.bits_left -= 1;
+    }
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BusActivity {
+    Read,
+    Write,
+    // an M-cycle spent inside the CPU, without a memory access
+    Internal,
+}
+
+// what happened on the bus during one M-cycle
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BusCycle {
+    pub address: u16,
+    // none on internal cycles
+    pub data: Option<u8>,
+    pub activity: BusActivity,
+}
+
+impl BusCycle {
+    pub fn read(address: u16, data: u8) -> Self {
+        BusCycle {
+            address,
+            data: Some(data),
+            activity: BusActivity::Read,
+        }
+    }
+
+    pub fn write(address: u16, data: u8) -> Self {
+        BusCycle {
+            address,
+            data: Some(data),
+            activity: BusActivity::Write,
+        }
+    }
+
+    pub fn internal(address: u16) -> Self {
+        BusCycle {
+            address,
+            data: None,
+            activity: BusActivity::Internal,
+        }
+    }
+
+    // The address left on the bus during internal cycles comes from the IDU, which is not
+    // modelled cycle by cycle, so only accesses are compared on the address and data.
+    pub fn matches(&self, other: &BusCycle) -> bool {
+        match self.activity {
+            BusActivity::Internal => other.activity == BusActivity::Internal,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for BusCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.activity, self.data) {
+            (BusActivity::Read, Some(data)) => {
+                write!(f, "read {:02x} from {:04x}", data, self.address)
+            }
+            (BusActivity::Write, Some(data)) => {
+                write!(f, "write {:02x} to {:04x}", data, self.address)
+            }
+            _ => write!(f, "internal"),
+        }
+    }
+}
+
+// Points at the first M-cycle where the traces differ, counting from 1.
+pub fn compare_bus_traces(expected: &[BusCycle], actual: &[BusCycle]) -> Result<(), String> {
+    for (i, (expected_cycle, actual_cycle)) in expected.iter().zip(actual).enumerate() {
+        if !expected_cycle.matches(actual_cycle) {
+            return Err(format!(
+                "M-cycle {}: expected {}, got {}",
+                i + 1,
+                expected_cycle,
+                actual_cycle
+            ));
+        }
+    }
+    if expected.len() != actual.len() {
+        let extra = match actual.get(expected.len()) {
+            Some(cycle) => format!(", M-cycle {} did {}", expected.len() + 1, cycle),
+            None => String::new(),
+        };
+        return Err(format!(
+            "expected {} M-cycles, got {}{}",
+            expected.len(),
+            actual.len(),
+            extra
+        ));
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
 pub mod alu;
+pub mod bus_trace;
 pub mod idu;
 pub mod opcodes;
 pub mod registers;
@@ -6,6 +7,7 @@ pub mod snapshot;
 
 use crate::system::ram::RAM;
 use alu::ALU;
+use bus_trace::BusCycle;
 use idu::IDU;
 use opcodes::{CBPrefixOpCode, OpCode};
 use registers::{RegisterFile, RegisterName};
@@ -27,12 +29,17 @@ pub struct SM83 {
     last_execution_time: std::time::Instant,
     iteration_time: u128,
     pub cycle_count: u128,
+    // the M-cycles of the current instruction so far, RAM is only ticked for them once it has run
+    instruction_cycles: u64,
     register_file: RegisterFile,
     address_bus: u16,
     data_bus: u8,
     ime: bool,
     last_opcode: u8,
     last_pc: u16,
+    // the M-cycles of the last instruction, only recorded when tracing is enabled
+    bus_trace: Option<Vec<BusCycle>>,
+    cycle_access: Option<BusCycle>,
 }
 
 impl SM83 {
@@ -41,12 +48,15 @@ impl SM83 {
             last_execution_time: std::time::Instant::now(),
             iteration_time: 1,
             cycle_count: 0,
+            instruction_cycles: 0,
             register_file: RegisterFile::new(),
             address_bus: 0,
             data_bus: 0,
             ime: false,
             last_opcode: 0,
             last_pc: 0,
+            bus_trace: None,
+            cycle_access: None,
         }
     }
 
@@ -127,17 +137,39 @@ impl SM83 {
     }
 
     fn read_ram(&mut self, ram: &RAM) {
-        self.data_bus = ram.get_at(self.address_bus).unwrap();
+        self.data_bus = ram
+            .read_on_cycle(self.address_bus, self.instruction_cycles)
+            .unwrap();
+        self.record_access(BusCycle::read(self.address_bus, self.data_bus));
     }
 
-    fn write_ram(&self, ram: &mut RAM) {
-        match ram.set_at(self.address_bus, self.data_bus) {
+    fn write_ram(&mut self, ram: &mut RAM) {
+        match ram.write_on_cycle(self.address_bus, self.data_bus, self.instruction_cycles) {
             Some(_) => (),
             None => panic!(
                 "Failed to write {:x} to address {:x}",
                 self.data_bus, self.address_bus
             ),
         }
+        self.record_access(BusCycle::write(self.address_bus, self.data_bus));
+    }
+
+    fn record_access(&mut self, access: BusCycle) {
+        if self.bus_trace.is_some() {
+            self.cycle_access = Some(access);
+        }
+    }
+
+    // records what the CPU did on the bus for every M-cycle of the next instructions
+    pub fn set_bus_tracing(&mut self, enabled: bool) {
+        self.bus_trace = if enabled { Some(Vec::new()) } else { None };
+        self.cycle_access = None;
+    }
+
+    // the M-cycles of the last call to next, from the one after the opcode fetch to the fetch
+    // of the next opcode
+    pub fn get_bus_trace(&self) -> &[BusCycle] {
+        self.bus_trace.as_deref().unwrap_or(&[])
     }
 
     fn push_stack(&mut self) {
@@ -248,10 +280,23 @@ impl SM83 {
             self.iteration_time = (self.iteration_time + duration.as_nanos()) / 2;
         }
         self.cycle_count += 1;
+        self.instruction_cycles += 1;
         self.last_execution_time = std::time::Instant::now();
+        if let Some(bus_trace) = &mut self.bus_trace {
+            let cycle = self
+                .cycle_access
+                .take()
+                .unwrap_or(BusCycle::internal(self.address_bus));
+            bus_trace.push(cycle);
+        }
     }
 
     pub fn next(&mut self, ram: &mut RAM) {
+        self.instruction_cycles = 0;
+        if let Some(bus_trace) = &mut self.bus_trace {
+            bus_trace.clear();
+            self.cycle_access = None;
+        }
         let ir = self.register_file.get_ir();
         let op_code = OpCode::from_ir(ir);
 
@@ -1040,14 +1085,39 @@ impl SM83 {
             panic!("unhandled TIMER interrupt");
         }
         if interrupts & SERIAL_INT == SERIAL_INT {
-            panic!("unhandled SERIAL interrupt");
+            self.dispatch_interrupt(ram, SERIAL_INT, SERIAL_INT_VECTOR);
+            return;
         }
         if interrupts & JOYPAD_INT == JOYPAD_INT {
-            panic!("unhandled JOYPAD interrupt");
+            self.dispatch_interrupt(ram, JOYPAD_INT, JOYPAD_INT_VECTOR);
+            return;
         }
         panic!("unknown interrupt");
     }
 
+    // Acknowledges the interrupt and calls its handler. The opcode in IR was fetched but not
+    // executed yet, so the return address pushed is the one it was fetched from.
+    fn dispatch_interrupt(&mut self, ram: &mut RAM, interrupt: u8, vector: u16) {
+        self.ime = false;
+        let flags = ram.get_at(0xFF0F).unwrap();
+        ram.set_at(0xFF0F, flags & !interrupt);
+        self.address_bus = self.register_file.get_pc();
+        self.idu_decrement();
+        self.register_file.set_pc(self.address_bus);
+        self.tick_clock();
+        self.push_stack();
+        self.tick_clock();
+        self.data_bus = ((self.register_file.get_pc() & 0xFF00) >> 8) as u8;
+        self.write_ram(ram);
+        self.push_stack();
+        self.tick_clock();
+        self.data_bus = (self.register_file.get_pc() & 0x00FF) as u8;
+        self.write_ram(ram);
+        self.register_file.set_pc(vector);
+        self.tick_clock();
+        self.fetch_cycle(ram);
+    }
+
     pub fn fps(&self) -> f32 {
         1. / (self.iteration_time as f32 * 1e-9)
     }
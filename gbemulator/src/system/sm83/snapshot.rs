@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub struct SM83Snapshot {
     pub address_bus: u16,
     pub data_bus: u8,
@@ -0,0 +1,58 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use super::boot::GameBoyModel;
+use super::ram::mapping_chip::DynamicMappingChip;
+use super::System;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TestRomResult {
+    Passed,
+    Failed,
+    // the cycle budget ran out before the ROM printed a result
+    Timeout,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SerialVerdict {
+    pub result: TestRomResult,
+    // everything the ROM sent over serial, with non printable bytes replaced
+    pub output: String,
+    pub cycles: u128,
+}
+
+impl SerialVerdict {
+    pub fn is_passed(&self) -> bool {
+        self.result == TestRomResult::Passed
+    }
+}
+
+// blargg's ROMs print the name of the test followed by "Passed", or "Failed" and the failing
+// cases. A failure anywhere in the output wins, as multi-part ROMs print a line per part.
+pub fn result_from_serial_output(output: &str) -> Option<TestRomResult> {
+    if output.contains("Failed") {
+        return Some(TestRomResult::Failed);
+    }
+    if output.contains("Passed") {
+        return Some(TestRomResult::Passed);
+    }
+    None
+}
+
+// Runs a ROM headless from the post boot state until it reports a result over serial or
+// max_cycles M-cycles have run. A panic in the emulator, such as an interrupt it does not
+// dispatch yet, is an error for that ROM instead of the end of the whole run.
+pub fn run_test_rom(rom_path: &str, max_cycles: u128) -> Result<SerialVerdict, String> {
+    let chip = DynamicMappingChip::from_rom_path(rom_path)?;
+    let mut system = System::new(Some(chip), true).with_skip_boot(GameBoyModel::DMG);
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        system.run_until_serial_verdict(max_cycles)
+    }))
+    .map_err(|payload| {
+        let message = payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        format!("{} panicked: {}", rom_path, message)
+    })
+}
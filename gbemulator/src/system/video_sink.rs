@@ -0,0 +1,235 @@
+use super::controllers::key_bindings::{HotkeyState, KeyBindings};
+use super::framebuffer::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::joypad::JoypadInput;
+use super::screenshot::save_png;
+use show_image::event::{VirtualKeyCode, WindowEvent};
+use show_image::{create_window, ImageInfo, ImageView};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+// Where the frames drawn by the LCD go. The LCD controller presents every finished frame in
+// the Grey8 format, from the thread that emulates it.
+pub trait VideoSink: Send {
+    fn present(&mut self, frame: &Framebuffer);
+
+    // the emulation is over, release the display
+    fn close(&mut self) {}
+}
+
+// drops the frames, for headless runs
+pub struct NullVideoSink;
+
+impl VideoSink for NullVideoSink {
+    fn present(&mut self, _frame: &Framebuffer) {}
+}
+
+// Writes every frame to a PNG file in a directory, named after the frame counter so that the
+// files sort in order, frame_000001.png for the first frame.
+pub struct PngSequenceSink {
+    directory: PathBuf,
+    // only the first error is reported, the following frames would fail the same way
+    failed: bool,
+}
+
+impl PngSequenceSink {
+    pub fn new(directory: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(directory)
+            .map_err(|e| format!("unable to create {}: {}", directory, e))?;
+        Ok(PngSequenceSink {
+            directory: PathBuf::from(directory),
+            failed: false,
+        })
+    }
+
+    pub fn frame_path(&self, frame: u64) -> PathBuf {
+        self.directory.join(format!("frame_{:06}.png", frame))
+    }
+}
+
+impl VideoSink for PngSequenceSink {
+    fn present(&mut self, frame: &Framebuffer) {
+        let path = self.frame_path(frame.frame);
+        if let Err(e) = save_png(path.to_str().unwrap(), &frame.data) {
+            if !self.failed {
+                println!("{}", e);
+            }
+            self.failed = true;
+        }
+    }
+}
+
+// Keeps the last frames in memory, oldest first. Clones share the frames, so a clone kept
+// aside can inspect what the system presented.
+#[derive(Clone)]
+pub struct FrameRing {
+    capacity: usize,
+    frames: Arc<Mutex<VecDeque<Framebuffer>>>,
+}
+
+impl FrameRing {
+    pub fn new(capacity: usize) -> Self {
+        FrameRing {
+            capacity,
+            frames: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    pub fn get_frames(&self) -> Vec<Framebuffer> {
+        self.frames.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn last_frame(&self) -> Option<Framebuffer> {
+        self.frames.lock().unwrap().back().cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl VideoSink for FrameRing {
+    fn present(&mut self, frame: &Framebuffer) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut frames = self.frames.lock().unwrap();
+        if frames.len() == self.capacity {
+            frames.pop_front();
+        }
+        frames.push_back(frame.clone());
+    }
+}
+
+fn apply_key_bindings(
+    key_bindings: &Mutex<KeyBindings>,
+    joypad_input: &JoypadInput,
+    hotkeys: &HotkeyState,
+    key: Option<VirtualKeyCode>,
+    pressed: bool,
+) {
+    let key = match key {
+        Some(key) => key,
+        None => return,
+    };
+    let key_bindings = key_bindings.lock().unwrap();
+    if let Some(button) = key_bindings.get_button(key) {
+        joypad_input.set_button(button, pressed);
+    }
+    if let Some(hotkey) = key_bindings.get_hotkey(key) {
+        hotkeys.apply(hotkey, pressed);
+    }
+}
+
+// Shows the frames in a show_image window, which also takes the keyboard input for the joypad
+// and the hotkeys. The window is opened on the first frame, by its own thread.
+pub struct WindowSink {
+    joypad_input: JoypadInput,
+    hotkeys: HotkeyState,
+    key_bindings: Arc<Mutex<KeyBindings>>,
+    // the last frame, until the window thread shows it
+    pending_frame: Arc<Mutex<Option<Vec<u8>>>>,
+    thread_finished: Arc<AtomicBool>,
+    thread_handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl WindowSink {
+    pub fn new(
+        joypad_input: JoypadInput,
+        hotkeys: HotkeyState,
+        key_bindings: Arc<Mutex<KeyBindings>>,
+    ) -> Self {
+        WindowSink {
+            joypad_input,
+            hotkeys,
+            key_bindings,
+            pending_frame: Arc::new(Mutex::new(None)),
+            thread_finished: Arc::new(AtomicBool::new(false)),
+            thread_handle: None,
+        }
+    }
+
+    fn spawn_window_thread(&self) -> std::thread::JoinHandle<()> {
+        let joypad_input = self.joypad_input.clone();
+        let hotkeys = self.hotkeys.clone();
+        let key_bindings = self.key_bindings.clone();
+        let pending_frame = self.pending_frame.clone();
+        let thread_finished = self.thread_finished.clone();
+        std::thread::spawn(move || {
+            let display_window = create_window("GameBoy Screen", Default::default()).unwrap();
+            let window_events = display_window.event_channel().unwrap();
+            let mut prev_cycle_time = std::time::Instant::now();
+            let mut screenshot_count = 0;
+            let mut shown_frame = vec![255u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+            loop {
+                if thread_finished.load(Ordering::Relaxed) {
+                    break;
+                }
+                while let Ok(event) = window_events.try_recv() {
+                    if let WindowEvent::KeyboardInput(event) = event {
+                        apply_key_bindings(
+                            &key_bindings,
+                            &joypad_input,
+                            &hotkeys,
+                            event.input.key_code,
+                            event.input.state.is_pressed(),
+                        );
+                    }
+                }
+                if hotkeys.take_screenshot_request() {
+                    let path = format!("screenshot_{}.png", screenshot_count);
+                    match save_png(&path, &shown_frame) {
+                        Ok(_) => println!("saved screenshot to {}", path),
+                        Err(e) => println!("{}", e),
+                    }
+                    screenshot_count += 1;
+                }
+                if let Some(frame) = pending_frame.lock().unwrap().take() {
+                    shown_frame = frame;
+                    let image = ImageView::new(
+                        ImageInfo::mono8(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32),
+                        &shown_frame,
+                    );
+                    let fps = 1e9 / prev_cycle_time.elapsed().as_nanos() as f64;
+                    display_window
+                        .set_image(format!("GameBoy Screen {:.2} fps", fps), image)
+                        .unwrap();
+                    prev_cycle_time = std::time::Instant::now();
+                }
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+            println!("outside display loop")
+        })
+    }
+}
+
+impl VideoSink for WindowSink {
+    fn present(&mut self, frame: &Framebuffer) {
+        *self.pending_frame.lock().unwrap() = Some(frame.data.clone());
+        if self.thread_handle.is_none() {
+            self.thread_handle = Some(self.spawn_window_thread());
+        }
+    }
+
+    fn close(&mut self) {
+        self.thread_finished.store(true, Ordering::Relaxed);
+        if let Some(thread_handle) = self.thread_handle.take() {
+            if thread_handle.join().is_err() {
+                println!("the window thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for WindowSink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,434 @@
+use gbemulator::system::apu::APU_CLOCK_RATE;
+use gbemulator::system::ram::RAM;
+use gbemulator::system::sm83::opcodes::{LDH_A_N, LDH_N_A, NOP};
+use gbemulator::system::sm83::registers::RegisterName;
+use gbemulator::system::sm83::SM83;
+
+const LD_A_N: u8 = 0x3E;
+
+// sound on, full volume on both outputs, every channel on both
+fn powered_ram() -> RAM {
+    let mut ram = RAM::new(None);
+    ram.set_at(0xFF26, 0x80);
+    ram.set_at(0xFF24, 0x77);
+    ram.set_at(0xFF25, 0xFF);
+    ram
+}
+
+// 50% duty, volume 15 and no envelope, 131072 / (2048 - 0x783) = 1049 Hz, triggered
+fn play_channel_2(ram: &mut RAM) {
+    ram.set_at(0xFF16, 0x80);
+    ram.set_at(0xFF17, 0xF0);
+    ram.set_at(0xFF18, 0x83);
+    ram.set_at(0xFF19, 0x87);
+}
+
+// the number of times the samples go from low to high, with some hysteresis
+fn periods(samples: &[f32], threshold: f32) -> usize {
+    let mut high = false;
+    let mut periods = 0;
+    for sample in samples {
+        if !high && *sample > threshold {
+            high = true;
+            periods += 1;
+        } else if high && *sample < -threshold {
+            high = false;
+        }
+    }
+    periods
+}
+
+fn split_stereo(samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let left = samples.iter().step_by(2).copied().collect();
+    let right = samples.iter().skip(1).step_by(2).copied().collect();
+    (left, right)
+}
+
+#[test]
+fn test_registers_read_back_with_their_unused_bits() {
+    let mut ram = powered_ram();
+    assert_eq!(ram.get_at(0xFF26), Some(0xF0));
+    ram.set_at(0xFF11, 0x80);
+    assert_eq!(ram.get_at(0xFF11), Some(0xBF));
+    // the frequency is write only
+    ram.set_at(0xFF13, 0x12);
+    assert_eq!(ram.get_at(0xFF13), Some(0xFF));
+    ram.set_at(0xFF14, 0x40);
+    assert_eq!(ram.get_at(0xFF14), Some(0xFF));
+    assert_eq!(ram.get_at(0xFF24), Some(0x77));
+    assert_eq!(ram.get_at(0xFF27), Some(0xFF));
+    ram.set_at(0xFF30, 0x1F);
+    assert_eq!(ram.get_at(0xFF30), Some(0x1F));
+}
+
+#[test]
+fn test_power_off_clears_the_registers() {
+    let mut ram = powered_ram();
+    play_channel_2(&mut ram);
+    ram.set_at(0xFF30, 0x1F);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF2));
+    ram.set_at(0xFF26, 0x00);
+    assert_eq!(ram.get_at(0xFF26), Some(0x70));
+    assert_eq!(ram.get_at(0xFF24), Some(0x00));
+    // ignored while off, but the wave RAM stays reachable
+    ram.set_at(0xFF24, 0x77);
+    assert_eq!(ram.get_at(0xFF24), Some(0x00));
+    assert_eq!(ram.get_at(0xFF30), Some(0x1F));
+}
+
+#[test]
+fn test_length_counter_stops_the_channel() {
+    let mut ram = powered_ram();
+    // length 64 - 0x3E = 2 steps of 256 Hz
+    ram.set_at(0xFF16, 0x3E);
+    ram.set_at(0xFF17, 0xF0);
+    ram.set_at(0xFF19, 0xC7);
+    assert_eq!(ram.get_at(0xFF26).map(|nr52| nr52 & 0x02), Some(0x02));
+    ram.tick(2048 * 2);
+    assert_eq!(ram.get_at(0xFF26).map(|nr52| nr52 & 0x02), Some(0x02));
+    ram.tick(2048 * 2);
+    assert_eq!(ram.get_at(0xFF26).map(|nr52| nr52 & 0x02), Some(0x00));
+}
+
+#[test]
+fn test_channels_need_their_dac() {
+    let mut ram = powered_ram();
+    // volume 0 and decreasing turns the DAC off, the trigger does not start the channel
+    ram.set_at(0xFF12, 0x00);
+    ram.set_at(0xFF14, 0x80);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF0));
+    ram.set_at(0xFF12, 0xF0);
+    ram.set_at(0xFF14, 0x80);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF1));
+    ram.set_at(0xFF12, 0x00);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF0));
+}
+
+#[test]
+fn test_sweep_overflow_stops_channel_1() {
+    let mut ram = powered_ram();
+    ram.set_at(0xFF12, 0xF0);
+    // increasing by a half, 0x700 + 0x380 overflows as soon as it is triggered
+    ram.set_at(0xFF10, 0x11);
+    ram.set_at(0xFF13, 0x00);
+    ram.set_at(0xFF14, 0x87);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF0));
+    // 0x100 goes up by 0x80 every 128 Hz step until it passes 2047
+    ram.set_at(0xFF14, 0x81);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF1));
+    ram.tick(APU_CLOCK_RATE as u64 / 4);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF0));
+}
+
+#[test]
+fn test_square_wave_is_rendered_at_the_sample_rate() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    play_channel_2(&mut ram);
+    ram.tick(APU_CLOCK_RATE as u64);
+    let samples = ram.get_apu_mut().take_samples();
+    // one second, less the delay of the resampler
+    assert!((2 * 47990..=2 * 48000).contains(&samples.len()));
+    assert!(ram.get_apu_mut().take_samples().is_empty());
+    assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+    let (left, right) = split_stereo(&samples);
+    assert!(left == right);
+    assert!((1047..=1050).contains(&periods(&left, 0.1)));
+}
+
+#[test]
+fn test_nr51_and_nr50_route_the_channels() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    // channel 2 on the left only, right volume at its lowest
+    ram.set_at(0xFF25, 0x20);
+    ram.set_at(0xFF24, 0x70);
+    play_channel_2(&mut ram);
+    ram.tick(APU_CLOCK_RATE as u64 / 10);
+    let (left, right) = split_stereo(&ram.get_apu_mut().take_samples());
+    assert!(right.iter().all(|sample| *sample == 0.0));
+    let loudest = left.iter().copied().fold(0.0f32, f32::max);
+    // the square goes from -0.25 to 0.25, with some ringing on the edges
+    assert!((0.25..=0.35).contains(&loudest));
+}
+
+#[test]
+fn test_noise_channel() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    for short_mode in [0x00, 0x08] {
+        ram.set_at(0xFF21, 0xF0);
+        ram.set_at(0xFF22, 0x52 | short_mode);
+        ram.set_at(0xFF23, 0x80);
+        assert_eq!(ram.get_at(0xFF26), Some(0xF8));
+        ram.tick(APU_CLOCK_RATE as u64 / 10);
+        let (left, _) = split_stereo(&ram.get_apu_mut().take_samples());
+        assert!(left.iter().any(|sample| *sample > 0.1));
+        assert!(left.iter().any(|sample| *sample < -0.1));
+    }
+}
+
+#[test]
+fn test_nothing_is_rendered_without_a_sample_rate() {
+    let mut ram = powered_ram();
+    play_channel_2(&mut ram);
+    ram.tick(APU_CLOCK_RATE as u64 / 10);
+    assert_eq!(ram.get_apu().get_sample_rate(), None);
+    assert!(ram.get_apu_mut().take_samples().is_empty());
+}
+
+fn channel_2_playing(ram: &RAM) -> bool {
+    ram.get_at(0xFF26).unwrap() & 0x02 != 0
+}
+
+// volume 15, length 1, length enabled and triggered
+fn play_channel_2_for_one_length_clock(ram: &mut RAM) {
+    ram.set_at(0xFF16, 0x3F);
+    ram.set_at(0xFF17, 0xF0);
+    ram.set_at(0xFF19, 0xC7);
+}
+
+#[test]
+fn test_frame_sequencer_is_clocked_from_div() {
+    let mut ram = powered_ram();
+    play_channel_2_for_one_length_clock(&mut ram);
+    // DIV keeps being cleared before bit 4 goes up, the length is never clocked
+    for _ in 0..100 {
+        ram.tick(1000);
+        ram.set_at(0xFF04, 0x00);
+    }
+    assert!(channel_2_playing(&ram));
+    ram.tick(2047);
+    assert!(channel_2_playing(&ram));
+    ram.tick(1);
+    assert!(!channel_2_playing(&ram));
+}
+
+#[test]
+fn test_clearing_div_with_bit_4_set_steps_the_frame_sequencer() {
+    let mut ram = powered_ram();
+    play_channel_2_for_one_length_clock(&mut ram);
+    ram.tick(1024);
+    assert_eq!(ram.get_at(0xFF04), Some(0x10));
+    assert!(channel_2_playing(&ram));
+    ram.set_at(0xFF04, 0x00);
+    assert_eq!(ram.get_at(0xFF04), Some(0x00));
+    assert!(!channel_2_playing(&ram));
+}
+
+#[test]
+fn test_enabling_the_length_between_length_clocks_clocks_it() {
+    for (cycles, playing) in [(0, true), (2048, false)] {
+        let mut ram = powered_ram();
+        // triggered without the length, which is 1
+        ram.set_at(0xFF16, 0x3F);
+        ram.set_at(0xFF17, 0xF0);
+        ram.set_at(0xFF19, 0x87);
+        // after the first step the next one does not clock the lengths
+        ram.tick(cycles);
+        ram.set_at(0xFF19, 0x47);
+        assert_eq!(channel_2_playing(&ram), playing, "{}", cycles);
+    }
+}
+
+#[test]
+fn test_envelope_writes_while_playing_change_the_volume() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    // volume 0, increasing with a period of 0, so the envelope does not move by itself
+    ram.set_at(0xFF16, 0x80);
+    ram.set_at(0xFF17, 0x08);
+    ram.set_at(0xFF18, 0x83);
+    ram.set_at(0xFF19, 0x87);
+    ram.tick(APU_CLOCK_RATE as u64 / 10);
+    let (left, _) = split_stereo(&ram.get_apu_mut().take_samples());
+    assert_eq!(periods(&left, 0.05), 0);
+    let mut swings = Vec::new();
+    for writes in [8, 7] {
+        // each write in zombie mode adds 1 to the volume
+        for _ in 0..writes {
+            ram.set_at(0xFF17, 0x08);
+        }
+        ram.tick(APU_CLOCK_RATE as u64 / 10);
+        let (left, _) = split_stereo(&ram.get_apu_mut().take_samples());
+        assert!(periods(&left, 0.05) > 100);
+        // once the high-pass filter settled
+        let settled = &left[left.len() / 2..];
+        let highest = settled.iter().copied().fold(f32::MIN, f32::max);
+        let lowest = settled.iter().copied().fold(f32::MAX, f32::min);
+        swings.push(highest - lowest);
+    }
+    // volume 8 then 15, a square of volume / 30 with some ringing on the edges
+    assert!((0.28..=0.36).contains(&swings[0]));
+    assert!((0.55..=0.68).contains(&swings[1]));
+}
+
+// a square in the wave RAM, 16 samples of 15 then 16 of 0
+fn load_square_wave(ram: &mut RAM) {
+    for offset in 0..16 {
+        ram.set_at(0xFF30 + offset, if offset < 8 { 0xFF } else { 0x00 });
+    }
+}
+
+fn channel_3_playing(ram: &RAM) -> bool {
+    ram.get_at(0xFF26).unwrap() & 0x04 != 0
+}
+
+#[test]
+fn test_wave_channel_plays_the_wave_ram() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    load_square_wave(&mut ram);
+    ram.set_at(0xFF1A, 0x80);
+    ram.set_at(0xFF1C, 0x20);
+    // 65536 / (2048 - 0x700) = 256 Hz
+    ram.set_at(0xFF1D, 0x00);
+    ram.set_at(0xFF1E, 0x87);
+    assert_eq!(ram.get_at(0xFF26), Some(0xF4));
+    ram.tick(APU_CLOCK_RATE as u64);
+    let (left, right) = split_stereo(&ram.get_apu_mut().take_samples());
+    assert!(left == right);
+    assert!((255..=257).contains(&periods(&left, 0.1)));
+}
+
+#[test]
+fn test_wave_output_level_shifts_the_samples() {
+    let mut ram = powered_ram();
+    ram.get_apu_mut().set_sample_rate(Some(48000));
+    load_square_wave(&mut ram);
+    ram.set_at(0xFF1A, 0x80);
+    ram.set_at(0xFF1D, 0x00);
+    ram.set_at(0xFF1E, 0x87);
+    let mut swings = Vec::new();
+    // 100%, 50%, 25% and muted
+    for output_level in [0x20, 0x40, 0x60, 0x00] {
+        ram.set_at(0xFF1C, output_level);
+        ram.tick(APU_CLOCK_RATE as u64 / 10);
+        let (left, _) = split_stereo(&ram.get_apu_mut().take_samples());
+        let settled = &left[left.len() / 2..];
+        let highest = settled.iter().copied().fold(f32::MIN, f32::max);
+        let lowest = settled.iter().copied().fold(f32::MAX, f32::min);
+        swings.push(highest - lowest);
+    }
+    // samples of 15, 7 and 3, the high-pass filter makes the swings larger at 256 Hz but keeps
+    // their ratios
+    assert!((0.44..=0.49).contains(&(swings[1] / swings[0])));
+    assert!((0.18..=0.22).contains(&(swings[2] / swings[0])));
+    assert!(swings[3] < 0.01);
+}
+
+#[test]
+fn test_wave_length_counts_256_steps() {
+    let mut ram = powered_ram();
+    ram.set_at(0xFF1A, 0x80);
+    ram.set_at(0xFF1B, 0x00);
+    ram.set_at(0xFF1E, 0xC7);
+    // the first length clock comes after 2048 M-cycles, then one every 4096
+    ram.tick(2048 + 255 * 4096 - 1);
+    assert!(channel_3_playing(&ram));
+    ram.tick(1);
+    assert!(!channel_3_playing(&ram));
+}
+
+#[test]
+fn test_wave_channel_needs_its_dac() {
+    let mut ram = powered_ram();
+    ram.set_at(0xFF1E, 0x87);
+    assert!(!channel_3_playing(&ram));
+    ram.set_at(0xFF1A, 0x80);
+    ram.set_at(0xFF1E, 0x87);
+    assert!(channel_3_playing(&ram));
+    ram.set_at(0xFF1A, 0x00);
+    assert!(!channel_3_playing(&ram));
+}
+
+#[test]
+fn test_wave_ram_is_only_reachable_when_the_channel_reads_it() {
+    let mut ram = powered_ram();
+    for offset in 0..16 {
+        ram.set_at(0xFF30 + offset, 0x10 + offset as u8);
+    }
+    ram.set_at(0xFF1A, 0x80);
+    // a sample every 4096 T-cycles, the channel is not reading
+    ram.set_at(0xFF1D, 0x00);
+    ram.set_at(0xFF1E, 0x80);
+    ram.tick(1);
+    assert_eq!(ram.get_at(0xFF35), Some(0xFF));
+    ram.set_at(0xFF35, 0x00);
+    // a sample every M-cycle, any address reaches the byte being read on the next cycle, the
+    // second one here
+    ram.set_at(0xFF1D, 0xFE);
+    ram.set_at(0xFF1E, 0x87);
+    ram.tick(3);
+    assert_eq!(ram.get_at(0xFF35), Some(0x11));
+    ram.set_at(0xFF3A, 0xAB);
+    ram.set_at(0xFF1A, 0x00);
+    assert_eq!(ram.get_at(0xFF30), Some(0x10));
+    assert_eq!(ram.get_at(0xFF31), Some(0xAB));
+    assert_eq!(ram.get_at(0xFF35), Some(0x15));
+    assert_eq!(ram.get_at(0xFF3A), Some(0x1A));
+}
+
+// the wave channel playing the wave RAM with a sample every 8 T-cycles, read on M-cycles 4, 6,
+// 8... after the trigger, and a CPU that runs the program after it
+fn wave_ram_program(program: &[u8]) -> (SM83, RAM) {
+    let mut ram = powered_ram();
+    for (address, byte) in program.iter().enumerate() {
+        ram.set_at(address as u16, *byte);
+    }
+    for offset in 0..16 {
+        ram.set_at(0xFF30 + offset, 0x10 + offset as u8);
+    }
+    ram.set_at(0xFF1A, 0x80);
+    ram.set_at(0xFF1D, 0xFC);
+    ram.set_at(0xFF1E, 0x87);
+    let mut cpu = SM83::new();
+    cpu.fetch_cycle(&ram);
+    (cpu, ram)
+}
+
+// as System runs the CPU, RAM is ticked once each instruction has run
+fn run_instructions(cpu: &mut SM83, ram: &mut RAM, count: usize) {
+    for _ in 0..count {
+        let start_cycle = cpu.cycle_count;
+        cpu.next(ram);
+        ram.tick((cpu.cycle_count - start_cycle) as u64);
+    }
+}
+
+// LDH A,(0x3F) after the given number of NOPs
+fn read_wave_ram_after_nops(nops: usize) -> u8 {
+    let mut program = vec![NOP; nops];
+    program.extend_from_slice(&[LDH_A_N, 0x3F]);
+    let (mut cpu, mut ram) = wave_ram_program(&program);
+    run_instructions(&mut cpu, &mut ram, nops + 1);
+    cpu.get_register(RegisterName::A) as u8
+}
+
+#[test]
+fn test_wave_ram_is_read_on_the_cycle_of_the_access() {
+    // LDH A,(n) reads on the second M-cycle of the instruction, the first one being its operand
+    assert_eq!(read_wave_ram_after_nops(0), 0xFF);
+    assert_eq!(read_wave_ram_after_nops(1), 0xFF);
+    assert_eq!(read_wave_ram_after_nops(2), 0x10);
+    assert_eq!(read_wave_ram_after_nops(3), 0xFF);
+    assert_eq!(read_wave_ram_after_nops(4), 0x11);
+}
+
+#[test]
+fn test_wave_ram_is_written_on_the_cycle_of_the_access() {
+    // LD A,n takes 2 M-cycles, so LDH (n),A writes on the 4th, as the channel reads the first byte
+    let (mut cpu, mut ram) = wave_ram_program(&[LD_A_N, 0xAB, LDH_N_A, 0x3F]);
+    run_instructions(&mut cpu, &mut ram, 2);
+    ram.set_at(0xFF1A, 0x00);
+    assert_eq!(ram.get_at(0xFF30), Some(0xAB));
+    assert_eq!(ram.get_at(0xFF3F), Some(0x1F));
+
+    // a cycle later the channel is not reading and the write is lost
+    let (mut cpu, mut ram) = wave_ram_program(&[NOP, LD_A_N, 0xAB, LDH_N_A, 0x3F]);
+    run_instructions(&mut cpu, &mut ram, 3);
+    ram.set_at(0xFF1A, 0x00);
+    for offset in 0..16 {
+        assert_eq!(ram.get_at(0xFF30 + offset), Some(0x10 + offset as u8));
+    }
+}
@@ -0,0 +1,52 @@
+use gbemulator::system::audio_sink::{AudioSink, NullAudioSink, SampleRecorder, WavAudioSink};
+
+#[test]
+fn test_sample_recorder() {
+    let recorder = SampleRecorder::new(44100);
+    let mut sink = recorder.clone();
+    assert_eq!(sink.sample_rate(), 44100);
+    sink.queue(&[0.5, -0.5]);
+    sink.queue(&[1.0, 0.0]);
+    assert_eq!(recorder.get_samples(), vec![0.5, -0.5, 1.0, 0.0]);
+    recorder.clear();
+    assert!(recorder.get_samples().is_empty());
+
+    let mut null_sink = NullAudioSink::new(22050);
+    null_sink.queue(&[0.5, 0.5]);
+    assert_eq!(null_sink.sample_rate(), 22050);
+}
+
+#[test]
+fn test_wav_audio_sink() {
+    let path = std::env::temp_dir().join("gbemulator_audio_sink.wav");
+    let path = path.to_str().unwrap();
+    let mut sink = WavAudioSink::new(path, 32000).unwrap();
+    sink.queue(&[0.0, 1.0]);
+    sink.queue(&[-1.0, 2.0]);
+    sink.close();
+
+    let contents = std::fs::read(path).unwrap();
+    let u32_at = |i: usize| u32::from_le_bytes(contents[i..i + 4].try_into().unwrap());
+    let u16_at = |i: usize| u16::from_le_bytes(contents[i..i + 2].try_into().unwrap());
+    assert_eq!(&contents[0..4], b"RIFF");
+    assert_eq!(u32_at(4) as usize, contents.len() - 8);
+    assert_eq!(&contents[8..16], b"WAVEfmt ");
+    // stereo at 32 kHz, 16 bits
+    assert_eq!(u16_at(22), 2);
+    assert_eq!(u32_at(24), 32000);
+    assert_eq!(u16_at(34), 16);
+    assert_eq!(&contents[36..40], b"data");
+    assert_eq!(u32_at(40), 8);
+    let samples: Vec<i16> = contents[44..]
+        .chunks(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    // out of range samples are clipped
+    assert_eq!(samples, vec![0, i16::MAX, -i16::MAX, i16::MAX]);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(WavAudioSink::new("/nonexistent/sound.wav", 32000)
+        .err()
+        .unwrap()
+        .contains("unable to create"));
+}
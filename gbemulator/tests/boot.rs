@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use gbemulator::system::boot::{post_boot_io_registers, skip_boot, GameBoyModel};
+use gbemulator::system::ram::mapping_chip::{DynamicMappingChip, MBC1};
+use gbemulator::system::ram::{BootRom, MemoryRegister, RAM};
+use gbemulator::system::sm83::registers::RegisterName;
+use gbemulator::system::sm83::SM83;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+
+fn make_mbc1_ram(header_checksum: u8) -> RAM {
+    let mut rom = vec![0u8; 4 * ROM_BANK_SIZE];
+    for bank in 0..4 {
+        rom[bank * ROM_BANK_SIZE] = bank as u8;
+    }
+    // NOP at the entry point
+    rom[0x0100] = 0x00;
+    rom[0x0147] = 0x01;
+    rom[0x014D] = header_checksum;
+    RAM::new(Some(DynamicMappingChip::MBC1(MBC1::from_rom_contents(rom))))
+}
+
+#[test]
+fn test_skip_boot_dmg_state() {
+    let mut ram = make_mbc1_ram(0x42);
+    let mut cpu = SM83::new();
+    skip_boot(GameBoyModel::DMG, &mut cpu, &mut ram);
+    assert_eq!(cpu.get_register(RegisterName::AF), 0x01B0);
+    assert_eq!(cpu.get_register(RegisterName::BC), 0x0013);
+    assert_eq!(cpu.get_register(RegisterName::DE), 0x00D8);
+    assert_eq!(cpu.get_register(RegisterName::HL), 0x014D);
+    assert_eq!(cpu.get_register(RegisterName::SP), 0xFFFE);
+    // the opcode at 0x0100 has already been fetched
+    assert_eq!(cpu.get_register(RegisterName::PC), 0x0101);
+    assert_eq!(ram.get_at(0xFF50), Some(0x01));
+    assert_eq!(ram.get_at(0xFF40), Some(0x91));
+    assert_eq!(ram.get_at(0xFF47), Some(0xFC));
+    assert_eq!(ram.get_at(0xFF26), Some(0xF1));
+    // the cartridge is mapped and its registers respond straight away
+    assert_eq!(ram.get_at(0x4000), Some(1));
+    ram.set_at(0x2000, 0x03).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(3));
+}
+
+#[test]
+fn test_skip_boot_flags_depend_on_header_checksum() {
+    let mut ram = make_mbc1_ram(0x00);
+    let mut cpu = SM83::new();
+    skip_boot(GameBoyModel::MGB, &mut cpu, &mut ram);
+    assert_eq!(cpu.get_register(RegisterName::AF), 0xFF80);
+}
+
+#[test]
+fn test_skip_boot_per_model_registers() {
+    let expected = [
+        (GameBoyModel::DMG0, 0x0100, 0xFF13, 0x00C1, 0x8403),
+        (GameBoyModel::SGB, 0x0100, 0x0014, 0x0000, 0xC060),
+        (GameBoyModel::SGB2, 0xFF00, 0x0014, 0x0000, 0xC060),
+        (GameBoyModel::CGB, 0x1180, 0x0000, 0xFF56, 0x000D),
+    ];
+    for (model, af, bc, de, hl) in expected {
+        let mut ram = make_mbc1_ram(0x42);
+        let mut cpu = SM83::new();
+        skip_boot(model, &mut cpu, &mut ram);
+        assert_eq!(cpu.get_register(RegisterName::AF), af, "{:?}", model);
+        assert_eq!(cpu.get_register(RegisterName::BC), bc, "{:?}", model);
+        assert_eq!(cpu.get_register(RegisterName::DE), de, "{:?}", model);
+        assert_eq!(cpu.get_register(RegisterName::HL), hl, "{:?}", model);
+        for (address, value) in post_boot_io_registers(model) {
+            assert_eq!(
+                ram.get_at(address),
+                Some(value),
+                "{:?} {:04X}",
+                model,
+                address
+            );
+        }
+    }
+}
+
+#[test]
+fn test_boot_rom_size_is_checked() {
+    assert!(BootRom::from_bytes(vec![0; 0x100], GameBoyModel::MGB).is_ok());
+    assert!(BootRom::from_bytes(vec![0; 0x900], GameBoyModel::CGB).is_ok());
+    assert!(BootRom::from_bytes(vec![0; 0x100], GameBoyModel::CGB).is_err());
+    assert!(BootRom::from_bytes(vec![0; 0x200], GameBoyModel::DMG0).is_err());
+    assert!(BootRom::from_file("./does_not_exist.bin", GameBoyModel::DMG).is_err());
+    assert_eq!(BootRom::new().get_model(), GameBoyModel::DMG);
+}
+
+#[test]
+fn test_boot_rom_overlays_cartridge_until_unlocked() {
+    let mut ram = make_mbc1_ram(0x42);
+    ram.load_base_rom_bank();
+    BootRom::new().load_in_ram(&mut ram);
+    assert!(ram.is_boot_rom_mapped());
+    assert_eq!(ram.get_at(0xFF50), Some(0x00));
+    // LD SP,0xFFFE from the boot rom, the header and the rest of bank 0 from the cartridge
+    assert_eq!(ram.get_at(0x0000), Some(0x31));
+    assert_eq!(ram.get_at(0x00FF), Some(0x50));
+    assert_eq!(ram.get_at(0x014D), Some(0x42));
+    assert_eq!(ram.get_at(0x4000), Some(1));
+
+    ram.set_at(0xFF50, 0x00).unwrap();
+    assert!(ram.is_boot_rom_mapped());
+    ram.set_at(0xFF50, 0x01).unwrap();
+    assert!(!ram.is_boot_rom_mapped());
+    assert_eq!(ram.get_at(0x0000), Some(0x00));
+    // once gone it cannot be mapped back from the bus
+    ram.set_at(0xFF50, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(0x00));
+    assert_eq!(ram.get_at(0xFF50), Some(0x01));
+}
+
+#[test]
+fn test_cgb_boot_rom_keeps_cartridge_header() {
+    let mut ram = make_mbc1_ram(0x42);
+    ram.load_base_rom_bank();
+    let boot_rom = BootRom::from_bytes(vec![0xAA; 0x900], GameBoyModel::CGB).unwrap();
+    ram.map_boot_rom(boot_rom);
+    assert_eq!(ram.get_at(0x00FF), Some(0xAA));
+    assert_eq!(ram.get_at(0x014D), Some(0x42));
+    assert_eq!(ram.get_at(0x0200), Some(0xAA));
+    assert_eq!(ram.get_at(0x08FF), Some(0xAA));
+    assert_eq!(ram.get_at(0x0900), Some(0x00));
+    ram.set_at(0xFF50, 0x11).unwrap();
+    assert_eq!(ram.get_at(0x0200), Some(0x00));
+}
+
+#[test]
+fn test_model_from_str() {
+    assert_eq!(GameBoyModel::from_str("dmg0"), Ok(GameBoyModel::DMG0));
+    assert_eq!(GameBoyModel::from_str("CGB"), Ok(GameBoyModel::CGB));
+    assert!(GameBoyModel::from_str("gba").is_err());
+}
@@ -0,0 +1,86 @@
+use gbemulator::system::ram::RAM;
+use gbemulator::system::sm83::bus_trace::{compare_bus_traces, BusActivity, BusCycle};
+use gbemulator::system::sm83::snapshot::SM83Snapshot;
+use gbemulator::system::sm83::SM83;
+
+fn traced_cpu(snapshot: SM83Snapshot) -> SM83 {
+    let mut cpu = SM83::new();
+    cpu.load_snapshot(snapshot);
+    cpu.set_bus_tracing(true);
+    cpu
+}
+
+#[test]
+fn test_bus_trace_ld_hl_n() {
+    let mut ram = RAM::new(None);
+    ram.set_at(0xC001, 0x42).unwrap();
+    ram.set_at(0xC002, 0x00).unwrap();
+    // LD (HL),n was fetched from 0xC000, the fetch left the incremented PC on the bus
+    let mut cpu = traced_cpu(
+        SM83Snapshot::new()
+            .with_ir(0x36)
+            .with_pc(0xC001)
+            .with_address_bus(0xC001)
+            .with_hl(0xC100),
+    );
+    cpu.next(&mut ram);
+    assert_eq!(
+        cpu.get_bus_trace(),
+        &[
+            BusCycle::read(0xC001, 0x42),
+            BusCycle::write(0xC100, 0x42),
+            BusCycle::read(0xC002, 0x00),
+        ]
+    );
+
+    // the trace only covers the last instruction
+    cpu.next(&mut ram);
+    assert_eq!(cpu.get_bus_trace(), &[BusCycle::read(0xC003, 0x00)]);
+}
+
+#[test]
+fn test_bus_trace_internal_cycle() {
+    let mut ram = RAM::new(None);
+    ram.set_at(0xC001, 0x00).unwrap();
+    // INC BC
+    let mut cpu = traced_cpu(SM83Snapshot::new().with_ir(0x03).with_pc(0xC001));
+    cpu.next(&mut ram);
+    let trace = cpu.get_bus_trace();
+    assert_eq!(trace.len(), 2);
+    assert_eq!(trace[0].activity, BusActivity::Internal);
+    assert_eq!(trace[0].data, None);
+    assert_eq!(trace[1], BusCycle::read(0xC001, 0x00));
+
+    let mut cpu = SM83::new();
+    cpu.load_snapshot(SM83Snapshot::new().with_pc(0xC001));
+    cpu.next(&mut ram);
+    assert!(cpu.get_bus_trace().is_empty());
+}
+
+#[test]
+fn test_bus_trace_comparison() {
+    let expected = [
+        BusCycle::internal(0x0000),
+        BusCycle::write(0xC100, 0x42),
+        BusCycle::read(0xC002, 0x00),
+    ];
+    // the address of internal cycles is not compared
+    let actual = [
+        BusCycle::internal(0x1234),
+        BusCycle::write(0xC100, 0x42),
+        BusCycle::read(0xC002, 0x00),
+    ];
+    assert_eq!(compare_bus_traces(&expected, &actual), Ok(()));
+    assert_eq!(
+        compare_bus_traces(&expected, &[expected[0], BusCycle::write(0xC100, 0x24)]),
+        Err("M-cycle 2: expected write 42 to c100, got write 24 to c100".to_string())
+    );
+    assert_eq!(
+        compare_bus_traces(&expected[..2], &expected),
+        Err("expected 2 M-cycles, got 3, M-cycle 3 did read 00 from c002".to_string())
+    );
+    assert_eq!(
+        compare_bus_traces(&expected, &expected[..1]),
+        Err("expected 3 M-cycles, got 1".to_string())
+    );
+}
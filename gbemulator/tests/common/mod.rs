@@ -0,0 +1,29 @@
+// Helpers shared by the integration tests, every test file uses its own subset of them.
+#![allow(dead_code)]
+
+use gbemulator::system::boot::GameBoyModel;
+use gbemulator::system::ram::mapping_chip::DynamicMappingChip;
+use gbemulator::system::System;
+
+// 32KB without a mapping chip, the program starts at the 0x0100 entry point and NOPs fill the
+// rest
+pub fn make_rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+    rom[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    rom
+}
+
+// writes the ROM to the temp directory and returns its path, the test removes it when done
+pub fn write_rom(name: &str, rom: &[u8]) -> String {
+    let rom_path = std::env::temp_dir().join(name);
+    std::fs::write(&rom_path, rom).unwrap();
+    rom_path.to_str().unwrap().to_string()
+}
+
+// a headless system running the ROM file from the state the DMG boot ROM leaves behind
+pub fn boot_headless(rom_path: &str) -> System {
+    let chip = DynamicMappingChip::from_rom_path(rom_path).unwrap();
+    let mut system = System::new(Some(chip), true).with_skip_boot(GameBoyModel::DMG);
+    system.boot();
+    system
+}
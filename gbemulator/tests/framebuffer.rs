@@ -0,0 +1,49 @@
+mod common;
+
+use gbemulator::system::framebuffer::{
+    shade_of_grey, Framebuffer, FramebufferFormat, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+#[test]
+fn test_framebuffer_formats() {
+    let grey = [255, 192, 64, 0, 170];
+    let shades = Framebuffer::from_grey(&grey, FramebufferFormat::ShadeIndex, 3);
+    assert_eq!(shades.data, vec![0, 1, 2, 3, 1]);
+    assert_eq!(shades.frame, 3);
+    assert_eq!(shade_of_grey(85), 2);
+
+    let grey8 = Framebuffer::from_grey(&grey, FramebufferFormat::Grey8, 3);
+    assert_eq!(grey8.data, grey.to_vec());
+
+    let rgba = Framebuffer::from_grey(&grey, FramebufferFormat::Rgba8888, 3);
+    assert_eq!(rgba.data.len(), grey.len() * 4);
+    assert_eq!(rgba.pixel(1, 0), &[192, 192, 192, 255]);
+}
+
+#[test]
+fn test_framebuffer_of_system() {
+    // a ROM of NOPs, the LCD keeps drawing the blank screen left by the boot
+    let rom_path = common::write_rom("gbemulator_framebuffer_nop.gb", &common::make_rom(&[]));
+    let mut system = common::boot_headless(&rom_path);
+    assert_eq!(system.frame_count(), 0);
+    for _ in 0..3 {
+        system.step_frame();
+    }
+    let frame_count = system.frame_count();
+    assert!(frame_count > 0);
+
+    let framebuffer = system.framebuffer(FramebufferFormat::Rgba8888);
+    assert_eq!(framebuffer.frame, frame_count);
+    assert_eq!(framebuffer.width(), SCREEN_WIDTH);
+    assert_eq!(framebuffer.height(), SCREEN_HEIGHT);
+    assert_eq!(framebuffer.data.len(), SCREEN_WIDTH * SCREEN_HEIGHT * 4);
+    let grey = system.framebuffer(FramebufferFormat::Grey8);
+    assert_eq!(grey.data, system.get_image_data());
+    assert_eq!(framebuffer.pixel(10, 20)[0], grey.pixel(10, 20)[0]);
+
+    // the LCD timings are not yet exact, a frame boundary can fall just before VBLANK
+    system.step_frame();
+    system.step_frame();
+    assert!(system.frame_count() > frame_count);
+    std::fs::remove_file(&rom_path).unwrap();
+}
@@ -0,0 +1,100 @@
+use gbemulator::system::joypad::{Button, JOYPAD_INTERRUPT};
+use gbemulator::system::ram::RAM;
+use gbemulator::system::sm83::registers::RegisterName;
+use gbemulator::system::sm83::snapshot::SM83Snapshot;
+use gbemulator::system::sm83::SM83;
+
+const P1: u16 = 0xFF00;
+const IF: u16 = 0xFF0F;
+const IE: u16 = 0xFFFF;
+const SELECT_DIRECTIONS: u8 = 0x20;
+const SELECT_ACTIONS: u8 = 0x10;
+const SELECT_NONE: u8 = 0x30;
+
+#[test]
+fn test_joypad_selection_matrix() {
+    let mut ram = RAM::new(None);
+    ram.set_button(Button::Right, true);
+    ram.set_button(Button::Start, true);
+    ram.set_button(Button::B, true);
+
+    ram.set_at(P1, SELECT_DIRECTIONS).unwrap();
+    assert_eq!(ram.get_at(P1), Some(0xEE));
+    ram.set_at(P1, SELECT_ACTIONS).unwrap();
+    assert_eq!(ram.get_at(P1), Some(0xD5));
+    ram.set_at(P1, SELECT_NONE).unwrap();
+    assert_eq!(ram.get_at(P1), Some(0xFF));
+    // with both groups selected the lines are shared
+    ram.set_at(P1, 0x00).unwrap();
+    assert_eq!(ram.get_at(P1), Some(0xC4));
+
+    ram.set_button(Button::Right, false);
+    assert_eq!(ram.get_at(P1), Some(0xC5));
+    // the low nibble is read only
+    ram.set_at(P1, SELECT_DIRECTIONS | 0x0F).unwrap();
+    assert_eq!(ram.get_at(P1), Some(0xEF));
+}
+
+#[test]
+fn test_joypad_interrupt_on_falling_edge() {
+    let mut ram = RAM::new(None);
+    ram.set_at(P1, SELECT_DIRECTIONS).unwrap();
+    ram.set_at(IF, 0x00).unwrap();
+
+    // buttons of the group that is not selected do not touch the lines
+    ram.set_button(Button::A, true);
+    assert_eq!(ram.get_at(IF), Some(0x00));
+
+    ram.set_button(Button::Down, true);
+    assert_eq!(ram.get_at(IF), Some(JOYPAD_INTERRUPT));
+
+    // releasing is a rising edge
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_button(Button::Down, false);
+    assert_eq!(ram.get_at(IF), Some(0x00));
+
+    // selecting a group with a button held also pulls a line low
+    ram.set_at(P1, SELECT_ACTIONS).unwrap();
+    assert_eq!(ram.get_at(IF), Some(JOYPAD_INTERRUPT));
+}
+
+#[test]
+fn test_joypad_shared_input_is_seen_on_tick() {
+    let mut ram = RAM::new(None);
+    ram.set_at(P1, SELECT_ACTIONS).unwrap();
+    ram.set_at(IF, 0x00).unwrap();
+    let input = ram.get_joypad_input();
+    input.set_button(Button::Start, true);
+    assert!(input.is_pressed(Button::Start));
+    assert_eq!(ram.get_at(P1), Some(0xDF));
+    ram.tick(1);
+    assert_eq!(ram.get_at(P1), Some(0xD7));
+    assert_eq!(ram.get_at(IF), Some(JOYPAD_INTERRUPT));
+}
+
+#[test]
+fn test_joypad_interrupt_dispatch() {
+    let mut ram = RAM::new(None);
+    ram.set_at(0x1000, 0x00).unwrap(); // NOP
+    ram.set_at(0x0060, 0x3C).unwrap(); // INC A
+    ram.set_at(P1, SELECT_DIRECTIONS).unwrap();
+    ram.set_at(IE, JOYPAD_INTERRUPT).unwrap();
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_button(Button::Left, true);
+
+    let snapshot = SM83Snapshot::new()
+        .with_pc(0x1000)
+        .with_sp(0xD000)
+        .with_ime(true);
+    let mut cpu = SM83::new();
+    cpu.load_snapshot(snapshot);
+    cpu.next(&mut ram);
+
+    assert_eq!(cpu.get_register(RegisterName::PC), 0x0061);
+    assert_eq!(cpu.get_register(RegisterName::IR), 0x3C);
+    assert_eq!(cpu.get_register(RegisterName::SP), 0xCFFE);
+    // the opcode fetched at 0x1000 runs once the handler returns
+    assert_eq!(ram.get_at(0xCFFF), Some(0x10));
+    assert_eq!(ram.get_at(0xCFFE), Some(0x00));
+    assert_eq!(ram.get_at(IF), Some(0x00));
+}
@@ -0,0 +1,103 @@
+use gbemulator::system::controllers::key_bindings::{
+    parse_host_key, Hotkey, HotkeyState, KeyBindings,
+};
+use gbemulator::system::joypad::Button;
+use show_image::event::VirtualKeyCode;
+
+#[test]
+fn test_default_key_bindings() {
+    let bindings = KeyBindings::new();
+    assert_eq!(bindings.get_button(VirtualKeyCode::Up), Some(Button::Up));
+    assert_eq!(bindings.get_button(VirtualKeyCode::Z), Some(Button::A));
+    assert_eq!(
+        bindings.get_button(VirtualKeyCode::Return),
+        Some(Button::Start)
+    );
+    assert_eq!(bindings.get_hotkey(VirtualKeyCode::P), Some(Hotkey::Pause));
+    assert_eq!(
+        bindings.get_hotkey(VirtualKeyCode::F8),
+        Some(Hotkey::LoadState)
+    );
+    assert_eq!(bindings.get_button(VirtualKeyCode::W), None);
+}
+
+#[test]
+fn test_key_bindings_from_json() {
+    let bindings = KeyBindings::from_json(
+        r#"{
+            "buttons": { "up": ["W", "up"], "left": "a", "down": "S", "right": "D",
+                         "a": "K", "b": "J", "select": "RShift", "start": "Return" },
+            "hotkeys": { "fast_forward": "Space" }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(bindings.get_button(VirtualKeyCode::W), Some(Button::Up));
+    assert_eq!(bindings.get_button(VirtualKeyCode::Up), Some(Button::Up));
+    assert_eq!(bindings.get_button(VirtualKeyCode::A), Some(Button::Left));
+    // the buttons section replaces the default ones
+    assert_eq!(bindings.get_button(VirtualKeyCode::Z), None);
+    assert_eq!(
+        bindings.get_hotkey(VirtualKeyCode::Space),
+        Some(Hotkey::FastForward)
+    );
+    assert_eq!(bindings.get_hotkey(VirtualKeyCode::P), None);
+
+    // a missing section keeps the defaults
+    let bindings = KeyBindings::from_json(r#"{ "hotkeys": { "pause": "Escape" } }"#).unwrap();
+    assert_eq!(bindings.get_button(VirtualKeyCode::Z), Some(Button::A));
+    assert_eq!(
+        bindings.get_hotkey(VirtualKeyCode::Escape),
+        Some(Hotkey::Pause)
+    );
+}
+
+#[test]
+fn test_key_bindings_validation() {
+    let error = |contents: &str| KeyBindings::from_json(contents).unwrap_err();
+    assert!(error("{ buttons").starts_with("invalid JSON"));
+    assert!(error("[]").contains("expected an object"));
+    assert!(error(r#"{ "keys": {} }"#).contains("unknown section keys"));
+    assert!(error(r#"{ "buttons": { "turbo": "T" } }"#).contains("buttons: unknown button turbo"));
+    assert!(error(r#"{ "hotkeys": { "rewind": "R" } }"#).contains("unknown hotkey rewind"));
+    assert!(error(r#"{ "buttons": { "a": "Enter" } }"#).contains("buttons.a: unknown key Enter"));
+    assert!(error(r#"{ "buttons": { "a": 4 } }"#).contains("expected a key name"));
+    assert!(error(r#"{ "buttons": [] }"#).contains("buttons should map names to keys"));
+    assert_eq!(
+        error(r#"{ "buttons": { "a": "P" } }"#),
+        "key P is bound to both A and Pause"
+    );
+    assert!(KeyBindings::from_file("./does_not_exist.json").is_err());
+}
+
+#[test]
+fn test_parse_host_key() {
+    assert_eq!(parse_host_key("key1"), Ok(VirtualKeyCode::Key1));
+    assert_eq!(parse_host_key("F12"), Ok(VirtualKeyCode::F12));
+    assert_eq!(parse_host_key("lshift"), Ok(VirtualKeyCode::LShift));
+    assert!(parse_host_key("Hyper").is_err());
+}
+
+#[test]
+fn test_hotkey_state() {
+    let hotkeys = HotkeyState::new();
+    hotkeys.apply(Hotkey::Pause, true);
+    hotkeys.apply(Hotkey::Pause, false);
+    assert!(hotkeys.is_paused());
+    hotkeys.apply(Hotkey::Pause, true);
+    assert!(!hotkeys.is_paused());
+
+    hotkeys.apply(Hotkey::FastForward, true);
+    assert!(hotkeys.is_fast_forwarding());
+    hotkeys.apply(Hotkey::FastForward, false);
+    assert!(!hotkeys.is_fast_forwarding());
+
+    hotkeys.apply(Hotkey::Screenshot, true);
+    assert!(hotkeys.take_screenshot_request());
+    assert!(!hotkeys.take_screenshot_request());
+    hotkeys.apply(Hotkey::SaveState, true);
+    assert!(hotkeys.clone().take_save_state_request());
+    assert!(!hotkeys.take_save_state_request());
+    hotkeys.apply(Hotkey::LoadState, true);
+    assert!(hotkeys.take_load_state_request());
+    assert!(!hotkeys.take_load_state_request());
+}
@@ -0,0 +1,192 @@
+use std::net::TcpListener;
+
+use gbemulator::system::link_cable::{LinkCable, LinkMessage, LocalLinkCable, SocketLinkCable};
+use gbemulator::system::ram::RAM;
+use gbemulator::system::serial::SERIAL_INTERRUPT;
+
+const SB: u16 = 0xFF01;
+const SC: u16 = 0xFF02;
+const IF: u16 = 0xFF0F;
+
+fn linked_rams() -> (RAM, RAM) {
+    let (first_end, second_end) = LocalLinkCable::pair();
+    let mut first = RAM::new(None);
+    let mut second = RAM::new(None);
+    first.set_link_cable(first_end);
+    second.set_link_cable(second_end);
+    first.set_at(IF, 0x00).unwrap();
+    second.set_at(IF, 0x00).unwrap();
+    (first, second)
+}
+
+#[test]
+fn test_local_link_cable_transfer() {
+    let (mut master, mut slave) = linked_rams();
+    slave.set_at(SB, 0x42).unwrap();
+    slave.set_at(SC, 0x80).unwrap();
+    master.set_at(SB, 0x99).unwrap();
+    master.set_at(SC, 0x81).unwrap();
+
+    // the byte goes out as the transfer starts, but is only exchanged on the cycle its 8 bits
+    // are done, 1024 M-cycles later
+    master.tick(1023);
+    slave.tick(1023);
+    assert_eq!(slave.get_at(SC), Some(0xFE));
+    assert_eq!(slave.get_at(SB), Some(0x42));
+    assert_eq!(master.get_at(SB), Some(0x99));
+
+    // the master is done and waits for the answer
+    master.tick(1);
+    assert_eq!(master.get_at(SC), Some(0xFF));
+    assert!(master.is_link_stalled());
+    slave.tick(1);
+    assert_eq!(slave.get_at(SB), Some(0x99));
+    assert_eq!(slave.get_at(SC), Some(0x7E));
+    assert_eq!(slave.get_at(IF), Some(SERIAL_INTERRUPT));
+    master.poll_link();
+    assert!(!master.is_link_stalled());
+    assert_eq!(master.get_at(SB), Some(0x42));
+    assert_eq!(master.get_at(SC), Some(0x7F));
+    assert_eq!(master.get_at(IF), Some(SERIAL_INTERRUPT));
+}
+
+#[test]
+fn test_local_link_cable_without_listener() {
+    // a slave that did not start a transfer shifts nothing and leaves the line high
+    let (mut master, mut slave) = linked_rams();
+    slave.set_at(SB, 0x42).unwrap();
+    master.set_at(SB, 0x99).unwrap();
+    master.set_at(SC, 0x81).unwrap();
+    master.tick(1024);
+    slave.tick(1024);
+    master.poll_link();
+    assert_eq!(master.get_at(SB), Some(0xFF));
+    assert_eq!(slave.get_at(SB), Some(0x42));
+    assert_eq!(slave.get_at(IF), Some(0x00));
+
+    // with both sides providing the clock neither gets the other's byte
+    let (mut first, mut second) = linked_rams();
+    first.set_at(SB, 0x11).unwrap();
+    first.set_at(SC, 0x81).unwrap();
+    second.set_at(SB, 0x22).unwrap();
+    second.set_at(SC, 0x81).unwrap();
+    for _ in 0..3 {
+        first.tick(1024);
+        second.tick(1024);
+    }
+    assert_eq!(first.get_at(SB), Some(0xFF));
+    assert_eq!(second.get_at(SB), Some(0xFF));
+    assert_eq!(first.get_at(SC), Some(0x7F));
+    assert_eq!(second.get_at(SC), Some(0x7F));
+}
+
+#[test]
+fn test_linked_game_boys_stay_in_step() {
+    let (mut first, mut second) = linked_rams();
+    // at most half a byte ahead of the last clock heard from the other side
+    first.tick(600);
+    assert!(first.is_link_stalled());
+    second.tick(100);
+    first.poll_link();
+    assert!(first.is_link_stalled());
+    second.tick(100);
+    first.poll_link();
+    assert!(!first.is_link_stalled());
+    assert!(!second.is_link_stalled());
+}
+
+#[test]
+fn test_local_link_cable_unplugged() {
+    let (first_end, second_end) = LocalLinkCable::pair();
+    let mut ram = RAM::new(None);
+    ram.set_link_cable(first_end);
+    drop(second_end);
+    ram.set_at(SB, 0x00).unwrap();
+    ram.set_at(SC, 0x81).unwrap();
+    ram.tick(1024);
+    ram.tick(1024);
+    assert_eq!(ram.get_at(SB), Some(0xFF));
+    assert_eq!(ram.get_at(SC), Some(0x7F));
+}
+
+#[test]
+fn test_tcp_link_cable_transfer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let slave_thread = std::thread::spawn(move || {
+        let mut slave = RAM::new(None);
+        slave.set_link_cable(SocketLinkCable::connect_tcp(&address).unwrap());
+        slave.set_at(SB, 0x5A).unwrap();
+        slave.set_at(SC, 0x80).unwrap();
+        let mut cycles = 0;
+        while slave.get_at(SC) == Some(0xFE) {
+            step_linked(&mut slave, &mut cycles);
+        }
+        (slave.get_at(SB), cycles)
+    });
+    let mut master = RAM::new(None);
+    master.set_link_cable(SocketLinkCable::accept_tcp(&listener).unwrap());
+    master.set_at(SB, 0xA5).unwrap();
+    master.set_at(SC, 0x81).unwrap();
+    let mut cycles = 0;
+    while master.get_at(SC) == Some(0xFF) {
+        step_linked(&mut master, &mut cycles);
+    }
+    assert_eq!(master.get_at(SB), Some(0x5A));
+    // both ends complete on the cycle the transfer was stamped with, however the threads ran
+    assert_eq!(cycles, 1024);
+    assert_eq!(slave_thread.join().unwrap(), (Some(0xA5), 1024));
+}
+
+// like the CPU thread, waits while the other end catches up
+fn step_linked(ram: &mut RAM, cycles: &mut u64) {
+    if ram.is_link_stalled() {
+        ram.poll_link();
+        std::thread::sleep(std::time::Duration::from_micros(100));
+    } else {
+        ram.tick(4);
+        *cycles += 4;
+    }
+}
+
+fn wait(end: &mut SocketLinkCable) -> Option<LinkMessage> {
+    loop {
+        if let Some(message) = end.receive() {
+            return Some(message);
+        }
+        if !end.is_connected() {
+            return None;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unix_link_cable_messages() {
+    let path = std::env::temp_dir().join("gbemulator_link_cable.sock");
+    let listen_path = path.to_str().unwrap().to_string();
+    let listener_thread = std::thread::spawn(move || {
+        let mut end = SocketLinkCable::listen_unix(&listen_path).unwrap();
+        let message = wait(&mut end);
+        end.send(LinkMessage::Reply(0x24));
+        message
+    });
+    let mut end = loop {
+        match SocketLinkCable::connect_unix(path.to_str().unwrap()) {
+            Ok(end) => break end,
+            Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+        }
+    };
+    let transfer = LinkMessage::Transfer {
+        byte: 0x42,
+        cycle: 0x0123_4567_89AB,
+    };
+    end.send(transfer);
+    assert_eq!(wait(&mut end), Some(LinkMessage::Reply(0x24)));
+    assert_eq!(listener_thread.join().unwrap(), Some(transfer));
+    assert!(end.is_connected());
+    assert_eq!(wait(&mut end), None);
+    assert!(!end.is_connected());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,608 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use gbemulator::system::ram::mapping_chip::accelerometer::{
+    SharedAccelerometer, ACCELEROMETER_CENTER,
+};
+use gbemulator::system::ram::mapping_chip::infrared::SharedInfrared;
+use gbemulator::system::ram::mapping_chip::rtc::{ManualClock, CYCLES_PER_SECOND, RTC_FOOTER_SIZE};
+use gbemulator::system::ram::mapping_chip::{
+    DynamicMappingChip, HuC1, HuC3, MappingChip, MBC1, MBC2, MBC3, MBC5, MBC7, MMM01, NINTENDO_LOGO,
+};
+use gbemulator::system::ram::RAM;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+
+// every bank starts with its own bank number so reads tell which one is mapped
+fn make_rom(n_banks: usize, cartridge_type: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0u8; n_banks * ROM_BANK_SIZE];
+    for bank in 0..n_banks {
+        rom[bank * ROM_BANK_SIZE] = bank as u8;
+    }
+    rom[0x0147] = cartridge_type;
+    rom[0x0149] = ram_size_code;
+    rom
+}
+
+fn make_ram(chip: MBC1) -> RAM {
+    make_ram_with_chip(DynamicMappingChip::MBC1(chip))
+}
+
+fn make_ram_with_chip(chip: DynamicMappingChip) -> RAM {
+    let mut ram = RAM::new(Some(chip));
+    ram.set_at(0xFF50, 0x01).unwrap();
+    ram.load_base_rom_bank();
+    ram
+}
+
+#[test]
+fn test_mbc1_bank1_zero_maps_to_one() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(8, 0x01, 0x00)));
+    assert_eq!(ram.get_at(0x4000), Some(1));
+    ram.set_at(0x2000, 0x05).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(5));
+    ram.set_at(0x2000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(1));
+    // only the lower five bits are checked for zero
+    ram.set_at(0x2000, 0xE0).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(1));
+}
+
+#[test]
+fn test_mbc1_rom_writes_do_not_modify_rom() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(4, 0x01, 0x00)));
+    ram.set_at(0x2000, 0x02).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(0));
+    assert_eq!(ram.get_at(0x2000), Some(0));
+    assert_eq!(ram.get_at(0x4000), Some(2));
+}
+
+#[test]
+fn test_mbc1_bank_number_masked_to_rom_size() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(4, 0x01, 0x00)));
+    ram.set_at(0x2000, 0x07).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(3));
+    ram.set_at(0x2000, 0x04).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0));
+}
+
+#[test]
+fn test_mbc1_large_rom_mode1_remaps_bank0() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(128, 0x01, 0x00)));
+    ram.set_at(0x4000, 0x02).unwrap();
+    ram.set_at(0x2000, 0x03).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x43));
+    // mode 0 keeps bank 0 in the lower window
+    assert_eq!(ram.get_at(0x0000), Some(0x00));
+    ram.set_at(0x6000, 0x01).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(0x40));
+    assert_eq!(ram.get_at(0x4000), Some(0x43));
+    // 0x20/0x40/0x60 cannot be selected in the upper window
+    ram.set_at(0x2000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x41));
+    ram.set_at(0x6000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(0x00));
+}
+
+#[test]
+fn test_mbc1_ram_enable_and_banking() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(4, 0x03, 0x03)));
+    // disabled ram reads as open bus and ignores writes
+    ram.set_at(0xA000, 0x12).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0xFF));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0xA000, 0x12).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x12));
+    // bank 2 only selects the ram bank in mode 1
+    ram.set_at(0x4000, 0x01).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x12));
+    ram.set_at(0x6000, 0x01).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x00));
+    ram.set_at(0xA000, 0x34).unwrap();
+    ram.set_at(0x4000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x12));
+    // only the lower nibble is decoded by the enable register
+    ram.set_at(0x0000, 0xFA).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x12));
+    ram.set_at(0x0000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0xFF));
+}
+
+#[test]
+fn test_oam_dma_copies_from_the_selected_ram_bank() {
+    let mut ram = make_ram(MBC1::from_rom_contents(make_rom(4, 0x03, 0x03)));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0x6000, 0x01).unwrap();
+    ram.set_at(0x4000, 0x01).unwrap();
+    for i in 0..0xA0 {
+        ram.set_at(0xA000 + i, i as u8).unwrap();
+    }
+    ram.set_at(0xFF46, 0xA0).unwrap();
+    for i in 0..0xA0 {
+        assert_eq!(ram.get_at(0xFE00 + i), Some(i as u8));
+    }
+}
+
+#[test]
+fn test_mbc1_multicart() {
+    let mut rom = make_rom(64, 0x01, 0x00);
+    for game in 0..4 {
+        let logo_start = game * 0x10 * ROM_BANK_SIZE + 0x0104;
+        rom[logo_start..(logo_start + NINTENDO_LOGO.len())].copy_from_slice(&NINTENDO_LOGO);
+    }
+    let chip = MBC1::from_rom_contents(rom);
+    assert!(chip.is_multicart());
+    let mut ram = make_ram(chip);
+    ram.set_at(0x4000, 0x01).unwrap();
+    ram.set_at(0x2000, 0x02).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x12));
+    // bit 4 of BANK1 is not wired on multicarts
+    ram.set_at(0x2000, 0x12).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x12));
+    ram.set_at(0x6000, 0x01).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(0x10));
+}
+
+#[test]
+fn test_mbc1_not_multicart_without_second_header() {
+    let chip = MBC1::from_rom_contents(make_rom(64, 0x01, 0x00));
+    assert!(!chip.is_multicart());
+    let mut chip = chip;
+    chip.is_selecting_chip_rom(0x4000, 0x01);
+    chip.is_selecting_chip_rom(0x2000, 0x02);
+    assert_eq!(chip.get_selected_rom_bank_number(), 0x22);
+}
+
+#[test]
+fn test_mbc2_register_selection_uses_address_bit_8() {
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC2(MBC2::from_rom_contents(make_rom(
+        16, 0x06, 0x00,
+    ))));
+    // bit 8 set selects the ROM bank, anywhere in 0x0000-0x3FFF
+    ram.set_at(0x2100, 0x03).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(3));
+    ram.set_at(0x0100, 0x07).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(7));
+    ram.set_at(0x3F00, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(1));
+    ram.set_at(0x0100, 0xFF).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(15));
+    // bit 8 clear only touches RAM enable
+    ram.set_at(0x2000, 0x02).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(15));
+    assert_eq!(ram.get_at(0xA000), Some(0xFF));
+    ram.set_at(0x2000, 0x0A).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0xF0));
+}
+
+#[test]
+fn test_mbc2_half_byte_ram_is_mirrored() {
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC2(MBC2::from_rom_contents(make_rom(
+        4, 0x06, 0x00,
+    ))));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0xA001, 0x5C).unwrap();
+    assert_eq!(ram.get_at(0xA001), Some(0xFC));
+    assert_eq!(ram.get_at(0xA201), Some(0xFC));
+    assert_eq!(ram.get_at(0xBE01), Some(0xFC));
+    ram.set_at(0xB1FF, 0x03).unwrap();
+    assert_eq!(ram.get_at(0xA1FF), Some(0xF3));
+    ram.set_at(0x0000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xA001), Some(0xFF));
+}
+
+#[test]
+fn test_mbc2_battery_save_roundtrip() {
+    let mut chip = DynamicMappingChip::MBC2(MBC2::from_rom_contents(make_rom(4, 0x06, 0x00)));
+    chip.is_ram_enabled(0x0000, 0x0A);
+    chip.write_ram(0xA010, 0x09);
+    let save_path = std::env::temp_dir().join("gbemulator_mbc2_test.sav");
+    let save_path = save_path.to_str().unwrap();
+    chip.save_battery(save_path).unwrap();
+    assert_eq!(std::fs::read(save_path).unwrap().len(), 512);
+
+    let mut restored = DynamicMappingChip::MBC2(MBC2::from_rom_contents(make_rom(4, 0x06, 0x00)));
+    restored.load_battery(save_path).unwrap();
+    restored.is_ram_enabled(0x0000, 0x0A);
+    assert_eq!(restored.read_ram(0xA010), Some(0xF9));
+    std::fs::remove_file(save_path).unwrap();
+
+    // carts without a battery have nothing to save
+    let no_battery = MBC2::from_rom_contents(make_rom(4, 0x05, 0x00));
+    assert!(no_battery.get_battery_data().is_none());
+}
+
+fn make_mbc3_ram(clock: &ManualClock) -> RAM {
+    let chip = MBC3::from_rom_contents(make_rom(128, 0x10, 0x03))
+        .with_clock_source(Arc::new(clock.clone()));
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC3(chip));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram
+}
+
+fn latch_clock(ram: &mut RAM) {
+    ram.set_at(0x6000, 0x00).unwrap();
+    ram.set_at(0x6000, 0x01).unwrap();
+}
+
+fn read_rtc_register(ram: &mut RAM, register: u8) -> u8 {
+    ram.set_at(0x4000, register).unwrap();
+    ram.get_at(0xA000).unwrap()
+}
+
+#[test]
+fn test_mbc3_rom_and_ram_banking() {
+    let mut ram = make_mbc3_ram(&ManualClock::new(0));
+    ram.set_at(0x2000, 0x7F).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x7F));
+    ram.set_at(0x2000, 0x80).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x01));
+    for bank in 0..4u8 {
+        ram.set_at(0x4000, bank).unwrap();
+        ram.set_at(0xA123, 0x10 + bank).unwrap();
+    }
+    for bank in 0..4u8 {
+        ram.set_at(0x4000, bank).unwrap();
+        assert_eq!(ram.get_at(0xA123), Some(0x10 + bank));
+    }
+}
+
+#[test]
+fn test_mbc3_rtc_advances_with_emulated_time_and_latches() {
+    let mut ram = make_mbc3_ram(&ManualClock::new(0));
+    ram.tick(CYCLES_PER_SECOND * 61);
+    // nothing latched yet
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 0);
+    latch_clock(&mut ram);
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 1);
+    assert_eq!(read_rtc_register(&mut ram, 0x09), 1);
+    // latched values stay put until the next latch
+    ram.tick(CYCLES_PER_SECOND * 5);
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 1);
+    latch_clock(&mut ram);
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 6);
+    // a 1 without a preceding 0 does not latch
+    ram.tick(CYCLES_PER_SECOND);
+    ram.set_at(0x6000, 0x01).unwrap();
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 6);
+}
+
+#[test]
+fn test_mbc3_rtc_halt_and_day_carry() {
+    let mut ram = make_mbc3_ram(&ManualClock::new(0));
+    // 511 days, 23:59:59
+    ram.set_at(0x4000, 0x0C).unwrap();
+    ram.set_at(0xA000, 0x41).unwrap();
+    ram.set_at(0x4000, 0x0B).unwrap();
+    ram.set_at(0xA000, 0xFF).unwrap();
+    ram.set_at(0x4000, 0x0A).unwrap();
+    ram.set_at(0xA000, 23).unwrap();
+    ram.set_at(0x4000, 0x09).unwrap();
+    ram.set_at(0xA000, 59).unwrap();
+    ram.set_at(0x4000, 0x08).unwrap();
+    ram.set_at(0xA000, 59).unwrap();
+    // halted clocks do not count
+    ram.tick(CYCLES_PER_SECOND * 3);
+    latch_clock(&mut ram);
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 59);
+    ram.set_at(0x4000, 0x0C).unwrap();
+    ram.set_at(0xA000, 0x01).unwrap();
+    ram.tick(CYCLES_PER_SECOND);
+    latch_clock(&mut ram);
+    assert_eq!(read_rtc_register(&mut ram, 0x08), 0);
+    assert_eq!(read_rtc_register(&mut ram, 0x09), 0);
+    assert_eq!(read_rtc_register(&mut ram, 0x0A), 0);
+    assert_eq!(read_rtc_register(&mut ram, 0x0B), 0);
+    assert_eq!(read_rtc_register(&mut ram, 0x0C), 0x80);
+}
+
+#[test]
+fn test_mbc3_battery_save_with_rtc_footer() {
+    let clock = ManualClock::new(1_000_000);
+    let mut chip = DynamicMappingChip::MBC3(
+        MBC3::from_rom_contents(make_rom(4, 0x10, 0x02)).with_clock_source(Arc::new(clock.clone())),
+    );
+    chip.is_ram_enabled(0x0000, 0x0A);
+    chip.write_ram(0xA000, 0x42);
+    chip.tick(CYCLES_PER_SECOND * 10);
+    let data = chip.get_battery_data().unwrap();
+    assert_eq!(data.len(), 8 * 1024 + RTC_FOOTER_SIZE);
+    assert_eq!(data[8 * 1024], 10);
+    assert_eq!(
+        u64::from_le_bytes(data[(data.len() - 8)..].try_into().unwrap()),
+        1_000_000
+    );
+
+    // the time spent powered off is added back on load
+    clock.advance(3600 + 5);
+    let mut restored =
+        MBC3::from_rom_contents(make_rom(4, 0x10, 0x02)).with_clock_source(Arc::new(clock.clone()));
+    restored.load_battery_data(&data);
+    let registers = restored.get_rtc().get_registers();
+    assert_eq!(registers.seconds, 15);
+    assert_eq!(registers.minutes, 0);
+    assert_eq!(registers.hours, 1);
+    restored.is_ram_enabled(0x0000, 0x0A);
+    assert_eq!(restored.read_ram(0xA000), Some(0x42));
+}
+
+#[test]
+fn test_mbc5_nine_bit_rom_banking() {
+    let mut rom = make_rom(512, 0x19, 0x00);
+    // bank numbers above 0xFF don't fit in the marker byte, use the second byte for bit 8
+    for bank in 256..512 {
+        rom[bank * ROM_BANK_SIZE + 1] = 0x01;
+    }
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC5(MBC5::from_rom_contents(rom)));
+    assert_eq!(ram.get_at(0x4000), Some(0x01));
+    ram.set_at(0x2000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x00));
+    ram.set_at(0x2FFF, 0x34).unwrap();
+    ram.set_at(0x3000, 0x01).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x34));
+    assert_eq!(ram.get_at(0x4001), Some(0x01));
+    ram.set_at(0x3000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x4001), Some(0x00));
+    assert_eq!(ram.get_at(0x0000), Some(0x00));
+}
+
+#[test]
+fn test_mbc5_sixteen_ram_banks() {
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC5(MBC5::from_rom_contents(make_rom(
+        4, 0x1B, 0x04,
+    ))));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    for bank in 0..16u8 {
+        ram.set_at(0x4000, bank).unwrap();
+        ram.set_at(0xBFFF, bank).unwrap();
+    }
+    for bank in 0..16u8 {
+        ram.set_at(0x4000, bank).unwrap();
+        assert_eq!(ram.get_at(0xBFFF), Some(bank));
+    }
+    ram.set_at(0x0000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xBFFF), Some(0xFF));
+}
+
+#[test]
+fn test_mbc5_rumble() {
+    let rumbling = Arc::new(AtomicBool::new(false));
+    let changes = Arc::new(AtomicUsize::new(0));
+    let callback_rumbling = rumbling.clone();
+    let callback_changes = changes.clone();
+    let chip = MBC5::from_rom_contents(make_rom(4, 0x1E, 0x03)).with_rumble_callback(Arc::new(
+        move |state| {
+            callback_rumbling.store(state, Ordering::Relaxed);
+            callback_changes.fetch_add(1, Ordering::Relaxed);
+        },
+    ));
+    assert!(chip.has_rumble());
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC5(chip));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0x4000, 0x00).unwrap();
+    ram.set_at(0xA000, 0x11).unwrap();
+    ram.set_at(0x4000, 0x08).unwrap();
+    assert!(rumbling.load(Ordering::Relaxed));
+    assert!(ram.get_mapping_chip().is_rumbling());
+    // bit 3 does not select a RAM bank on rumble carts
+    assert_eq!(ram.get_at(0xA000), Some(0x11));
+    ram.set_at(0x4000, 0x09).unwrap();
+    assert_eq!(changes.load(Ordering::Relaxed), 1);
+    ram.set_at(0x4000, 0x00).unwrap();
+    assert!(!rumbling.load(Ordering::Relaxed));
+    assert_eq!(changes.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn test_huc1_banking_and_infrared() {
+    let infrared = SharedInfrared::new();
+    let chip = HuC1::from_rom_contents(make_rom(64, 0xFF, 0x03))
+        .with_infrared_port(Arc::new(infrared.clone()));
+    let mut ram = make_ram_with_chip(DynamicMappingChip::HuC1(chip));
+    ram.set_at(0x2000, 0x3F).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(0x3F));
+    ram.set_at(0x4000, 0x02).unwrap();
+    ram.set_at(0xA000, 0x42).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x42));
+
+    ram.set_at(0x0000, 0x0E).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0xC0));
+    infrared.set_light(true);
+    assert_eq!(ram.get_at(0xA000), Some(0xC1));
+    ram.set_at(0xA000, 0x01).unwrap();
+    assert!(infrared.is_led_on());
+    ram.set_at(0xA000, 0x00).unwrap();
+    assert!(!infrared.is_led_on());
+
+    // leaving IR mode gives the RAM back untouched
+    ram.set_at(0x0000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x42));
+}
+
+fn huc3_command(ram: &mut RAM, command: u8) -> u8 {
+    ram.set_at(0x0000, 0x0B).unwrap();
+    ram.set_at(0xA000, command).unwrap();
+    ram.set_at(0x0000, 0x0D).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0xFF));
+    ram.set_at(0xA000, 0xFE).unwrap();
+    ram.set_at(0x0000, 0x0C).unwrap();
+    ram.get_at(0xA000).unwrap()
+}
+
+#[test]
+fn test_huc3_rtc_commands() {
+    let clock = ManualClock::new(1_000_000);
+    let chip =
+        HuC3::from_rom_contents(make_rom(4, 0xFE, 0x03)).with_clock_source(Arc::new(clock.clone()));
+    let mut ram = make_ram_with_chip(DynamicMappingChip::HuC3(chip));
+    ram.tick(61 * 60 * CYCLES_PER_SECOND);
+
+    // copy the time to memory and read the minutes back from address 0x00
+    huc3_command(&mut ram, 0x60);
+    huc3_command(&mut ram, 0x40);
+    huc3_command(&mut ram, 0x50);
+    let nibbles: Vec<u8> = (0..6)
+        .map(|_| huc3_command(&mut ram, 0x10) & 0x0F)
+        .collect();
+    assert_eq!(nibbles, vec![0x0D, 0x03, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(huc3_command(&mut ram, 0x62), 0x61);
+
+    // write 2 days and 0 minutes to memory and load them into the clock
+    huc3_command(&mut ram, 0x40);
+    for nibble in [0x0, 0x0, 0x0, 0x2, 0x0, 0x0] {
+        huc3_command(&mut ram, 0x30 | nibble);
+    }
+    huc3_command(&mut ram, 0x61);
+    let DynamicMappingChip::HuC3(huc3) = ram.get_mapping_chip() else {
+        panic!("expected a HuC3");
+    };
+    assert_eq!(huc3.get_clock().get_days(), 2);
+    assert_eq!(huc3.get_clock().get_minutes(), 0);
+
+    // the footer catches up on the minutes spent with the emulator closed
+    let data = ram.get_mapping_chip().get_battery_data().unwrap();
+    clock.advance(25 * 60 * 60);
+    let mut reloaded =
+        HuC3::from_rom_contents(make_rom(4, 0xFE, 0x03)).with_clock_source(Arc::new(clock.clone()));
+    reloaded.load_battery_data(&data);
+    assert_eq!(reloaded.get_clock().get_days(), 3);
+    assert_eq!(reloaded.get_clock().get_minutes(), 60);
+}
+
+#[test]
+fn test_huc3_ram_modes() {
+    let mut ram = make_ram_with_chip(DynamicMappingChip::HuC3(HuC3::from_rom_contents(make_rom(
+        4, 0xFE, 0x03,
+    ))));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0xA000, 0x12).unwrap();
+    // mode 0 can read but not write the RAM
+    ram.set_at(0x0000, 0x00).unwrap();
+    ram.set_at(0xA000, 0x34).unwrap();
+    assert_eq!(ram.get_at(0xA000), Some(0x12));
+}
+
+fn make_mmm01_rom() -> Vec<u8> {
+    // 16 banks of games followed by the two menu banks, padded to a power of two
+    let mut rom = make_rom(32, 0x00, 0x00);
+    let menu = 30 * ROM_BANK_SIZE;
+    rom[menu + 0x0147] = 0x0D;
+    rom[menu + 0x0149] = 0x03;
+    rom
+}
+
+#[test]
+fn test_mmm01_menu_then_mapped_game() {
+    let rom = make_mmm01_rom();
+    assert!(MMM01::is_mmm01_rom(&rom));
+    assert!(!MMM01::is_mmm01_rom(&make_rom(32, 0x0D, 0x00)));
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MMM01(MMM01::from_rom_contents(rom)));
+    // the menu boots from the last 32KiB
+    assert_eq!(ram.get_at(0x0000), Some(30));
+    assert_eq!(ram.get_at(0x4000), Some(31));
+
+    // select the game starting at bank 8, lock ROM bank bits 2-4 for a 4 bank window and map it
+    ram.set_at(0x2000, 0x08).unwrap();
+    ram.set_at(0x6000, 0x0E << 2).unwrap();
+    ram.set_at(0x0000, 0x40).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(8));
+    assert_eq!(ram.get_at(0x4000), Some(9));
+    ram.set_at(0x2000, 0x03).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(11));
+    // bits outside of the window stay with the menu configuration
+    ram.set_at(0x2000, 0x1F).unwrap();
+    assert_eq!(ram.get_at(0x4000), Some(11));
+    assert_eq!(ram.get_at(0x0000), Some(8));
+    // the map enable bit cannot be cleared anymore
+    ram.set_at(0x0000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0x0000), Some(8));
+}
+
+fn mbc7_eeprom_clock(ram: &mut RAM, bit: u8) {
+    let di = (bit & 0x01) << 1;
+    ram.set_at(0xA080, 0x80 | di).unwrap();
+    ram.set_at(0xA080, 0xC0 | di).unwrap();
+}
+
+fn mbc7_eeprom_command(ram: &mut RAM, bits: u32, length: u8) {
+    ram.set_at(0xA080, 0x00).unwrap();
+    ram.set_at(0xA080, 0x80).unwrap();
+    for i in (0..length).rev() {
+        mbc7_eeprom_clock(ram, ((bits >> i) & 0x01) as u8);
+    }
+}
+
+fn mbc7_eeprom_read(ram: &mut RAM, address: u8) -> u16 {
+    mbc7_eeprom_command(ram, 0b110 << 8 | address as u32, 11);
+    assert_eq!(ram.get_at(0xA080).unwrap() & 0x01, 0);
+    let mut value = 0u16;
+    for _ in 0..16 {
+        mbc7_eeprom_clock(ram, 0);
+        value = (value << 1) | (ram.get_at(0xA080).unwrap() & 0x01) as u16;
+    }
+    value
+}
+
+fn make_mbc7_ram(accelerometer: &SharedAccelerometer) -> RAM {
+    let chip = MBC7::from_rom_contents(make_rom(8, 0x22, 0x00))
+        .with_accelerometer(Arc::new(accelerometer.clone()));
+    let mut ram = make_ram_with_chip(DynamicMappingChip::MBC7(chip));
+    ram.set_at(0x0000, 0x0A).unwrap();
+    ram.set_at(0x4000, 0x40).unwrap();
+    ram
+}
+
+#[test]
+fn test_mbc7_accelerometer_latch() {
+    let accelerometer = SharedAccelerometer::new();
+    let mut ram = make_mbc7_ram(&accelerometer);
+    accelerometer.set_tilt(1.0, -0.5);
+    // nothing is sampled before the latch has been erased
+    ram.set_at(0xA010, 0xAA).unwrap();
+    assert_eq!(ram.get_at(0xA030), Some(0x80));
+    ram.set_at(0xA000, 0x55).unwrap();
+    ram.set_at(0xA010, 0xAA).unwrap();
+    let read_axis = |ram: &RAM, low: u16| {
+        (ram.get_at(low + 0x10).unwrap() as u16) << 8 | ram.get_at(low).unwrap() as u16
+    };
+    assert_eq!(read_axis(&ram, 0xA020), ACCELEROMETER_CENTER + 112);
+    assert_eq!(read_axis(&ram, 0xA040), ACCELEROMETER_CENTER - 56);
+    assert_eq!(ram.get_at(0xA060), Some(0x00));
+    assert_eq!(ram.get_at(0xA070), Some(0xFF));
+    assert_eq!(ram.get_at(0xB020), Some(0xFF));
+
+    // without the second enable the registers are gone
+    ram.set_at(0x4000, 0x00).unwrap();
+    assert_eq!(ram.get_at(0xA020), Some(0xFF));
+}
+
+#[test]
+fn test_mbc7_eeprom_protocol() {
+    let mut ram = make_mbc7_ram(&SharedAccelerometer::new());
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x05), 0xFFFF);
+
+    // writes are ignored until EWEN
+    mbc7_eeprom_command(&mut ram, (0b101 << 24) | (0x05 << 16) | 0x1234, 27);
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x05), 0xFFFF);
+    mbc7_eeprom_command(&mut ram, 0b100_1100_0000, 11);
+    mbc7_eeprom_command(&mut ram, (0b101 << 24) | (0x05 << 16) | 0x1234, 27);
+    assert_eq!(ram.get_at(0xA080).unwrap() & 0x01, 0x01);
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x05), 0x1234);
+
+    // ERASE sets the word back to all ones
+    mbc7_eeprom_command(&mut ram, 0b111 << 8 | 0x05, 11);
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x05), 0xFFFF);
+
+    // WRAL then EWDS
+    mbc7_eeprom_command(&mut ram, (0b100_0100_0000 << 16) | 0xBEEF, 27);
+    mbc7_eeprom_command(&mut ram, 0b100_0000_0000, 11);
+    mbc7_eeprom_command(&mut ram, 0b111 << 8 | 0x10, 11);
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x10), 0xBEEF);
+    assert_eq!(mbc7_eeprom_read(&mut ram, 0x7F), 0xBEEF);
+
+    let data = ram.get_mapping_chip().get_battery_data().unwrap();
+    assert_eq!(data.len(), 256);
+    let mut reloaded = MBC7::from_rom_contents(make_rom(8, 0x22, 0x00));
+    reloaded.load_battery_data(&data);
+    assert_eq!(reloaded.get_eeprom().get_word(0x10), 0xBEEF);
+}
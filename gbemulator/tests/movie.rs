@@ -0,0 +1,219 @@
+mod common;
+
+use gbemulator::system::boot::GameBoyModel;
+use gbemulator::system::controllers::key_bindings::Hotkey;
+use gbemulator::system::movie::{fnv1a_hash, Movie, MovieStart};
+use gbemulator::system::ram::mapping_chip::rtc::RTC_FOOTER_SIZE;
+use gbemulator::system::ram::mapping_chip::{battery_save_path, DynamicMappingChip};
+use gbemulator::system::System;
+
+// an MBC3 cart with a clock and a battery, which adds the pressed buttons, the clock seconds and
+// its first RAM byte into that byte and the background palette in a loop, so that every frame
+// depends on the three
+fn make_battery_clock_rom() -> Vec<u8> {
+    let mut rom = common::make_rom(&[0xC3, 0x50, 0x01]); // JP 0x0150
+    rom[0x0147] = 0x10;
+    rom[0x0149] = 0x02;
+    let program = [
+        0x3E, 0x0A, // LD A,0x0A
+        0xEA, 0x00, 0x00, // LD (0x0000),A  enables the RAM and the clock
+        0x3E, 0x08, // LD A,0x08
+        0xEA, 0x00, 0x40, // LD (0x4000),A  selects the seconds
+        0xAF, // XOR A
+        0xEA, 0x00, 0x60, // LD (0x6000),A
+        0x3C, // INC A
+        0xEA, 0x00, 0x60, // LD (0x6000),A  latches the clock
+        0xFA, 0x00, 0xA0, // LD A,(0xA000)
+        0x47, // LD B,A
+        0xAF, // XOR A
+        0xEA, 0x00, 0x40, // LD (0x4000),A  selects RAM bank 0
+        0x3E, 0x10, // LD A,0x10
+        0xE0, 0x00, // LDH (0x00),A  selects the action buttons
+        0xF0, 0x00, // LDH A,(0x00)
+        0x80, // ADD A,B
+        0x21, 0x00, 0xA0, // LD HL,0xA000
+        0x86, // ADD A,(HL)
+        0x77, // LD (HL),A
+        0xE0, 0x47, // LDH (0x47),A
+        0x18, 0xDB, // JR back to the seconds
+    ];
+    rom[0x0150..0x0150 + program.len()].copy_from_slice(&program);
+    rom
+}
+
+// 8KB of RAM followed by a clock footer saved at the given unix time
+fn make_battery_save(first_byte: u8, timestamp: u64) -> Vec<u8> {
+    let mut save = vec![0u8; 0x2000 + RTC_FOOTER_SIZE];
+    save[0] = first_byte;
+    save[0x2000 + 40..0x2000 + 48].copy_from_slice(&timestamp.to_le_bytes());
+    save
+}
+
+#[test]
+fn test_fnv1a_hash() {
+    assert_eq!(fnv1a_hash(b""), 0xCBF29CE484222325);
+    assert_eq!(fnv1a_hash(b"a"), 0xAF63DC4C8601EC8C);
+    assert_eq!(fnv1a_hash(b"foobar"), 0x85944171F73967E8);
+}
+
+#[test]
+fn test_movie_round_trip() {
+    let mut movie = Movie::new(
+        0x0123456789ABCDEF,
+        None,
+        1_700_000_000,
+        MovieStart::SkipBoot(GameBoyModel::MGB),
+    )
+    .with_check(2, 0xFEDCBA9876543210);
+    movie.record_frame(0x00);
+    movie.record_frame(0x10);
+    movie.record_frame(0x81);
+    let contents = movie.to_string();
+    assert_eq!(
+        contents,
+        "gbmovie 1\n\
+         rom 0123456789abcdef\n\
+         battery none\n\
+         clock 1700000000\n\
+         start skip mgb\n\
+         check 2 fedcba9876543210\n\
+         frames 3\n\
+         00\n\
+         10\n\
+         81\n"
+    );
+    assert_eq!(Movie::parse(&contents), Ok(movie));
+
+    let movie = Movie::new(0x42, Some(vec![0x00, 0xA5, 0xFF]), 0, MovieStart::BootRom);
+    assert!(movie.to_string().contains("battery 00a5ff\n"));
+    let parsed = Movie::parse(&movie.to_string()).unwrap();
+    assert_eq!(parsed.get_start(), MovieStart::BootRom);
+    assert_eq!(parsed.get_rom_hash(), 0x42);
+    assert!(parsed.get_frames().is_empty());
+    assert_eq!(parsed, movie);
+}
+
+#[test]
+fn test_movie_parse_errors() {
+    let error = |contents: &str| Movie::parse(contents).unwrap_err();
+    assert!(error("").contains("a movie starts with"));
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nbattery none\nclock 0\nstart boot\nframes 1\n"),
+        "expected 1 frames, got 0"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom xyz\n"),
+        "line 2: xyz is not a hex hash"
+    );
+    assert!(
+        error("gbmovie 1\nrom 12\nbattery none\nclock 0\nstart skip gba\nframes 0\n")
+            .starts_with("line 5: unknown model gba")
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nbattery none\nclock 0\nstart boot\nframes 1\n1FF\n"),
+        "line 7: 1FF is not a joypad state"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nbattery none\nclock 0\nframes 0\n"),
+        "missing start"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nbattery none\nstart boot\nframes 0\n"),
+        "missing clock"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nbattery a5f\n"),
+        "line 3: the battery save should be hex bytes"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nclock yesterday\n"),
+        "line 3: the clock should be a unix time"
+    );
+    assert_eq!(
+        error("gbmovie 1\nrom 12\nspeed 2\n"),
+        "line 3: unexpected \"speed 2\""
+    );
+    assert!(Movie::from_file("./does_not_exist.txt").is_err());
+}
+
+#[test]
+fn test_movie_rejects_other_rom() {
+    let rom_path = common::write_rom("gbemulator_movie_other.gb", &common::make_rom(&[]));
+    let movie = Movie::new(0x1234, None, 0, MovieStart::BootRom);
+    let result = movie.play(&rom_path);
+    assert!(result
+        .unwrap_err()
+        .contains("does not match the ROM the movie was recorded with"));
+    std::fs::remove_file(rom_path).unwrap();
+}
+
+#[test]
+fn test_recorded_movie_plays_back_the_same_twice() {
+    let rom_path = common::write_rom("gbemulator_movie_battery.gb", &make_battery_clock_rom());
+    let rom_path = rom_path.as_str();
+    let movie_path = std::env::temp_dir().join("gbemulator_movie_battery.txt");
+    let movie_path = movie_path.to_str().unwrap();
+    let save_path = battery_save_path(rom_path);
+    let save = make_battery_save(0x5A, 1_000_000_000);
+    std::fs::write(&save_path, &save).unwrap();
+
+    let system = System::new(
+        Some(DynamicMappingChip::from_rom_path(rom_path).unwrap()),
+        true,
+    )
+    .with_skip_boot(GameBoyModel::DMG)
+    .with_movie_recording(movie_path)
+    .unwrap();
+    system.get_hotkeys().apply(Hotkey::FastForward, true);
+    // A held for the whole run
+    system.get_joypad_input().set_state(0x10);
+    system.run(20_000);
+    // the run saved its battery over the one the movie started from
+    assert!(std::fs::read(&save_path).unwrap() != save);
+
+    let movie = Movie::from_file(movie_path).unwrap();
+    let frame_count = movie.get_frames().len();
+    assert!(frame_count > 2);
+    let frame_hashes = movie.play(rom_path).unwrap();
+    let movie = movie
+        .with_check(2, frame_hashes[1])
+        .with_check(frame_count, frame_hashes[frame_count - 1]);
+    assert!(movie.play(rom_path).unwrap() == frame_hashes);
+    // playback neither reads nor writes the battery save on disk
+    std::fs::write(&save_path, make_battery_save(0xC3, 0)).unwrap();
+    assert!(movie.play(rom_path).unwrap() == frame_hashes);
+    assert_eq!(
+        std::fs::read(&save_path).unwrap(),
+        make_battery_save(0xC3, 0)
+    );
+
+    std::fs::remove_file(rom_path).unwrap();
+    std::fs::remove_file(movie_path).unwrap();
+    std::fs::remove_file(save_path).unwrap();
+}
+
+#[test]
+fn test_movie_clock_drives_the_cartridge_clock() {
+    let rom = make_battery_clock_rom();
+    let rom_path = common::write_rom("gbemulator_movie_clock.gb", &rom);
+    let rom_path = rom_path.as_str();
+    let save = make_battery_save(0x00, 1_000_000_000);
+    let movie_at = |clock: u64| {
+        let mut movie = Movie::new(
+            fnv1a_hash(&rom),
+            Some(save.clone()),
+            clock,
+            MovieStart::SkipBoot(GameBoyModel::DMG),
+        );
+        for _ in 0..2 {
+            movie.record_frame(0x00);
+        }
+        movie
+    };
+
+    // the time between the save and the movie is caught up on, whenever the movie is played
+    let frame_hashes = movie_at(1_000_000_000).play(rom_path).unwrap();
+    assert!(movie_at(1_000_000_000).play(rom_path).unwrap() == frame_hashes);
+    assert!(movie_at(1_000_000_030).play(rom_path).unwrap() != frame_hashes);
+    std::fs::remove_file(rom_path).unwrap();
+}
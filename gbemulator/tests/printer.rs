@@ -0,0 +1,176 @@
+use gbemulator::system::printer::{
+    decompress, Printer, COMMAND_DATA, COMMAND_INIT, COMMAND_PRINT, COMMAND_STATUS, PAPER_WIDTH,
+    STATUS_CHECKSUM_ERROR, STATUS_IMAGE_FULL, STATUS_PRINTING, STATUS_UNPROCESSED_DATA,
+};
+use gbemulator::system::ram::RAM;
+
+const SB: u16 = 0xFF01;
+const SC: u16 = 0xFF02;
+
+fn packet(command: u8, compressed: bool, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![
+        command,
+        compressed as u8,
+        data.len() as u8,
+        (data.len() >> 8) as u8,
+    ];
+    body.extend_from_slice(data);
+    let checksum = body
+        .iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+    let mut packet = vec![0x88, 0x33];
+    packet.extend(body);
+    packet.extend([checksum as u8, (checksum >> 8) as u8, 0x00, 0x00]);
+    packet
+}
+
+// sends a packet the way games do and returns the device id and status bytes
+fn send_packet(ram: &mut RAM, packet: &[u8]) -> (u8, u8) {
+    let mut replies = Vec::new();
+    for byte in packet {
+        ram.set_at(SB, *byte).unwrap();
+        ram.set_at(SC, 0x81).unwrap();
+        ram.tick(1024);
+        assert_eq!(ram.get_at(SC), Some(0x7F));
+        replies.push(ram.get_at(SB).unwrap());
+    }
+    let (answers, rest) = replies.split_at(replies.len() - 2);
+    assert!(answers.iter().all(|reply| *reply == 0x00));
+    (rest[0], rest[1])
+}
+
+fn printer_ram(printer: &Printer) -> RAM {
+    let mut ram = RAM::new(None);
+    ram.set_link_cable(printer.clone());
+    ram
+}
+
+// a band where tile n is filled with color n % 4
+fn band() -> Vec<u8> {
+    let mut band = Vec::new();
+    for tile in 0..40 {
+        let (low, high) = match tile % 4 {
+            0 => (0x00, 0x00),
+            1 => (0xFF, 0x00),
+            2 => (0x00, 0xFF),
+            _ => (0xFF, 0xFF),
+        };
+        for _ in 0..8 {
+            band.extend([low, high]);
+        }
+    }
+    band
+}
+
+#[test]
+fn test_printer_decompress() {
+    assert_eq!(decompress(&[0x02, 1, 2, 3]), vec![1, 2, 3]);
+    assert_eq!(decompress(&[0x81, 0xAA]), vec![0xAA; 3]);
+    assert_eq!(
+        decompress(&[0x00, 7, 0x80, 9, 0x01, 4, 5]),
+        vec![7, 9, 9, 4, 5]
+    );
+    assert!(decompress(&[]).is_empty());
+}
+
+#[test]
+fn test_printer_prints_a_band() {
+    let printer = Printer::new();
+    let mut ram = printer_ram(&printer);
+    assert_eq!(
+        send_packet(&mut ram, &packet(COMMAND_INIT, false, &[])),
+        (0x81, 0x00)
+    );
+    assert_eq!(
+        send_packet(&mut ram, &packet(COMMAND_DATA, false, &band())),
+        (0x81, STATUS_UNPROCESSED_DATA)
+    );
+    assert_eq!(
+        send_packet(&mut ram, &packet(COMMAND_DATA, false, &[])),
+        (0x81, STATUS_UNPROCESSED_DATA | STATUS_IMAGE_FULL)
+    );
+    assert_eq!(printer.get_paper_height(), 0);
+
+    // one sheet, no margins, inverted palette
+    let (_, status) = send_packet(
+        &mut ram,
+        &packet(COMMAND_PRINT, false, &[1, 0x00, 0x1B, 0x40]),
+    );
+    assert_eq!(status, STATUS_PRINTING);
+    assert_eq!(printer.get_paper_height(), 16);
+    let paper = printer.get_paper();
+    assert_eq!(paper[0], 0);
+    assert_eq!(paper[8], 64);
+    assert_eq!(paper[16], 192);
+    assert_eq!(paper[24], 255);
+    // the second row of tiles starts at tile 20, color 0
+    assert_eq!(paper[8 * PAPER_WIDTH + 7], 0);
+    assert_eq!(paper[15 * PAPER_WIDTH + 159], 255);
+
+    let mut statuses = Vec::new();
+    for _ in 0..5 {
+        statuses.push(send_packet(&mut ram, &packet(COMMAND_STATUS, false, &[])).1);
+    }
+    assert_eq!(
+        statuses,
+        vec![STATUS_PRINTING, STATUS_PRINTING, STATUS_PRINTING, 0, 0]
+    );
+}
+
+#[test]
+fn test_printer_checksum_error() {
+    let printer = Printer::new();
+    let mut ram = printer_ram(&printer);
+    let mut bad_packet = packet(COMMAND_DATA, false, &band());
+    let checksum_index = bad_packet.len() - 4;
+    bad_packet[checksum_index] ^= 0x01;
+    assert_eq!(
+        send_packet(&mut ram, &bad_packet),
+        (0x81, STATUS_CHECKSUM_ERROR)
+    );
+    send_packet(
+        &mut ram,
+        &packet(COMMAND_PRINT, false, &[1, 0x00, 0xE4, 0x40]),
+    );
+    assert_eq!(printer.get_paper_height(), 0);
+
+    // stray bytes before the magic are ignored
+    ram.set_at(SB, 0x12).unwrap();
+    ram.set_at(SC, 0x81).unwrap();
+    ram.tick(1024);
+    assert_eq!(
+        send_packet(&mut ram, &packet(COMMAND_INIT, false, &[])),
+        (0x81, 0x00)
+    );
+}
+
+#[test]
+fn test_printer_compressed_data_to_image() {
+    let path = std::env::temp_dir().join("gbemulator_printer.pgm");
+    let printer = Printer::new().with_image_path(path.to_str().unwrap());
+    let mut ram = printer_ram(&printer);
+    send_packet(&mut ram, &packet(COMMAND_INIT, false, &[]));
+    // 640 bytes of 0xFF, every pixel color 3, in runs of 128
+    let compressed = [0xFE, 0xFF].repeat(5);
+    send_packet(&mut ram, &packet(COMMAND_DATA, true, &compressed));
+    send_packet(&mut ram, &packet(COMMAND_DATA, false, &[]));
+    send_packet(
+        &mut ram,
+        &packet(COMMAND_PRINT, false, &[1, 0x00, 0xE4, 0x40]),
+    );
+    // the next print goes under the first one
+    send_packet(&mut ram, &packet(COMMAND_DATA, false, &band()));
+    send_packet(
+        &mut ram,
+        &packet(COMMAND_PRINT, false, &[1, 0x00, 0xE4, 0x40]),
+    );
+
+    let contents = std::fs::read(&path).unwrap();
+    let header = b"P5\n160 32\n255\n";
+    assert_eq!(&contents[..header.len()], header);
+    let pixels = &contents[header.len()..];
+    assert_eq!(pixels.len(), 160 * 32);
+    assert!(pixels[..160 * 16].iter().all(|pixel| *pixel == 0));
+    assert_eq!(pixels[160 * 16..], printer.get_paper()[160 * 16..]);
+    std::fs::remove_file(path).unwrap();
+}
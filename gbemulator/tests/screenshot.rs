@@ -0,0 +1,163 @@
+mod common;
+
+use gbemulator::system::ram::mapping_chip::NINTENDO_LOGO;
+use gbemulator::system::screenshot::{
+    check_screenshot, compare_with_reference, load_png, run_rom_frames, save_png, SCREEN_HEIGHT,
+    SCREEN_WIDTH,
+};
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(name)
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+// the 4 shades in bands of 36 lines
+fn bands() -> Vec<u8> {
+    let shades = [255, 192, 64, 0];
+    (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+        .map(|i| shades[i / SCREEN_WIDTH / 36])
+        .collect()
+}
+
+#[test]
+fn test_screenshot_png_round_trip() {
+    let path = temp_path("gbemulator_screenshot_round_trip.png");
+    save_png(&path, &bands()).unwrap();
+    assert_eq!(load_png(&path).unwrap(), bands());
+    std::fs::remove_file(&path).unwrap();
+    assert!(load_png(&path).unwrap_err().contains("unable to open"));
+}
+
+#[test]
+fn test_screenshot_compares_shades() {
+    let reference_path = temp_path("gbemulator_screenshot_reference.png");
+    let diff_path = temp_path("gbemulator_screenshot_reference.diff.png");
+    // the reference was taken with another palette
+    let reference: Vec<u8> = bands()
+        .iter()
+        .map(|grey| match grey {
+            192 => 170,
+            64 => 85,
+            grey => *grey,
+        })
+        .collect();
+    save_png(&reference_path, &reference).unwrap();
+    assert_eq!(
+        compare_with_reference(&bands(), &reference_path, &diff_path),
+        Ok(())
+    );
+    assert!(!std::path::Path::new(&diff_path).exists());
+
+    let mut frame = bands();
+    frame[SCREEN_WIDTH * 40 + 3] = 255;
+    frame[SCREEN_WIDTH * 100] = 255;
+    assert_eq!(
+        compare_with_reference(&frame, &reference_path, &diff_path),
+        Err(format!(
+            "2 pixels differ from {}, the first at (3, 40) is shade 0 instead of 1, see {}",
+            reference_path, diff_path
+        ))
+    );
+    let diff = std::fs::read(&diff_path).unwrap();
+    let decoder = png::Decoder::new(diff.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    let pixel = |x: usize, y: usize| {
+        let i = (y * SCREEN_WIDTH + x) * 3;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    };
+    assert_eq!(pixel(3, 40), [255, 0, 0]);
+    assert_eq!(pixel(0, 100), [255, 0, 0]);
+    assert_eq!(pixel(4, 40), [213, 213, 213]);
+    std::fs::remove_file(&reference_path).unwrap();
+    std::fs::remove_file(&diff_path).unwrap();
+
+    save_png(&reference_path, &reference).unwrap();
+    assert!(compare_with_reference(&frame[1..], &reference_path, &diff_path).is_err());
+    std::fs::remove_file(&reference_path).unwrap();
+}
+
+// Draws the logo of the cartridge header where the boot ROM leaves it, with the boot ROM's own
+// routine doubling every pixel of the header bitmap into tiles 1 to 24. The ® is left out.
+fn make_logo_rom() -> Vec<u8> {
+    let mut rom = common::make_rom(&[0xC3, 0x50, 0x01]); // JP 0x0150
+    rom[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+    let program = [
+        0xAF, // XOR A
+        0xE0, 0x40, // LDH (0x40),A  switches the LCD off
+        0x11, 0x04, 0x01, // LD DE,0x0104
+        0x21, 0x10, 0x80, // LD HL,0x8010
+        0x1A, // LD A,(DE)
+        0xCD, 0x80, 0x01, // CALL 0x0180  the high nibble
+        0xCD, 0x81, 0x01, // CALL 0x0181  the low nibble
+        0x13, // INC DE
+        0x7B, // LD A,E
+        0xFE, 0x34, // CP 0x34
+        0x20, 0xF3, // JR NZ,-13  until the end of the logo
+        0x3E, 0x19, // LD A,0x19
+        0x21, 0x2F, 0x99, // LD HL,0x992F
+        0x0E, 0x0C, // LD C,0x0C
+        0x3D, // DEC A
+        0x28, 0x08, // JR Z,+8  once tile 1 is placed
+        0x32, // LD (HL-),A
+        0x0D, // DEC C
+        0x20, 0xF9, // JR NZ,-7
+        0x2E, 0x0F, // LD L,0x0F  the row above
+        0x18, 0xF3, // JR -13
+        0x3E, 0x91, // LD A,0x91
+        0xE0, 0x40, // LDH (0x40),A  switches the LCD back on
+        0x18, 0xFE, // JR -2
+    ];
+    rom[0x0150..0x0150 + program.len()].copy_from_slice(&program);
+    let double_nibble = [
+        0x4F, // LD C,A
+        0x06, 0x04, // LD B,4
+        0xC5, // PUSH BC
+        0xCB, 0x11, // RL C
+        0x17, // RLA
+        0xC1, // POP BC
+        0xCB, 0x11, // RL C
+        0x17, // RLA
+        0x05, // DEC B
+        0x20, 0xF5, // JR NZ,-11
+        0x22, // LD (HL+),A
+        0x23, // INC HL
+        0x22, // LD (HL+),A
+        0x23, // INC HL
+        0xC9, // RET
+    ];
+    rom[0x0180..0x0180 + double_nibble.len()].copy_from_slice(&double_nibble);
+    rom
+}
+
+#[test]
+fn test_screenshot_of_rom() {
+    let rom_path = common::write_rom("gbemulator_screenshot_logo.gb", &make_logo_rom());
+    let frame = run_rom_frames(&rom_path, 2).unwrap();
+    assert_eq!(frame.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    assert_eq!(run_rom_frames(&rom_path, 2).unwrap(), frame);
+    assert_eq!(
+        check_screenshot(&rom_path, 2, "tests/screenshots/logo.png"),
+        Ok(())
+    );
+    std::fs::remove_file(&rom_path).unwrap();
+}
+
+// dmg-acid2 is not distributed with the emulator, copy dmg-acid2.gb to tests/roms and the
+// reference image from its repository to tests/screenshots to run this one
+#[test]
+#[ignore]
+fn test_screenshot_dmg_acid2() {
+    assert_eq!(
+        check_screenshot(
+            "tests/roms/dmg-acid2.gb",
+            60,
+            "tests/screenshots/dmg-acid2.png"
+        ),
+        Ok(())
+    );
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use gbemulator::system::ram::RAM;
+use gbemulator::system::serial::SERIAL_INTERRUPT;
+use gbemulator::system::sm83::registers::RegisterName;
+use gbemulator::system::sm83::snapshot::SM83Snapshot;
+use gbemulator::system::sm83::SM83;
+use gbemulator::system::test_rom::{result_from_serial_output, run_test_rom, TestRomResult};
+use gbemulator::system::M_CYCLES_PER_FRAME;
+
+const SB: u16 = 0xFF01;
+const SC: u16 = 0xFF02;
+const IF: u16 = 0xFF0F;
+const IE: u16 = 0xFFFF;
+
+#[test]
+fn test_serial_internal_clock_transfer() {
+    let mut ram = RAM::new(None);
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_at(SB, 0x5A).unwrap();
+    assert_eq!(ram.get_at(SC), Some(0x7E));
+    ram.set_at(SC, 0x81).unwrap();
+    assert_eq!(ram.get_at(SC), Some(0xFF));
+
+    // one bit every 128 M-cycles, the disconnected line shifts ones in
+    ram.tick(128);
+    assert_eq!(ram.get_at(SB), Some(0xB5));
+    ram.tick(128 * 6 + 127);
+    assert_eq!(ram.get_at(SB), Some(0x7F));
+    assert_eq!(ram.get_at(SC), Some(0xFF));
+    assert_eq!(ram.get_at(IF), Some(0x00));
+
+    ram.tick(1);
+    assert_eq!(ram.get_at(SB), Some(0xFF));
+    assert_eq!(ram.get_at(SC), Some(0x7F));
+    assert_eq!(ram.get_at(IF), Some(SERIAL_INTERRUPT));
+
+    // nothing happens until the next transfer is started
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_at(SB, 0x00).unwrap();
+    ram.tick(2048);
+    assert_eq!(ram.get_at(SB), Some(0x00));
+    assert_eq!(ram.get_at(IF), Some(0x00));
+}
+
+#[test]
+fn test_serial_external_clock_without_partner() {
+    let mut ram = RAM::new(None);
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_at(SB, 0x42).unwrap();
+    ram.set_at(SC, 0x80).unwrap();
+    ram.tick(1_000_000);
+    assert_eq!(ram.get_at(SB), Some(0x42));
+    assert_eq!(ram.get_at(SC), Some(0xFE));
+    assert_eq!(ram.get_at(IF), Some(0x00));
+
+    // switching to the internal clock restarts the transfer
+    ram.set_at(SC, 0x81).unwrap();
+    ram.tick(1024);
+    assert_eq!(ram.get_at(SC), Some(0x7F));
+    assert_eq!(ram.get_at(IF), Some(SERIAL_INTERRUPT));
+}
+
+#[test]
+fn test_serial_interrupt_dispatch() {
+    let mut ram = RAM::new(None);
+    ram.set_at(0x2000, 0x00).unwrap(); // NOP
+    ram.set_at(0x0058, 0x3C).unwrap(); // INC A
+    ram.set_at(IE, SERIAL_INTERRUPT).unwrap();
+    ram.set_at(IF, 0x00).unwrap();
+    ram.set_at(SC, 0x81).unwrap();
+    ram.tick(1024);
+
+    let snapshot = SM83Snapshot::new()
+        .with_pc(0x2000)
+        .with_sp(0xD000)
+        .with_ime(true);
+    let mut cpu = SM83::new();
+    cpu.load_snapshot(snapshot);
+    cpu.next(&mut ram);
+
+    assert_eq!(cpu.get_register(RegisterName::PC), 0x0059);
+    assert_eq!(cpu.get_register(RegisterName::IR), 0x3C);
+    assert_eq!(ram.get_at(0xCFFF), Some(0x20));
+    assert_eq!(ram.get_at(0xCFFE), Some(0x00));
+    assert_eq!(ram.get_at(IF), Some(0x00));
+}
+
+#[test]
+fn test_serial_output_capture() {
+    let mut ram = RAM::new(None);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let callback_received = received.clone();
+    ram.set_serial_callback(Arc::new(move |byte| {
+        callback_received.lock().unwrap().push(byte)
+    }));
+    for byte in b"ok\n" {
+        ram.set_at(SB, *byte).unwrap();
+        ram.set_at(SC, 0x81).unwrap();
+        ram.tick(1024);
+    }
+    // the external clock does not send anything by itself
+    ram.set_at(SB, b'x').unwrap();
+    ram.set_at(SC, 0x80).unwrap();
+
+    assert_eq!(ram.get_serial_output(), b"ok\n");
+    assert_eq!(*received.lock().unwrap(), b"ok\n".to_vec());
+    assert_eq!(ram.clone().get_serial_output(), b"ok\n");
+}
+
+#[test]
+fn test_serial_verdict_from_output() {
+    assert_eq!(result_from_serial_output("cpu_instrs\n\n"), None);
+    assert_eq!(
+        result_from_serial_output("cpu_instrs\n\nPassed all tests\n"),
+        Some(TestRomResult::Passed)
+    );
+    assert_eq!(
+        result_from_serial_output("01:ok  02:01  \n\nFailed 1 tests.\n"),
+        Some(TestRomResult::Failed)
+    );
+    assert_eq!(
+        result_from_serial_output("01:Passed\n02:Failed\n"),
+        Some(TestRomResult::Failed)
+    );
+}
+
+// a ROM without a mapping chip running the given program from 0x0150
+fn write_test_rom(name: &str, program: &[u8]) -> String {
+    let mut rom = common::make_rom(&[0xC3, 0x50, 0x01]); // JP 0x0150
+    rom[0x0150..0x0150 + program.len()].copy_from_slice(program);
+    common::write_rom(name, &rom)
+}
+
+#[test]
+fn test_run_test_rom_verdicts() {
+    let passed = write_test_rom(
+        "gbemulator_serial_passed.gb",
+        &[
+            0x21, 0x67, 0x01, // LD HL,0x0167  the message after the program
+            0x2A, // LD A,(HL+)
+            0xB7, // OR A
+            0x28, 0x0E, // JR Z,+14  the message ends with a zero
+            0xE0, 0x01, // LDH (0x01),A
+            0x3E, 0x81, // LD A,0x81
+            0xE0, 0x02, // LDH (0x02),A  starts the transfer
+            0xF0, 0x02, // LDH A,(0x02)
+            0xCB, 0x7F, // BIT 7,A
+            0x20, 0xFA, // JR NZ,-6  until the byte is out
+            0x18, 0xEE, // JR -18  to the next byte
+            0x18, 0xFE, // JR -2
+            b'P', b'a', b's', b's', b'e', b'd', 0x00,
+        ],
+    );
+    let verdict = run_test_rom(&passed, M_CYCLES_PER_FRAME * 10).unwrap();
+    assert_eq!(verdict.result, TestRomResult::Passed);
+    assert_eq!(verdict.output, "Passed");
+    assert!(verdict.is_passed());
+    std::fs::remove_file(passed).unwrap();
+
+    let silent = write_test_rom("gbemulator_serial_silent.gb", &[0x18, 0xFE]); // JR -2
+    let verdict = run_test_rom(&silent, M_CYCLES_PER_FRAME * 2).unwrap();
+    assert_eq!(verdict.result, TestRomResult::Timeout);
+    assert_eq!(verdict.output, "");
+    assert!(verdict.cycles >= M_CYCLES_PER_FRAME * 2);
+    std::fs::remove_file(silent).unwrap();
+}
+
+#[test]
+fn test_run_test_rom_reports_a_panic() {
+    // the timer interrupt is not dispatched yet, the emulator panics when it is requested
+    let rom_path = write_test_rom(
+        "gbemulator_serial_timer.gb",
+        &[
+            0x3E, 0x04, // LD A,0x04
+            0xE0, 0x0F, // LDH (0x0F),A  requests the timer interrupt
+            0xE0, 0xFF, // LDH (0xFF),A  and enables it
+            0xFB, // EI
+            0x18, 0xFE, // JR -2
+        ],
+    );
+    let error = run_test_rom(&rom_path, M_CYCLES_PER_FRAME * 2).unwrap_err();
+    assert!(error.contains("panicked: unhandled TIMER interrupt"));
+    std::fs::remove_file(rom_path).unwrap();
+}
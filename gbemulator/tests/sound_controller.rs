@@ -1,58 +1,51 @@
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
 
-use gbemulator::system::controllers::sound_controller::{ToneNSweep, WhiteNoise};
-use rodio::source::Source;
-use rodio::{OutputStream, Sink};
+use gbemulator::system::apu::APU_CLOCK_RATE;
+use gbemulator::system::audio_sink::SampleRecorder;
+use gbemulator::system::controllers::sound_controller::SoundController;
+use gbemulator::system::ram::RAM;
 
 #[test]
-fn test_sound_1() {
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let sound1048Hz = ToneNSweep::new(
-        Duration::ZERO,
-        true,
-        0,
-        2,
-        None,
-        15.0f32,
-        false,
-        3,
-        1048,
-        true,
-    );
-    sink.append(sound1048Hz.amplify(7 as f32));
-    std::thread::sleep(std::time::Duration::from_millis(1000));
-    sink.clear();
-    let sound2080Hz = ToneNSweep::new(
-        Duration::ZERO,
-        true,
-        0,
-        2,
-        None,
-        15.0f32,
-        false,
-        3,
-        2080,
-        true,
-    );
-    sink.append(sound2080Hz.amplify(7 as f32));
-    sink.play();
-    std::thread::sleep(std::time::Duration::from_millis(1000));
-    sink.clear();
-}
+fn test_sound_controller_moves_the_samples_to_the_sink() {
+    let mut ram = RAM::new(None);
+    let recorder = SampleRecorder::new(24000);
+    let mut sound_controller = SoundController::new(Box::new(recorder.clone()));
+    sound_controller.attach(&mut ram);
+    assert_eq!(ram.get_apu().get_sample_rate(), Some(24000));
+    // sound on, full volume on both outputs, channel 2 on both
+    ram.set_at(0xFF26, 0x80);
+    ram.set_at(0xFF24, 0x77);
+    ram.set_at(0xFF25, 0x22);
+    // 50% duty, volume 15, 131072 / (2048 - 0x383) = 114 Hz, triggered
+    ram.set_at(0xFF16, 0x80);
+    ram.set_at(0xFF17, 0xF0);
+    ram.set_at(0xFF18, 0x83);
+    ram.set_at(0xFF19, 0x83);
+    let ram = Arc::new(Mutex::new(ram));
 
-#[test]
-fn test_white_noise() {
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let white_noise = WhiteNoise::new(None, 15.0f32, false, 3, false, 2080.0f32, true);
-    sink.append(white_noise.amplify(7 as f32));
-    sink.play();
-    std::thread::sleep(std::time::Duration::from_millis(2000));
-    sink.clear();
-    std::thread::sleep(std::time::Duration::from_millis(1000));
-    let white_noise = WhiteNoise::new(None, 15.0f32, false, 3, true, 2080.0f32, true);
-    sink.append(white_noise.amplify(7 as f32));
-    sink.play();
-    std::thread::sleep(std::time::Duration::from_millis(2000));
+    // half a second, drained every millisecond
+    for _ in 0..500 {
+        ram.lock().unwrap().tick(APU_CLOCK_RATE as u64 / 1000);
+        sound_controller.next(&ram);
+    }
+    sound_controller.close_audio_sink();
+
+    let samples = recorder.get_samples();
+    // 0.5 s at 24 kHz, in stereo
+    assert!((23900..=24000).contains(&samples.len()));
+    let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+    let right: Vec<f32> = samples.iter().skip(1).step_by(2).copied().collect();
+    assert!(left == right);
+    // about 57 periods of the tone, the edges ring so the level has to move well across 0
+    let mut high = false;
+    let mut periods = 0;
+    for sample in left {
+        if !high && sample > 0.1 {
+            high = true;
+            periods += 1;
+        } else if high && sample < -0.1 {
+            high = false;
+        }
+    }
+    assert!((56..=58).contains(&periods));
 }
@@ -1,3 +1,6 @@
+mod common;
+
+use gbemulator::system::sm83::registers::RegisterName;
 use gbemulator::system::System;
 
 fn test_next() {
@@ -7,3 +10,32 @@ fn test_next() {
     system.next();
     assert_eq!(system.cycle_count(), 2);
 }
+
+#[test]
+fn test_load_state_goes_back_to_the_saved_state() {
+    // INC A, LD (0xC000),A, JR back to the INC
+    let rom = common::make_rom(&[0x3C, 0xEA, 0x00, 0xC0, 0x18, 0xFA]);
+    let rom_path = common::write_rom("gbemulator_save_state_counter.gb", &rom);
+    let mut system = common::boot_headless(&rom_path);
+    assert!(!system.load_state());
+    for _ in 0..30 {
+        system.step_instruction();
+    }
+    system.save_state();
+    let saved_counter = system.get_ram().get_at(0xC000);
+    let saved_pc = system.get_register(RegisterName::PC);
+    for _ in 0..60 {
+        system.step_instruction();
+    }
+    let later_counter = system.get_ram().get_at(0xC000);
+    assert_ne!(later_counter, saved_counter);
+
+    assert!(system.load_state());
+    assert_eq!(system.get_ram().get_at(0xC000), saved_counter);
+    assert_eq!(system.get_register(RegisterName::PC), saved_pc);
+    for _ in 0..60 {
+        system.step_instruction();
+    }
+    assert_eq!(system.get_ram().get_at(0xC000), later_counter);
+    std::fs::remove_file(&rom_path).unwrap();
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use gbemulator::system::audio_sink::SampleRecorder;
+use gbemulator::system::boot::GameBoyModel;
+use gbemulator::system::framebuffer::{
+    Framebuffer, FramebufferFormat, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+use gbemulator::system::ram::mapping_chip::DynamicMappingChip;
+use gbemulator::system::screenshot::load_png;
+use gbemulator::system::video_sink::{FrameRing, PngSequenceSink, VideoSink};
+use gbemulator::system::System;
+use std::sync::{Arc, Mutex};
+
+// keeps the frame numbers it was given
+struct CountingSink {
+    frames: Arc<Mutex<Vec<u64>>>,
+}
+
+impl VideoSink for CountingSink {
+    fn present(&mut self, frame: &Framebuffer) {
+        assert_eq!(frame.format, FramebufferFormat::Grey8);
+        self.frames.lock().unwrap().push(frame.frame);
+    }
+}
+
+#[test]
+fn test_video_sink_receives_frames() {
+    let rom_path = common::write_rom("gbemulator_video_sink_nop.gb", &common::make_rom(&[]));
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let mut system = common::boot_headless(&rom_path).with_video_sink(CountingSink {
+        frames: frames.clone(),
+    });
+    for _ in 0..5 {
+        system.step_frame();
+    }
+    let frame_count = system.frame_count();
+    assert!(frame_count > 0);
+    assert_eq!(
+        *frames.lock().unwrap(),
+        (1..=frame_count).collect::<Vec<u64>>()
+    );
+    std::fs::remove_file(&rom_path).unwrap();
+}
+
+#[test]
+fn test_given_sinks_replace_the_window_and_the_audio_device() {
+    let rom_path = common::write_rom("gbemulator_given_sinks_nop.gb", &common::make_rom(&[]));
+    let chip = DynamicMappingChip::from_rom_path(&rom_path).unwrap();
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let recorder = SampleRecorder::new(48000);
+    let mut system = System::with_sinks(
+        Some(chip),
+        Some(Box::new(CountingSink {
+            frames: frames.clone(),
+        })),
+        Some(Box::new(recorder.clone())),
+    )
+    .with_skip_boot(GameBoyModel::DMG);
+    system.boot();
+    for _ in 0..5 {
+        system.step_frame();
+    }
+    assert_eq!(frames.lock().unwrap().len() as u64, system.frame_count());
+    assert!(!recorder.get_samples().is_empty());
+    std::fs::remove_file(&rom_path).unwrap();
+}
+
+fn frame(number: u64) -> Framebuffer {
+    Framebuffer::from_grey(&[number as u8; 4], FramebufferFormat::Grey8, number)
+}
+
+#[test]
+fn test_frame_ring_keeps_the_last_frames() {
+    let ring = FrameRing::new(3);
+    let mut sink = ring.clone();
+    assert!(ring.is_empty());
+    assert_eq!(ring.last_frame(), None);
+    for number in 1..=5 {
+        sink.present(&frame(number));
+    }
+    assert_eq!(ring.len(), 3);
+    let numbers: Vec<u64> = ring.get_frames().iter().map(|frame| frame.frame).collect();
+    assert_eq!(numbers, vec![3, 4, 5]);
+    assert_eq!(ring.last_frame(), Some(frame(5)));
+}
+
+#[test]
+fn test_png_sequence_sink() {
+    let rom_path = common::write_rom("gbemulator_png_sequence_nop.gb", &common::make_rom(&[]));
+    let directory = std::env::temp_dir().join("gbemulator_png_sequence");
+    let _ = std::fs::remove_dir_all(&directory);
+    let sink = PngSequenceSink::new(directory.to_str().unwrap()).unwrap();
+    let first_path = sink.frame_path(1);
+    let mut system = common::boot_headless(&rom_path).with_video_sink(sink);
+    for _ in 0..3 {
+        system.step_frame();
+    }
+    let files = std::fs::read_dir(&directory).unwrap().count();
+    assert_eq!(files as u64, system.frame_count());
+    assert!(first_path.ends_with("frame_000001.png"));
+    let first = load_png(first_path.to_str().unwrap()).unwrap();
+    assert_eq!(first.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    std::fs::remove_dir_all(&directory).unwrap();
+    std::fs::remove_file(&rom_path).unwrap();
+}